@@ -0,0 +1,323 @@
+//! Where the store is kept: a JSON file (the default) or an SQLite database.
+//!
+//! A backend is a storage format, not a query engine. The `Store` methods (starting, ending,
+//! filtering and the rest) work on the whole store in memory whichever backend is used, and a
+//! backend only loads it, saves it and takes the text snapshots which `wtt undo` puts back.
+//! So the commands are backend-agnostic, and the undo, the batches and the dry runs work the
+//! same on both. The JSON file is rewritten as a whole, while SQLite writes only the sessions
+//! which changed, in one transaction, so a crash can't leave a half-written database.
+//!
+//! Running the operations as SQL queries is not done: every command loads the whole store
+//! either way, so SQLite doesn't make reading a long history faster.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+};
+
+use rusqlite::{Connection, OptionalExtension, params};
+
+use crate::{
+    Result, config,
+    store::{Session, Store, get_path_to_store_file, write_file_atomically},
+};
+
+pub trait Backend {
+    /// Load the store, or an empty one if nothing was saved yet.
+    fn load(&self) -> Result<Store>;
+    fn save(&self, store: &Store) -> Result<()>;
+    /// The saved data as text, or None if nothing was saved yet.
+    fn snapshot(&self) -> Result<Option<String>>;
+    /// Put the data of a snapshot back. None removes the data.
+    fn restore(&self, snapshot: Option<&str>) -> Result<()>;
+}
+
+/// The backend chosen by `storage.backend`.
+pub fn current() -> Box<dyn Backend> {
+    let path = get_path_to_store_file();
+    match config::get().backend.value {
+        config::Backend::Json => Box::new(JsonFile { path }),
+        config::Backend::Sqlite => Box::new(Sqlite { path }),
+    }
+}
+
+pub struct JsonFile {
+    pub path: String,
+}
+
+impl Backend for JsonFile {
+    fn load(&self) -> Result<Store> {
+        match self.snapshot()? {
+            Some(content) => Store::from_json_str(&content, &self.path),
+            None => {
+                log::debug!(
+                    "The database file {} doesn't exist yet. Starting with an empty store.",
+                    self.path
+                );
+                Ok(Store::default())
+            }
+        }
+    }
+
+    fn save(&self, store: &Store) -> Result<()> {
+        let store_json = serde_json::to_string(store)
+            .map_err(|x| format!("Could not create a JSON string from the store. {x}"))?;
+        // A crash while writing leaves only the temporary file broken, never the database.
+        write_file_atomically(&self.path, &store_json).map_err(|x| {
+            format!(
+                "Could not dump the JSON string into the database file {}. {}",
+                &self.path, x
+            )
+        })?;
+        Ok(())
+    }
+
+    fn snapshot(&self) -> Result<Option<String>> {
+        let file_exists = fs::exists(&self.path)
+            .map_err(|x| format!("Could not check the database file {}. {}", &self.path, x))?;
+        if !file_exists {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&self.path)
+            .map_err(|x| format!("Could not open the database file {}. {}", &self.path, x))?;
+        Ok(Some(content))
+    }
+
+    fn restore(&self, snapshot: Option<&str>) -> Result<()> {
+        match snapshot {
+            Some(content) => write_file_atomically(&self.path, content)
+                .map_err(|x| format!("Could not write the database file {}. {}", &self.path, x))?,
+            None => fs::remove_file(&self.path)
+                .map_err(|x| format!("Could not remove the database file {}. {}", &self.path, x))?,
+        }
+        Ok(())
+    }
+}
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS sessions (
+    id TEXT PRIMARY KEY NOT NULL,
+    seq INTEGER NOT NULL,
+    start_at INTEGER NOT NULL,
+    end_at INTEGER,
+    note TEXT,
+    labels TEXT NOT NULL,
+    rate REAL,
+    kind TEXT,
+    locked INTEGER NOT NULL DEFAULT 0,
+    git TEXT,
+    external_ids TEXT NOT NULL DEFAULT '{}',
+    breaks TEXT NOT NULL DEFAULT '[]',
+    extra TEXT NOT NULL DEFAULT '{}'
+);
+CREATE TABLE IF NOT EXISTS meta (
+    key TEXT PRIMARY KEY NOT NULL,
+    value TEXT NOT NULL
+);
+";
+
+/// The sessions in a `sessions` table with a column per field of `Session`. The lists and maps
+/// are JSON text. The fields of the store itself are in the `meta` table.
+pub struct Sqlite {
+    pub path: String,
+}
+
+impl Sqlite {
+    fn open(&self) -> Result<Connection> {
+        let connection = Connection::open(&self.path)
+            .map_err(|x| format!("Could not open the database {}. {}", &self.path, x))?;
+        connection
+            .execute_batch(SCHEMA)
+            .map_err(|x| format!("Could not prepare the database {}. {}", &self.path, x))?;
+        Ok(connection)
+    }
+
+    fn exists(&self) -> Result<bool> {
+        Ok(fs::exists(&self.path)
+            .map_err(|x| format!("Could not check the database file {}. {}", &self.path, x))?)
+    }
+}
+
+impl Backend for Sqlite {
+    fn load(&self) -> Result<Store> {
+        if !self.exists()? {
+            log::debug!(
+                "The database {} doesn't exist yet. Starting with an empty store.",
+                self.path
+            );
+            return Ok(Store::default());
+        }
+        let connection = self.open()?;
+        let last_seq = read_meta(&connection, "last_seq")?;
+        let extra = read_meta(&connection, "extra")?;
+        let mut store = Store {
+            sessions: read_sessions(&connection)?,
+            last_seq: last_seq.map(|x| x.parse()).transpose()?.unwrap_or(0),
+            extra: extra
+                .map(|x| from_json(&x))
+                .transpose()?
+                .unwrap_or_default(),
+        };
+        store.assign_missing_seqs();
+        Ok(store)
+    }
+
+    fn save(&self, store: &Store) -> Result<()> {
+        let mut connection = self.open()?;
+        let transaction = connection.transaction()?;
+        let saved: HashMap<String, Session> = read_sessions(&transaction)?
+            .into_iter()
+            .map(|x| (x.id.clone(), x))
+            .collect();
+        let mut written = 0;
+        for session in &store.sessions {
+            if saved.get(&session.id) == Some(session) {
+                continue;
+            }
+            write_session(&transaction, session)?;
+            written += 1;
+        }
+        let ids: HashSet<&str> = store.sessions.iter().map(|x| x.id.as_str()).collect();
+        for id in saved.keys().filter(|x| !ids.contains(x.as_str())) {
+            transaction.execute("DELETE FROM sessions WHERE id = ?1", [id])?;
+        }
+        write_meta(&transaction, "last_seq", &store.last_seq.to_string())?;
+        write_meta(&transaction, "extra", &serde_json::to_string(&store.extra)?)?;
+        transaction
+            .commit()
+            .map_err(|x| format!("Could not save to the database {}. {}", &self.path, x))?;
+        log::debug!("Wrote {written} changed sessions to {}.", self.path);
+        Ok(())
+    }
+
+    /// The store as JSON, in the same form as the JSON backend keeps it.
+    fn snapshot(&self) -> Result<Option<String>> {
+        if !self.exists()? {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::to_string(&self.load()?)?))
+    }
+
+    fn restore(&self, snapshot: Option<&str>) -> Result<()> {
+        match snapshot {
+            Some(content) => self.save(&Store::from_json_str(content, &self.path)?),
+            None => Ok(fs::remove_file(&self.path).map_err(|x| {
+                format!("Could not remove the database file {}. {}", &self.path, x)
+            })?),
+        }
+    }
+}
+
+fn read_sessions(connection: &Connection) -> Result<Vec<Session>> {
+    let mut statement = connection.prepare(
+        "SELECT id, seq, start_at, end_at, note, labels, rate, kind, locked, git, external_ids,
+            breaks, extra
+        FROM sessions ORDER BY rowid",
+    )?;
+    let mut rows = statement.query([])?;
+    let mut sessions: Vec<Session> = vec![];
+    while let Some(row) = rows.next()? {
+        sessions.push(Session {
+            id: row.get(0)?,
+            seq: row.get::<_, i64>(1)? as u64,
+            start_at: row.get(2)?,
+            end_at: row.get(3)?,
+            note: row.get(4)?,
+            labels: from_json(&row.get::<_, String>(5)?)?,
+            rate: row.get(6)?,
+            kind: row.get(7)?,
+            locked: row.get(8)?,
+            git: row
+                .get::<_, Option<String>>(9)?
+                .map(|x| from_json(&x))
+                .transpose()?,
+            external_ids: from_json(&row.get::<_, String>(10)?)?,
+            breaks: from_json(&row.get::<_, String>(11)?)?,
+            extra: from_json(&row.get::<_, String>(12)?)?,
+        });
+    }
+    Ok(sessions)
+}
+
+fn write_session(connection: &Connection, session: &Session) -> Result<()> {
+    connection.execute(
+        "INSERT INTO sessions (id, seq, start_at, end_at, note, labels, rate, kind, locked, git,
+            external_ids, breaks, extra)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
+        ON CONFLICT(id) DO UPDATE SET seq = excluded.seq, start_at = excluded.start_at,
+            end_at = excluded.end_at, note = excluded.note, labels = excluded.labels,
+            rate = excluded.rate, kind = excluded.kind, locked = excluded.locked,
+            git = excluded.git, external_ids = excluded.external_ids, breaks = excluded.breaks,
+            extra = excluded.extra",
+        params![
+            session.id,
+            session.seq as i64,
+            session.start_at,
+            session.end_at,
+            session.note,
+            serde_json::to_string(&session.labels)?,
+            session.rate,
+            session.kind,
+            session.locked,
+            session
+                .git
+                .as_ref()
+                .map(serde_json::to_string)
+                .transpose()?,
+            serde_json::to_string(&session.external_ids)?,
+            serde_json::to_string(&session.breaks)?,
+            serde_json::to_string(&session.extra)?,
+        ],
+    )?;
+    Ok(())
+}
+
+fn read_meta(connection: &Connection, key: &str) -> Result<Option<String>> {
+    Ok(connection
+        .query_row("SELECT value FROM meta WHERE key = ?1", [key], |row| {
+            row.get(0)
+        })
+        .optional()?)
+}
+
+fn write_meta(connection: &Connection, key: &str, value: &str) -> Result<()> {
+    connection.execute(
+        "INSERT INTO meta (key, value) VALUES (?1, ?2)
+        ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+        [key, value],
+    )?;
+    Ok(())
+}
+
+fn from_json<T: serde::de::DeserializeOwned>(text: &str) -> Result<T> {
+    serde_json::from_str(text)
+        .map_err(|x| format!("Could not parse a value of the database as JSON. {x}").into())
+}
+
+/// Copy the sessions of a JSON database file into the SQLite database of `storage.path`.
+/// Sessions there already are replaced only with `force`. Returns how many were copied.
+pub fn migrate_json(from: &str, force: bool) -> Result<usize> {
+    if config::get().backend.value != config::Backend::Sqlite {
+        return Err("Set storage.backend to sqlite (or WTT_BACKEND=sqlite) first.".into());
+    }
+    let source = JsonFile {
+        path: from.to_string(),
+    };
+    if source.snapshot()?.is_none() {
+        return Err(format!("There is no JSON database at {from}.").into());
+    }
+    let store = source.load()?;
+    let target = Sqlite {
+        path: get_path_to_store_file(),
+    };
+    let existing = target.load()?.sessions.len();
+    if existing > 0 && !force {
+        return Err(format!(
+            "The database {} has {existing} sessions already. Use --force to replace them.",
+            target.path
+        )
+        .into());
+    }
+    target.save(&store)?;
+    Ok(store.sessions.len())
+}
@@ -0,0 +1,114 @@
+//! Running many commands against one store, for scripts and fixtures.
+//!
+//! The store is loaded once, every line changes it in memory, and it's saved once at the end.
+//! That's much faster than starting the app for every command. A line which fails leaves no
+//! changes behind, because a command hands its store over only when it saves.
+
+use std::{
+    fs,
+    io::Read,
+    panic::{self, AssertUnwindSafe},
+};
+
+use clap::Parser;
+use wtt::Result;
+
+use crate::{
+    Cli, MainCommands, handle, handle::StoreHandle, output, requested_format, supported_formats,
+};
+
+/// Run the commands. Fails if any line failed.
+pub fn run(input: &str, keep_going: bool) -> Result<()> {
+    let text = read_input(input)?;
+    let store = StoreHandle::open()?;
+    handle::start_batch((*store).clone());
+
+    let mut failed: Vec<usize> = vec![];
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let number = index + 1;
+        if let Err(x) = run_line(line) {
+            eprintln!("Line {number} failed: {line}\n{x}");
+            failed.push(number);
+            if !keep_going {
+                handle::finish_batch();
+                return Err("Stopped the batch. Nothing was saved.".into());
+            }
+        }
+    }
+
+    let mut store = store;
+    *store = handle::finish_batch().ok_or("The batch store is gone.")?;
+    store.save()?;
+    if failed.is_empty() {
+        return Ok(());
+    }
+    let numbers: Vec<String> = failed.iter().map(|x| x.to_string()).collect();
+    Err(format!(
+        "{} lines failed and were skipped: {}.",
+        failed.len(),
+        numbers.join(", ")
+    )
+    .into())
+}
+
+fn read_input(input: &str) -> Result<String> {
+    let mut text = String::new();
+    if input == "-" {
+        std::io::stdin()
+            .read_to_string(&mut text)
+            .map_err(|x| format!("Could not read the commands from stdin. {x}"))?;
+    } else {
+        text = fs::read_to_string(input)
+            .map_err(|x| format!("Could not read the file {input}. {x}"))?;
+    }
+    Ok(text)
+}
+
+fn run_line(line: &str) -> Result<()> {
+    let words = shlex::split(line).ok_or("The quotes are not closed.")?;
+    let cli = Cli::try_parse_from(std::iter::once("wtt".to_string()).chain(words))
+        .map_err(|x| x.to_string().trim_end().to_string())?;
+    if cli.db.is_some() || cli.dry_run || cli.verbose > 0 {
+        return Err(
+            "--db, --dry-run and -v apply to the whole batch. Pass them to `wtt batch`.".into(),
+        );
+    }
+    if !is_allowed(&cli.command) {
+        return Err("This command can't be run in a batch.".into());
+    }
+    output::set_format(
+        requested_format(cli.format, &cli.command),
+        supported_formats(&cli.command),
+    )?;
+
+    // A panic is a bug, but it shouldn't take the rest of the batch down with it.
+    panic::catch_unwind(AssertUnwindSafe(|| crate::run(cli.command)))
+        .map_err(|_| "The command panicked.")?
+}
+
+/// Commands which work on the database as a whole or need a terminal can't share the batch store.
+fn is_allowed(command: &MainCommands) -> bool {
+    match command {
+        MainCommands::Session(x) => !matches!(
+            x.command,
+            crate::SessionCommands::Current { pomodoro: Some(_) }
+        ),
+        MainCommands::Batch { .. }
+        | MainCommands::Ui
+        | MainCommands::Remind { .. }
+        | MainCommands::WatchIdle { .. }
+        | MainCommands::Pomodoro { .. }
+        | MainCommands::Serve { .. }
+        | MainCommands::Undo { .. }
+        | MainCommands::Doctor
+        | MainCommands::MigrateJson { .. }
+        | MainCommands::Init { .. }
+        | MainCommands::Completions { .. }
+        | MainCommands::Complete { .. } => false,
+        _ => true,
+    }
+}
@@ -0,0 +1,302 @@
+//! Pushing sessions to a CalDAV calendar as events.
+//!
+//! Every ended session is stored at `<calendar>/wtt-<session id>.ics` with the session id in
+//! the UID, so pushing it again replaces the event instead of adding another one, and nothing
+//! has to be remembered in the database. The `wtt-` prefix also tells which events of the
+//! calendar are ours when pruning the ones of deleted sessions.
+
+use std::time::Duration;
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use chrono::{DateTime, Local as LocalTZ};
+use ureq::http;
+use wtt::{
+    Result,
+    report::GroupBy,
+    store::{Filter, Session},
+};
+
+use crate::handle;
+
+const PREFIX: &str = "wtt-";
+/// The longest line of an iCalendar file in bytes, without the line break.
+const LINE_LIMIT: usize = 75;
+
+enum Outcome {
+    Created,
+    Updated,
+    Deleted,
+}
+
+/// Push the ended sessions selected by the filter, and with `prune` delete the events of
+/// the sessions which don't exist anymore. Returns the exit code.
+pub fn push(url: &str, filter: &Filter, prune: bool) -> Result<i32> {
+    let calendar = Calendar::new(url)?;
+    let store = handle::load()?;
+    let sessions: Vec<&Session> = store
+        .report(filter, GroupBy::None)
+        .rows
+        .into_iter()
+        .filter(|x| x.end_at.is_some())
+        .filter_map(|row| store.sessions.iter().find(|x| x.id == row.id))
+        .collect();
+
+    let (mut created, mut updated, mut deleted) = (0, 0, 0);
+    let mut failures = 0;
+    let mut count = |seq: String, outcome: std::result::Result<Outcome, String>| match outcome {
+        Ok(Outcome::Created) => {
+            created += 1;
+            println!("{seq}: created");
+        }
+        Ok(Outcome::Updated) => {
+            updated += 1;
+            println!("{seq}: updated");
+        }
+        Ok(Outcome::Deleted) => {
+            deleted += 1;
+            println!("{seq}: deleted");
+        }
+        Err(x) => {
+            failures += 1;
+            println!("{seq}: failed. {x}");
+        }
+    };
+
+    for session in sessions {
+        let name = format!("{PREFIX}{}.ics", session.id);
+        if handle::is_dry_run() {
+            println!("(dry-run) Would put #{} at {name}", session.seq);
+            continue;
+        }
+        let outcome = calendar.put(&name, &event(session));
+        // An auth failure or an unreachable server stops the push, it would fail for all the rest.
+        if let Some(x) = calendar.fatal.take() {
+            return Err(format!("Stopped at #{}. {x}", session.seq).into());
+        }
+        count(format!("#{}", session.seq), outcome);
+    }
+
+    if prune {
+        for name in calendar.list()? {
+            let Some(id) = name
+                .strip_prefix(PREFIX)
+                .and_then(|x| x.strip_suffix(".ics"))
+            else {
+                continue;
+            };
+            if store.sessions.iter().any(|x| x.id == id) {
+                continue;
+            }
+            if handle::is_dry_run() {
+                println!("(dry-run) Would delete {name}, its session doesn't exist");
+                continue;
+            }
+            let outcome = calendar.delete(&name);
+            if let Some(x) = calendar.fatal.take() {
+                return Err(format!("Stopped at {name}. {x}").into());
+            }
+            count(name, outcome);
+        }
+    }
+
+    if handle::is_dry_run() {
+        return Ok(0);
+    }
+    println!(
+        "Pushed to CalDAV: {created} created, {updated} updated, {deleted} deleted, {failures} failed."
+    );
+    Ok(if failures == 0 { 0 } else { 1 })
+}
+
+struct Calendar {
+    agent: ureq::Agent,
+    url: String,
+    auth: Option<String>,
+    /// Set by a request which failed in a way that dooms the rest of them.
+    fatal: std::cell::Cell<Option<String>>,
+}
+
+impl Calendar {
+    fn new(url: &str) -> Result<Self> {
+        let auth = match (
+            std::env::var("WTT_CALDAV_USER").ok(),
+            std::env::var("WTT_CALDAV_PASSWORD").ok(),
+        ) {
+            (Some(user), Some(password)) => Some(format!(
+                "Basic {}",
+                STANDARD.encode(format!("{user}:{password}"))
+            )),
+            (None, None) => None,
+            _ => {
+                return Err("Set both WTT_CALDAV_USER and WTT_CALDAV_PASSWORD, or neither.".into());
+            }
+        };
+        let agent = ureq::Agent::config_builder()
+            .http_status_as_error(false)
+            .allow_non_standard_methods(true)
+            .timeout_global(Some(Duration::from_secs(30)))
+            .build()
+            .into();
+        Ok(Self {
+            agent,
+            url: url.trim_end_matches('/').to_string(),
+            auth,
+            fatal: Default::default(),
+        })
+    }
+
+    fn put(&self, name: &str, event: &str) -> std::result::Result<Outcome, String> {
+        let request = self
+            .request("PUT", name)
+            .header("Content-Type", "text/calendar; charset=utf-8")
+            .body(event.to_string())
+            .unwrap();
+        match self.send(request)? {
+            (201, _) => Ok(Outcome::Created),
+            (200 | 204, _) => Ok(Outcome::Updated),
+            (status, text) => Err(format!("The server answered {status}. {}", text.trim())),
+        }
+    }
+
+    fn delete(&self, name: &str) -> std::result::Result<Outcome, String> {
+        let request = self.request("DELETE", name).body(String::new()).unwrap();
+        match self.send(request)? {
+            (200 | 204 | 404, _) => Ok(Outcome::Deleted),
+            (status, text) => Err(format!("The server answered {status}. {}", text.trim())),
+        }
+    }
+
+    /// The file names of the events in the calendar.
+    fn list(&self) -> Result<Vec<String>> {
+        let body = concat!(
+            r#"<?xml version="1.0" encoding="utf-8"?>"#,
+            r#"<d:propfind xmlns:d="DAV:"><d:prop><d:getetag/></d:prop></d:propfind>"#,
+        );
+        let request = self
+            .request("PROPFIND", "")
+            .header("Depth", "1")
+            .header("Content-Type", "application/xml; charset=utf-8")
+            .body(body.to_string())
+            .unwrap();
+        let (status, text) = self.send(request)?;
+        if status != 207 {
+            return Err(
+                format!("Could not list the calendar, the server answered {status}.").into(),
+            );
+        }
+        Ok(hrefs(&text)
+            .into_iter()
+            .filter_map(|x| {
+                x.trim_end_matches('/')
+                    .rsplit('/')
+                    .next()
+                    .map(str::to_string)
+            })
+            .collect())
+    }
+
+    fn request(&self, method: &str, name: &str) -> http::request::Builder {
+        let uri = match name {
+            "" => format!("{}/", self.url),
+            _ => format!("{}/{name}", self.url),
+        };
+        let mut request = http::Request::builder().method(method).uri(uri);
+        if let Some(auth) = &self.auth {
+            request = request.header("Authorization", auth);
+        }
+        request
+    }
+
+    /// Send the request and return the status with the body. Failures which would repeat for
+    /// every other request are also stored in `fatal`.
+    fn send(&self, request: http::Request<String>) -> std::result::Result<(u16, String), String> {
+        let mut response = match self.agent.run(request) {
+            Ok(x) => x,
+            Err(x) => {
+                let reason = format!("Could not reach the CalDAV server. {x}");
+                self.fatal.set(Some(reason.clone()));
+                return Err(reason);
+            }
+        };
+        let status = response.status().as_u16();
+        if status == 401 || status == 403 {
+            let reason = format!(
+                "The CalDAV server refused the credentials ({status}). \
+                 Check WTT_CALDAV_USER and WTT_CALDAV_PASSWORD."
+            );
+            self.fatal.set(Some(reason.clone()));
+            return Err(reason);
+        }
+        let text = response.body_mut().read_to_string().unwrap_or_default();
+        Ok((status, text))
+    }
+}
+
+/// The contents of all the `href` elements of a WebDAV answer, whatever the namespace prefix.
+fn hrefs(xml: &str) -> Vec<String> {
+    xml.split('<')
+        .filter_map(|x| {
+            let (tag, text) = x.split_once('>')?;
+            let name = tag.split_whitespace().next()?;
+            (name == "href" || name.ends_with(":href")).then(|| text.trim().to_string())
+        })
+        .collect()
+}
+
+fn event(session: &Session) -> String {
+    let summary = match session.labels.is_empty() {
+        true => "wtt".to_string(),
+        false => session.labels.join(", "),
+    };
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//wtt//wtt//EN".to_string(),
+        "BEGIN:VEVENT".to_string(),
+        format!("UID:{}@wtt", session.id),
+        format!("DTSTAMP:{}", format_utc(LocalTZ::now().timestamp())),
+        format!("DTSTART:{}", format_utc(session.start_at)),
+        format!("DTEND:{}", format_utc(session.end_at.unwrap())),
+        format!("SUMMARY:{}", escape(&summary)),
+    ];
+    if !session.labels.is_empty() {
+        let labels: Vec<String> = session.labels.iter().map(|x| escape(x)).collect();
+        lines.push(format!("CATEGORIES:{}", labels.join(",")));
+    }
+    if let Some(note) = session.note.as_deref().filter(|x| !x.is_empty()) {
+        lines.push(format!("DESCRIPTION:{}", escape(note)));
+    }
+    lines.push("END:VEVENT".to_string());
+    lines.push("END:VCALENDAR".to_string());
+    lines.iter().map(|x| fold(x) + "\r\n").collect()
+}
+
+fn format_utc(timestamp: i64) -> String {
+    DateTime::from_timestamp(timestamp, 0)
+        .unwrap()
+        .format("%Y%m%dT%H%M%SZ")
+        .to_string()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Break a long line into lines of at most 75 bytes, the next ones starting with a space.
+fn fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > LINE_LIMIT {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+    folded
+}
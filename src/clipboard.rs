@@ -0,0 +1,58 @@
+//! Copying text to the system clipboard.
+//!
+//! The text is sent to the terminal as an OSC 52 escape sequence, which works over SSH
+//! in terminals that support it, and also piped to a platform clipboard tool when one is installed.
+
+use std::{
+    io::{IsTerminal, Write},
+    process::{Command, Stdio},
+};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+
+/// Platform clipboard tools, tried in order until one works.
+const TOOLS: &[(&str, &[&str])] = &[
+    ("wl-copy", &[]),
+    ("xclip", &["-selection", "clipboard"]),
+    ("xsel", &["--clipboard", "--input"]),
+    ("pbcopy", &[]),
+    ("clip.exe", &[]),
+];
+
+/// Returns whether the text reached a clipboard. It never fails, because copying is only a convenience.
+pub fn copy(text: &str) -> bool {
+    let osc52 = copy_with_osc52(text);
+    let tool = TOOLS
+        .iter()
+        .any(|(name, args)| copy_with_tool(name, args, text));
+    osc52 || tool
+}
+
+fn copy_with_osc52(text: &str) -> bool {
+    let mut stdout = std::io::stdout();
+    if !stdout.is_terminal() {
+        return false;
+    }
+    let sequence = format!("\x1b]52;c;{}\x07", STANDARD.encode(text));
+    stdout
+        .write_all(sequence.as_bytes())
+        .and_then(|_| stdout.flush())
+        .is_ok()
+}
+
+fn copy_with_tool(name: &str, args: &[&str], text: &str) -> bool {
+    let Ok(mut child) = Command::new(name)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+    else {
+        return false;
+    };
+    let written = child
+        .stdin
+        .take()
+        .is_some_and(|mut stdin| stdin.write_all(text.as_bytes()).is_ok());
+    child.wait().is_ok_and(|x| x.success()) && written
+}
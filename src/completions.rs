@@ -0,0 +1,189 @@
+//! Shell completion scripts.
+//!
+//! The scripts are generated by clap_complete from the CLI definition, so every subcommand and flag
+//! is covered automatically. Then the arguments which take a label are wired to `wtt __complete labels`,
+//! so they offer the labels from the store. To get this for a new argument, set its value name to `LABEL`.
+
+use clap::Command;
+use clap_complete::Shell;
+
+/// The value name which marks an argument as taking a label.
+pub const LABEL: &str = "LABEL";
+
+const BIN_NAME: &str = "wtt";
+const LABELS_COMMAND: &str = "wtt __complete labels 2>/dev/null";
+
+/// An argument which takes a label.
+struct LabelArg {
+    /// Subcommands leading to the argument, e.g. ["session", "table"].
+    path: Vec<String>,
+    short: Option<char>,
+    long: Option<String>,
+    /// Set for positional arguments. Starts from 1.
+    index: Option<usize>,
+    id: String,
+}
+
+pub fn generate(shell: Shell, command: &mut Command) -> String {
+    let mut buffer: Vec<u8> = vec![];
+    clap_complete::generate(shell, command, BIN_NAME, &mut buffer);
+    let script = String::from_utf8(buffer).expect("The completion script is not valid UTF-8.");
+
+    let mut label_args: Vec<LabelArg> = vec![];
+    find_label_args(command, &mut vec![], &mut label_args);
+
+    match shell {
+        Shell::Bash => wire_bash(script, &label_args),
+        Shell::Zsh => wire_zsh(script, &label_args),
+        Shell::Fish => wire_fish(script, &label_args),
+        _ => script,
+    }
+}
+
+fn find_label_args(command: &Command, path: &mut Vec<String>, result: &mut Vec<LabelArg>) {
+    for arg in command.get_arguments() {
+        let is_label = arg
+            .get_value_names()
+            .is_some_and(|names| names.iter().any(|x| x == LABEL));
+        if is_label {
+            result.push(LabelArg {
+                path: path.clone(),
+                short: arg.get_short(),
+                long: arg.get_long().map(|x| x.to_string()),
+                index: arg.get_index(),
+                id: arg.get_id().to_string(),
+            });
+        }
+    }
+    for subcommand in command.get_subcommands() {
+        path.push(subcommand.get_name().to_string());
+        find_label_args(subcommand, path, result);
+        path.pop();
+    }
+}
+
+fn wire_bash(script: String, label_args: &[LabelArg]) -> String {
+    let mut conditions: Vec<String> = vec![];
+    for arg in label_args {
+        let path_check = format!(
+            "\"${{COMP_WORDS[*]:1:{}}}\" == \"{}\"",
+            arg.path.len(),
+            arg.path.join(" ")
+        );
+        match arg.index {
+            Some(index) => conditions.push(format!(
+                "{path_check} && ${{COMP_CWORD}} -eq {} && \"${{cur}}\" != -*",
+                arg.path.len() + index
+            )),
+            None => {
+                let flags: Vec<String> = arg
+                    .short
+                    .map(|x| format!("-{x}"))
+                    .into_iter()
+                    .chain(arg.long.as_ref().map(|x| format!("--{x}")))
+                    .map(|x| format!("\"${{prev}}\" == \"{x}\""))
+                    .collect();
+                conditions.push(format!("{path_check} && ( {} )", flags.join(" || ")));
+            }
+        }
+    }
+
+    let mut wrapper = String::from(
+        "\n_wtt_with_labels() {\n    local cur=\"${COMP_WORDS[COMP_CWORD]}\"\n    local prev=\"${COMP_WORDS[COMP_CWORD-1]}\"\n",
+    );
+    for condition in conditions {
+        wrapper.push_str(&format!(
+            "    if [[ {condition} ]]; then\n        COMPREPLY=( $(compgen -W \"$({LABELS_COMMAND})\" -- \"${{cur}}\") )\n        return 0\n    fi\n"
+        ));
+    }
+    wrapper.push_str("    _wtt \"$@\"\n}\n");
+
+    // Register the wrapper instead of the generated function.
+    let script = script.replace("complete -F _wtt ", "complete -F _wtt_with_labels ");
+    match script.find("\nif [[ \"${BASH_VERSINFO[0]}\"") {
+        Some(position) => format!("{}{}{}", &script[..position], wrapper, &script[position..]),
+        None => script + &wrapper,
+    }
+}
+
+fn wire_zsh(script: String, label_args: &[LabelArg]) -> String {
+    let option_marker = format!(":{LABEL}:_default'");
+    let positional_prefixes: Vec<String> = label_args
+        .iter()
+        .filter(|x| x.index.is_some())
+        .map(|x| format!("':{}", x.id))
+        .collect();
+
+    let mut lines: Vec<String> = vec![];
+    for line in script.lines() {
+        let is_label_positional = line.ends_with(":_default' \\")
+            && positional_prefixes.iter().any(|prefix| {
+                line.strip_prefix(prefix.as_str())
+                    .is_some_and(|rest| rest.starts_with(':') || rest.starts_with(" -- "))
+            });
+        if line.contains(&option_marker) || is_label_positional {
+            lines.push(line.replace(":_default'", ":_wtt_labels'"));
+        } else {
+            lines.push(line.to_string());
+        }
+    }
+    let script = lines.join("\n") + "\n";
+
+    let function = format!(
+        "(( $+functions[_wtt_labels] )) ||\n_wtt_labels() {{\n    local -a labels\n    labels=(${{(f)\"$({LABELS_COMMAND})\"}})\n    compadd -a labels\n}}\n\n"
+    );
+    match script.find("if [ \"$funcstack[1]\" = \"_wtt\" ]; then") {
+        Some(position) => format!("{}{}{}", &script[..position], function, &script[position..]),
+        None => script + &function,
+    }
+}
+
+fn wire_fish(mut script: String, label_args: &[LabelArg]) -> String {
+    for arg in label_args {
+        let Some((first, rest)) = arg.path.split_first() else {
+            continue;
+        };
+        let mut condition = format!("__fish_{BIN_NAME}_using_subcommand {first}");
+        for name in rest {
+            condition.push_str(&format!("; and __fish_seen_subcommand_from {name}"));
+        }
+
+        let mut line = format!("complete -c {BIN_NAME} -n \"{condition}\"");
+        if let Some(short) = arg.short {
+            line.push_str(&format!(" -s {short}"));
+        }
+        if let Some(long) = &arg.long {
+            line.push_str(&format!(" -l {long}"));
+        }
+        line.push_str(&format!(" -f -a \"({LABELS_COMMAND})\"\n"));
+        script.push_str(&line);
+    }
+    script
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::{CommandFactory, ValueEnum};
+
+    use super::*;
+
+    #[test]
+    fn every_shell_gets_a_script() {
+        for shell in Shell::value_variants() {
+            let script = generate(*shell, &mut crate::Cli::command());
+            assert!(script.contains("session"), "{shell} has no subcommands");
+            assert!(script.contains("--from"), "{shell} has no flags");
+        }
+    }
+
+    #[test]
+    fn label_arguments_complete_the_stored_labels() {
+        for shell in [Shell::Bash, Shell::Zsh, Shell::Fish] {
+            let script = generate(shell, &mut crate::Cli::command());
+            assert!(
+                script.contains(LABELS_COMMAND),
+                "{shell} doesn't offer labels"
+            );
+        }
+    }
+}
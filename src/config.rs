@@ -0,0 +1,850 @@
+//! Settings of the app.
+//!
+//! Every setting can come from (in the order of precedence) a command-line flag, an environment variable,
+//! the config file or the built-in default. The config file is TOML and lives at
+//! `$XDG_CONFIG_HOME/wtt/config.toml` (`~/.config/wtt/config.toml` when the variable is not set),
+//! unless `WTT_PATH_CONFIG` points somewhere else.
+
+use std::{fmt, fs, ops::Deref, sync::OnceLock};
+
+use chrono::{
+    Weekday,
+    format::{Item, StrftimeItems},
+};
+
+use crate::Result;
+
+/// A setting which can be configured. This table drives the config resolution,
+/// so adding a setting here is enough for it to be read from the env and the config file.
+pub struct Setting {
+    /// A dotted path inside the config file, e.g. "storage.path".
+    pub key: &'static str,
+    pub env: Option<&'static str>,
+    pub default: &'static str,
+    pub description: &'static str,
+}
+
+pub const SETTINGS: &[Setting] = &[
+    Setting {
+        key: "storage.path",
+        env: Some("WTT_PATH_DATABASE"),
+        default: "db.json",
+        description: "Where to store the sessions.",
+    },
+    Setting {
+        key: "storage.backend",
+        env: Some("WTT_BACKEND"),
+        default: "json",
+        description: "How to store the sessions: json, or sqlite in <db>.sqlite3 next to the JSON file.",
+    },
+    Setting {
+        key: "storage.today_file",
+        env: None,
+        default: "false",
+        description: "Whether to keep today's totals in <db>.today.json for `session today --fast`.",
+    },
+    Setting {
+        key: "display.date_format",
+        env: Some("WTT_DATE_FORMAT"),
+        default: "%d.%m.%Y",
+        description: "How to parse and display dates (chrono format).",
+    },
+    Setting {
+        key: "display.datetime_format",
+        env: Some("WTT_DATETIME_FORMAT"),
+        default: "%d.%m.%Y %H:%M",
+        description: "How to parse and display dates with time (chrono format).",
+    },
+    Setting {
+        key: "display.note_width",
+        env: Some("WTT_PPRINT_NOTE_CELL_MAXLENGTH"),
+        default: "40",
+        description: "The maximum width of the note cell in the table.",
+    },
+    Setting {
+        key: "display.colors",
+        env: None,
+        default: "true",
+        description: "Whether to style the output with colors and bold text.",
+    },
+    Setting {
+        key: "display.table_order",
+        env: None,
+        default: "asc",
+        description: "The order of sessions in the table by start time: asc or desc.",
+    },
+    Setting {
+        key: "display.table_columns",
+        env: Some("WTT_TABLE_COLUMNS"),
+        default: "number,start,labels,end,duration,note",
+        description: "The columns of the session table, comma separated. See `session table --help`.",
+    },
+    Setting {
+        key: "behavior.default_labels",
+        env: None,
+        default: "",
+        description: "Labels for new sessions started without labels (comma separated in the env).",
+    },
+    Setting {
+        key: "behavior.kinds",
+        env: None,
+        default: "focus,meeting,break,admin",
+        description: "The allowed kinds of sessions (comma separated in the env).",
+    },
+    Setting {
+        key: "behavior.copy_id_on_start",
+        env: None,
+        default: "false",
+        description: "Whether to copy the id of a new session to the clipboard.",
+    },
+    Setting {
+        key: "behavior.nag_after_hours",
+        env: None,
+        default: "8",
+        description: "Remind about sessions running longer than this many hours. 0 turns it off.",
+    },
+    Setting {
+        key: "behavior.require_note",
+        env: None,
+        default: "false",
+        description: "Whether ending a session requires a note.",
+    },
+    Setting {
+        key: "behavior.week_start",
+        env: None,
+        default: "monday",
+        description: "The first day of the week for `--from week` and the weekly totals.",
+    },
+    Setting {
+        key: "behavior.note_separator",
+        env: None,
+        default: "\\n",
+        description: "What `note --append` puts between the old and the new text. \\n is a line break.",
+    },
+    Setting {
+        key: "webhooks.start_url",
+        env: Some("WTT_WEBHOOK_START_URL"),
+        default: "",
+        description: "Where to POST a JSON payload when a session starts. Empty turns it off.",
+    },
+    Setting {
+        key: "webhooks.end_url",
+        env: Some("WTT_WEBHOOK_END_URL"),
+        default: "",
+        description: "Where to POST a JSON payload when a session ends. Empty turns it off.",
+    },
+    Setting {
+        key: "toggl.workspace_id",
+        env: Some("WTT_TOGGL_WORKSPACE_ID"),
+        default: "",
+        description: "The Toggl Track workspace `wtt push toggl` creates the time entries in.",
+    },
+    Setting {
+        key: "toggl.projects",
+        env: None,
+        default: "",
+        description: "Toggl project ids by label, e.g. { acme = 123 }. The other labels become tags.",
+    },
+    Setting {
+        key: "timesheet.projects",
+        env: None,
+        default: "",
+        description: "Where `wtt export timesheet` puts the sessions, by label: \"Client/Project/Task\".",
+    },
+    Setting {
+        key: "timesheet.email",
+        env: None,
+        default: "",
+        description: "The email of the Clockify user the exported timesheet belongs to.",
+    },
+    Setting {
+        key: "slack.enabled",
+        env: None,
+        default: "false",
+        description: "Whether starting a session sets the Slack status, like `session start --slack`.",
+    },
+    Setting {
+        key: "slack.emoji",
+        env: None,
+        default: ":technologist:",
+        description: "The emoji of the Slack status set on start.",
+    },
+    Setting {
+        key: "slack.template",
+        env: None,
+        default: "{label}",
+        description: "The text of the Slack status: a template like --template, which also has {label} and {eta}.",
+    },
+    Setting {
+        key: "slack.eta_minutes",
+        env: None,
+        default: "60",
+        description: "How long a session is expected to last, for the {eta} of the Slack status.",
+    },
+];
+
+/// Environment variables which are not settings, with what they do. The variables of the settings
+/// are in `SETTINGS`. Both are listed by `wtt man`.
+pub const ENVIRONMENT: &[(&str, &str)] = &[
+    (
+        "WTT_PATH_CONFIG",
+        "The config file to read instead of $XDG_CONFIG_HOME/wtt/config.toml.",
+    ),
+    (
+        "XDG_CONFIG_HOME",
+        "The directory of the config file, ~/.config when it's not set.",
+    ),
+    (
+        "WTT_LANG",
+        "The language of the messages, e.g. uk. Falls back to LC_ALL, LC_MESSAGES and LANG.",
+    ),
+    (
+        "WTT_LOG",
+        "The level of the logs on stderr when -v is not given: error, warn, info, debug or trace.",
+    ),
+    (
+        "WTT_ASSUME_YES",
+        "Set to 1 to confirm destructive commands without asking, like --yes.",
+    ),
+    (
+        "WTT_NO_NAG",
+        "Set to 1 to silence the reminder about sessions running for too long.",
+    ),
+    (
+        "WTT_CALDAV_USER",
+        "The user name for `wtt push caldav`. Set it together with WTT_CALDAV_PASSWORD.",
+    ),
+    (
+        "WTT_CALDAV_PASSWORD",
+        "The password for `wtt push caldav`. Many servers want an app password here.",
+    ),
+    (
+        "WTT_SERVE_TOKEN",
+        "The bearer token `wtt serve` requires for the requests which change the sessions.",
+    ),
+    (
+        "WTT_SLACK_TOKEN",
+        "A Slack user token with the users.profile:read and users.profile:write scopes, \
+         for the Slack status.",
+    ),
+    (
+        "WTT_SLACK_API_URL",
+        "The base URL of the Slack API, https://slack.com/api when it's not set.",
+    ),
+    (
+        "WTT_TOGGL_TOKEN",
+        "The Toggl Track API token for `wtt push toggl`. It's found in the Toggl profile settings.",
+    ),
+    (
+        "WTT_TOGGL_API_URL",
+        "The base URL of the Toggl API, https://api.track.toggl.com/api/v9 when it's not set.",
+    ),
+    (
+        "WTT_WEBHOOK_SECRET",
+        "The key to sign the webhook payloads with. The HMAC-SHA256 of the body is sent \
+         in the X-Wtt-Signature header as sha256=<hex>.",
+    ),
+];
+
+/// Where the value of a setting came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Default,
+    ConfigFile,
+    Env,
+    Cli,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Default => write!(f, "default"),
+            Source::ConfigFile => write!(f, "config file"),
+            Source::Env => write!(f, "environment"),
+            Source::Cli => write!(f, "command line"),
+        }
+    }
+}
+
+/// A chrono format string. It's checked when the config is loaded,
+/// so a typo is reported right away instead of breaking the output later.
+#[derive(Debug, Clone)]
+pub struct TimeFormat(String);
+
+impl Deref for TimeFormat {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for TimeFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    Json,
+    Sqlite,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableOrder {
+    Asc,
+    Desc,
+}
+
+/// A column of the session table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableColumn {
+    Number,
+    Id,
+    Weekday,
+    Start,
+    End,
+    Labels,
+    Duration,
+    Note,
+    Kind,
+    Rate,
+}
+
+impl TableColumn {
+    pub const NAMES: &[(&str, TableColumn)] = &[
+        ("number", TableColumn::Number),
+        ("id", TableColumn::Id),
+        ("weekday", TableColumn::Weekday),
+        ("start", TableColumn::Start),
+        ("end", TableColumn::End),
+        ("labels", TableColumn::Labels),
+        ("duration", TableColumn::Duration),
+        ("note", TableColumn::Note),
+        ("kind", TableColumn::Kind),
+        ("rate", TableColumn::Rate),
+    ];
+}
+
+/// Parse a comma separated list of table columns, e.g. "start,labels,duration".
+/// Used for both the setting and the --columns flag.
+pub fn parse_table_columns(value: &str) -> std::result::Result<Vec<TableColumn>, String> {
+    let columns = value
+        .split(',')
+        .map(|x| x.trim())
+        .filter(|x| !x.is_empty())
+        .map(|name| {
+            TableColumn::NAMES
+                .iter()
+                .find(|(x, _)| *x == name)
+                .map(|(_, column)| *column)
+                .ok_or_else(|| {
+                    let names: Vec<&str> = TableColumn::NAMES.iter().map(|x| x.0).collect();
+                    format!(
+                        "'{name}' is not a table column (one of {})",
+                        names.join(", ")
+                    )
+                })
+        })
+        .collect::<std::result::Result<Vec<TableColumn>, String>>()?;
+    if columns.is_empty() {
+        return Err("the table needs at least one column".to_string());
+    }
+    Ok(columns)
+}
+
+/// Values given per label, e.g. the Toggl project of each label.
+/// Written as "label=value,..." in the env, or as a table in the config file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LabelMap(pub Vec<(String, String)>);
+
+impl LabelMap {
+    pub fn get(&self, label: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(x, _)| x == label)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// A resolved setting value.
+#[derive(Debug, Clone)]
+pub struct Value<T> {
+    pub value: T,
+    pub source: Source,
+    /// The value as the user would write it, for displaying the config.
+    pub raw: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// The config file that was looked for, and whether it exists.
+    pub path: String,
+    pub path_exists: bool,
+    pub database_path: Value<String>,
+    pub backend: Value<Backend>,
+    pub today_file: Value<bool>,
+    pub date_format: Value<TimeFormat>,
+    pub datetime_format: Value<TimeFormat>,
+    pub note_width: Value<u16>,
+    pub colors: Value<bool>,
+    pub table_order: Value<TableOrder>,
+    pub table_columns: Value<Vec<TableColumn>>,
+    pub default_labels: Value<Vec<String>>,
+    pub kinds: Value<Vec<String>>,
+    pub copy_id_on_start: Value<bool>,
+    pub nag_after_hours: Value<u16>,
+    pub require_note: Value<bool>,
+    pub week_start: Value<Weekday>,
+    pub note_separator: Value<String>,
+    pub webhook_start_url: Value<String>,
+    pub webhook_end_url: Value<String>,
+    pub toggl_workspace_id: Value<String>,
+    pub toggl_projects: Value<LabelMap>,
+    pub timesheet_projects: Value<LabelMap>,
+    pub timesheet_email: Value<String>,
+    pub slack_enabled: Value<bool>,
+    pub slack_emoji: Value<String>,
+    pub slack_template: Value<String>,
+    pub slack_eta_minutes: Value<u16>,
+}
+
+/// A setting type which can be parsed from a string (env, default, CLI) or from a TOML value.
+trait SettingValue: Sized {
+    fn parse(value: &str) -> std::result::Result<Self, String>;
+
+    fn from_toml(value: &toml::Value) -> std::result::Result<Self, String> {
+        match value {
+            toml::Value::String(x) => Self::parse(x),
+            other => Self::parse(&other.to_string()),
+        }
+    }
+}
+
+impl SettingValue for String {
+    fn parse(value: &str) -> std::result::Result<Self, String> {
+        Ok(value.to_string())
+    }
+}
+
+impl SettingValue for u16 {
+    fn parse(value: &str) -> std::result::Result<Self, String> {
+        value
+            .trim()
+            .parse()
+            .map_err(|_| format!("'{value}' is not a valid u16 number"))
+    }
+}
+
+impl SettingValue for bool {
+    fn parse(value: &str) -> std::result::Result<Self, String> {
+        match value.trim().to_lowercase().as_str() {
+            "true" | "1" | "yes" => Ok(true),
+            "false" | "0" | "no" => Ok(false),
+            _ => Err(format!("'{value}' is not a boolean (true or false)")),
+        }
+    }
+}
+
+impl SettingValue for TimeFormat {
+    fn parse(value: &str) -> std::result::Result<Self, String> {
+        if StrftimeItems::new(value).any(|x| x == Item::Error) {
+            return Err(format!(
+                "'{value}' is not a valid chrono format. See \
+                https://docs.rs/chrono/latest/chrono/format/strftime/ for the specifiers"
+            ));
+        }
+        Ok(Self(value.to_string()))
+    }
+}
+
+impl SettingValue for Backend {
+    fn parse(value: &str) -> std::result::Result<Self, String> {
+        match value.trim() {
+            "json" => Ok(Backend::Json),
+            "sqlite" => Ok(Backend::Sqlite),
+            _ => Err(format!(
+                "'{value}' is not a storage backend (json or sqlite)"
+            )),
+        }
+    }
+}
+
+impl SettingValue for TableOrder {
+    fn parse(value: &str) -> std::result::Result<Self, String> {
+        match value.trim() {
+            "asc" => Ok(TableOrder::Asc),
+            "desc" => Ok(TableOrder::Desc),
+            _ => Err(format!("'{value}' is not a table order (asc or desc)")),
+        }
+    }
+}
+
+impl SettingValue for Weekday {
+    fn parse(value: &str) -> std::result::Result<Self, String> {
+        value
+            .trim()
+            .parse()
+            .map_err(|_| format!("'{value}' is not a day of the week (monday to sunday)"))
+    }
+}
+
+impl SettingValue for Vec<TableColumn> {
+    fn parse(value: &str) -> std::result::Result<Self, String> {
+        parse_table_columns(value)
+    }
+
+    fn from_toml(value: &toml::Value) -> std::result::Result<Self, String> {
+        match value {
+            toml::Value::Array(items) => {
+                let names: Vec<String> = items
+                    .iter()
+                    .map(|x| x.as_str().unwrap_or("").to_string())
+                    .collect();
+                parse_table_columns(&names.join(","))
+            }
+            toml::Value::String(x) => parse_table_columns(x),
+            other => Err(format!("{other} is not a list of table columns")),
+        }
+    }
+}
+
+impl SettingValue for LabelMap {
+    fn parse(value: &str) -> std::result::Result<Self, String> {
+        value
+            .split(',')
+            .map(|x| x.trim())
+            .filter(|x| !x.is_empty())
+            .map(|pair| match pair.split_once('=') {
+                Some((label, value)) => Ok((label.trim().to_string(), value.trim().to_string())),
+                None => Err(format!("'{pair}' is not a label=value pair")),
+            })
+            .collect::<std::result::Result<Vec<(String, String)>, String>>()
+            .map(LabelMap)
+    }
+
+    fn from_toml(value: &toml::Value) -> std::result::Result<Self, String> {
+        match value {
+            toml::Value::Table(table) => Ok(LabelMap(
+                table
+                    .iter()
+                    .map(|(label, value)| {
+                        let value = match value {
+                            toml::Value::String(x) => x.clone(),
+                            other => other.to_string(),
+                        };
+                        (label.clone(), value)
+                    })
+                    .collect(),
+            )),
+            toml::Value::String(x) => Self::parse(x),
+            other => Err(format!("{other} is not a table of labels")),
+        }
+    }
+}
+
+impl SettingValue for Vec<String> {
+    fn parse(value: &str) -> std::result::Result<Self, String> {
+        Ok(value
+            .split(',')
+            .map(|x| x.trim())
+            .filter(|x| !x.is_empty())
+            .map(|x| x.to_string())
+            .collect())
+    }
+
+    fn from_toml(value: &toml::Value) -> std::result::Result<Self, String> {
+        match value {
+            toml::Value::Array(items) => items
+                .iter()
+                .map(|x| match x {
+                    toml::Value::String(x) => Ok(x.clone()),
+                    other => Err(format!("{other} is not a string")),
+                })
+                .collect(),
+            toml::Value::String(x) => Self::parse(x),
+            other => Err(format!("{other} is not a list of strings")),
+        }
+    }
+}
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// Get the config of this run. It is loaded on the first call, unless it was set with `set` before.
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(|| Config::load().unwrap_or_else(|x| panic!("{x}")))
+}
+
+/// Use this config for the rest of the run. Fails if the config was already used.
+pub fn set(config: Config) -> Result<()> {
+    CONFIG
+        .set(config)
+        .map_err(|_| "The config has already been loaded.".into())
+}
+
+pub fn get_path_to_config_file() -> String {
+    if let Ok(path) = std::env::var("WTT_PATH_CONFIG") {
+        return path;
+    }
+    let config_home = match std::env::var("XDG_CONFIG_HOME") {
+        Ok(x) if !x.is_empty() => x,
+        _ => format!("{}/.config", std::env::var("HOME").unwrap_or_default()),
+    };
+    format!("{config_home}/wtt/config.toml")
+}
+
+impl Config {
+    pub fn load() -> Result<Self> {
+        let path = get_path_to_config_file();
+        let path_exists = fs::exists(&path)
+            .map_err(|x| format!("Could not check the config file {}. {}", &path, x))?;
+        log::debug!(
+            "Config file: {path}{}",
+            if path_exists { "" } else { " (not found)" }
+        );
+
+        let file = if path_exists {
+            let content = fs::read_to_string(&path)
+                .map_err(|x| format!("Could not open the config file {}. {}", &path, x))?;
+            content
+                .parse::<toml::Table>()
+                .map_err(|x| format!("Could not parse the config file {} as TOML. {}", &path, x))?
+        } else {
+            toml::Table::new()
+        };
+        check_unknown_keys(&file, &path)?;
+
+        Ok(Self {
+            database_path: resolve("storage.path", &file)?,
+            backend: resolve("storage.backend", &file)?,
+            today_file: resolve("storage.today_file", &file)?,
+            date_format: resolve("display.date_format", &file)?,
+            datetime_format: resolve("display.datetime_format", &file)?,
+            note_width: resolve("display.note_width", &file)?,
+            colors: resolve("display.colors", &file)?,
+            table_order: resolve("display.table_order", &file)?,
+            table_columns: resolve("display.table_columns", &file)?,
+            default_labels: resolve("behavior.default_labels", &file)?,
+            kinds: resolve("behavior.kinds", &file)?,
+            copy_id_on_start: resolve("behavior.copy_id_on_start", &file)?,
+            nag_after_hours: resolve("behavior.nag_after_hours", &file)?,
+            require_note: resolve("behavior.require_note", &file)?,
+            week_start: resolve("behavior.week_start", &file)?,
+            note_separator: resolve("behavior.note_separator", &file)?,
+            webhook_start_url: resolve("webhooks.start_url", &file)?,
+            webhook_end_url: resolve("webhooks.end_url", &file)?,
+            toggl_workspace_id: resolve("toggl.workspace_id", &file)?,
+            toggl_projects: resolve("toggl.projects", &file)?,
+            timesheet_projects: resolve("timesheet.projects", &file)?,
+            timesheet_email: resolve("timesheet.email", &file)?,
+            slack_enabled: resolve("slack.enabled", &file)?,
+            slack_emoji: resolve("slack.emoji", &file)?,
+            slack_template: resolve("slack.template", &file)?,
+            slack_eta_minutes: resolve("slack.eta_minutes", &file)?,
+            path,
+            path_exists,
+        })
+    }
+
+    /// Override a setting with a value given on the command line.
+    pub fn set_from_cli(&mut self, key: &str, value: &str) -> Result<()> {
+        match key {
+            "storage.path" => self.database_path = from_cli(key, value)?,
+            "storage.backend" => self.backend = from_cli(key, value)?,
+            "storage.today_file" => self.today_file = from_cli(key, value)?,
+            "display.date_format" => self.date_format = from_cli(key, value)?,
+            "display.datetime_format" => self.datetime_format = from_cli(key, value)?,
+            "display.note_width" => self.note_width = from_cli(key, value)?,
+            "display.colors" => self.colors = from_cli(key, value)?,
+            "display.table_order" => self.table_order = from_cli(key, value)?,
+            "display.table_columns" => self.table_columns = from_cli(key, value)?,
+            "behavior.default_labels" => self.default_labels = from_cli(key, value)?,
+            "behavior.kinds" => self.kinds = from_cli(key, value)?,
+            "behavior.copy_id_on_start" => self.copy_id_on_start = from_cli(key, value)?,
+            "behavior.nag_after_hours" => self.nag_after_hours = from_cli(key, value)?,
+            "behavior.require_note" => self.require_note = from_cli(key, value)?,
+            "behavior.week_start" => self.week_start = from_cli(key, value)?,
+            "behavior.note_separator" => self.note_separator = from_cli(key, value)?,
+            "webhooks.start_url" => self.webhook_start_url = from_cli(key, value)?,
+            "webhooks.end_url" => self.webhook_end_url = from_cli(key, value)?,
+            "toggl.workspace_id" => self.toggl_workspace_id = from_cli(key, value)?,
+            "toggl.projects" => self.toggl_projects = from_cli(key, value)?,
+            "timesheet.projects" => self.timesheet_projects = from_cli(key, value)?,
+            "timesheet.email" => self.timesheet_email = from_cli(key, value)?,
+            "slack.enabled" => self.slack_enabled = from_cli(key, value)?,
+            "slack.emoji" => self.slack_emoji = from_cli(key, value)?,
+            "slack.template" => self.slack_template = from_cli(key, value)?,
+            "slack.eta_minutes" => self.slack_eta_minutes = from_cli(key, value)?,
+            _ => return Err(format!("There is no setting {key}.").into()),
+        }
+        Ok(())
+    }
+
+    /// Every setting with its raw value and where the value came from, in the order of `SETTINGS`.
+    pub fn entries(&self) -> Vec<(&'static Setting, &str, Source)> {
+        let values: [(&str, Source); 26] = [
+            (&self.database_path.raw, self.database_path.source),
+            (&self.backend.raw, self.backend.source),
+            (&self.today_file.raw, self.today_file.source),
+            (&self.date_format.raw, self.date_format.source),
+            (&self.datetime_format.raw, self.datetime_format.source),
+            (&self.note_width.raw, self.note_width.source),
+            (&self.colors.raw, self.colors.source),
+            (&self.table_order.raw, self.table_order.source),
+            (&self.table_columns.raw, self.table_columns.source),
+            (&self.default_labels.raw, self.default_labels.source),
+            (&self.kinds.raw, self.kinds.source),
+            (&self.copy_id_on_start.raw, self.copy_id_on_start.source),
+            (&self.nag_after_hours.raw, self.nag_after_hours.source),
+            (&self.require_note.raw, self.require_note.source),
+            (&self.week_start.raw, self.week_start.source),
+            (&self.note_separator.raw, self.note_separator.source),
+            (&self.webhook_start_url.raw, self.webhook_start_url.source),
+            (&self.webhook_end_url.raw, self.webhook_end_url.source),
+            (&self.toggl_workspace_id.raw, self.toggl_workspace_id.source),
+            (&self.toggl_projects.raw, self.toggl_projects.source),
+            (&self.timesheet_projects.raw, self.timesheet_projects.source),
+            (&self.timesheet_email.raw, self.timesheet_email.source),
+            (&self.slack_enabled.raw, self.slack_enabled.source),
+            (&self.slack_emoji.raw, self.slack_emoji.source),
+            (&self.slack_template.raw, self.slack_template.source),
+            (&self.slack_eta_minutes.raw, self.slack_eta_minutes.source),
+        ];
+        SETTINGS
+            .iter()
+            .zip(values)
+            .map(|(setting, (raw, source))| (setting, raw, source))
+            .collect()
+    }
+}
+
+/// Typos in the config file would silently fall back to defaults, so they are errors.
+fn check_unknown_keys(file: &toml::Table, path: &str) -> Result<()> {
+    for (section, value) in file {
+        let Some(table) = value.as_table() else {
+            return Err(format!("Unknown setting {section} in the config file {path}.").into());
+        };
+        for name in table.keys() {
+            let key = format!("{section}.{name}");
+            if !SETTINGS.iter().any(|x| x.key == key) {
+                return Err(format!("Unknown setting {key} in the config file {path}.").into());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn get_setting(key: &str) -> &'static Setting {
+    SETTINGS
+        .iter()
+        .find(|x| x.key == key)
+        .unwrap_or_else(|| panic!("The setting {key} is not registered."))
+}
+
+fn from_cli<T: SettingValue>(key: &str, value: &str) -> Result<Value<T>> {
+    Ok(Value {
+        value: T::parse(value).map_err(|x| format!("Invalid value for {key}: {x}."))?,
+        source: Source::Cli,
+        raw: value.to_string(),
+    })
+}
+
+/// Resolve a setting from the env, then the config file, then the default.
+fn resolve<T: SettingValue>(key: &str, file: &toml::Table) -> Result<Value<T>> {
+    let setting = get_setting(key);
+
+    if let Some(env) = setting.env
+        && let Ok(raw) = std::env::var(env)
+    {
+        let value = T::parse(&raw).map_err(|x| format!("Invalid value for {env}: {x}."))?;
+        log::trace!("{key} = {raw} (environment {env})");
+        return Ok(Value {
+            value,
+            source: Source::Env,
+            raw,
+        });
+    }
+
+    let (section, name) = key.split_once('.').unwrap();
+    if let Some(toml_value) = file
+        .get(section)
+        .and_then(|x| x.as_table())
+        .and_then(|x| x.get(name))
+    {
+        let value = T::from_toml(toml_value)
+            .map_err(|x| format!("Invalid value for {key} in the config file: {x}."))?;
+        let raw = match toml_value {
+            toml::Value::String(x) => x.clone(),
+            other => other.to_string(),
+        };
+        log::trace!("{key} = {raw} (config file)");
+        return Ok(Value {
+            value,
+            source: Source::ConfigFile,
+            raw,
+        });
+    }
+
+    Ok(Value {
+        value: T::parse(setting.default).unwrap_or_else(|x| panic!("Bad default of {key}: {x}")),
+        source: Source::Default,
+        raw: setting.default.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn table_columns_in_the_given_order() {
+        assert_eq!(
+            parse_table_columns("start, labels ,duration,").unwrap(),
+            vec![
+                TableColumn::Start,
+                TableColumn::Labels,
+                TableColumn::Duration
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_table_column_names_the_valid_ones() {
+        let error = parse_table_columns("start,bogus").unwrap_err();
+        assert!(error.contains("'bogus'"), "{error}");
+        for (name, _) in TableColumn::NAMES {
+            assert!(error.contains(name), "{error}");
+        }
+    }
+
+    #[test]
+    fn table_needs_a_column() {
+        assert!(parse_table_columns("").is_err());
+        assert!(parse_table_columns(" , ").is_err());
+    }
+
+    #[test]
+    fn table_columns_from_the_config_file() {
+        let list = toml::Value::Array(vec!["note".into(), "id".into()]);
+        assert_eq!(
+            <Vec<TableColumn>>::from_toml(&list).unwrap(),
+            vec![TableColumn::Note, TableColumn::Id]
+        );
+        let text = toml::Value::String("note,id".to_string());
+        assert_eq!(
+            <Vec<TableColumn>>::from_toml(&text).unwrap(),
+            vec![TableColumn::Note, TableColumn::Id]
+        );
+        assert!(<Vec<TableColumn>>::from_toml(&toml::Value::Integer(1)).is_err());
+    }
+
+    #[test]
+    fn week_start_is_a_day_of_the_week() {
+        assert_eq!(Weekday::parse("sunday"), Ok(Weekday::Sun));
+        assert_eq!(Weekday::parse(" Mon "), Ok(Weekday::Mon));
+        let error = Weekday::parse("someday").unwrap_err();
+        assert!(error.contains("'someday'"), "{error}");
+        assert!(Weekday::from_toml(&toml::Value::Integer(1)).is_err());
+    }
+}
@@ -0,0 +1,239 @@
+//! Diagnostics of the setup around the store: the config, the database file and the terminal.
+//!
+//! Every check prints a line. A failed hard check comes with a remedy and makes the command
+//! exit with a non-zero code. Warnings point at things which work, but probably not as intended.
+
+use std::{fs, io::IsTerminal, path::Path};
+
+use chrono::Local as LocalTZ;
+use wtt::{
+    config::{self, Config},
+    store::{Store, get_path_to_store_file},
+};
+
+enum Status {
+    Ok,
+    Warn,
+    Fail,
+}
+
+#[derive(Default)]
+struct Report {
+    failed: bool,
+}
+
+impl Report {
+    fn line(&mut self, status: Status, message: &str, remedy: Option<&str>) {
+        let tag = match status {
+            Status::Ok => "ok",
+            Status::Warn => "warn",
+            Status::Fail => {
+                self.failed = true;
+                "fail"
+            }
+        };
+        println!("[{tag}] {message}");
+        if let Some(remedy) = remedy {
+            println!("       {remedy}");
+        }
+    }
+}
+
+/// Run the checks and return the exit code.
+pub fn run(db: Option<&str>) -> i32 {
+    let mut report = Report::default();
+
+    let mut config = match Config::load() {
+        Ok(x) => x,
+        Err(x) => {
+            report.line(
+                Status::Fail,
+                &format!("The config can't be loaded. {x}"),
+                Some("Fix the config file or the environment variable named above."),
+            );
+            return 1;
+        }
+    };
+    if let Some(db) = db
+        && let Err(x) = config.set_from_cli("storage.path", db)
+    {
+        report.line(Status::Fail, &x.to_string(), None);
+        return 1;
+    }
+    report.line(
+        Status::Ok,
+        &format!(
+            "Config file: {}{}",
+            config.path,
+            if config.path_exists {
+                ""
+            } else {
+                " (not found, the defaults are used)"
+            }
+        ),
+        None,
+    );
+    let colors = config.colors.value;
+    // The checks below resolve the database path through the config, like the commands do.
+    if config::set(config).is_err() {
+        report.line(
+            Status::Fail,
+            "The config was loaded before the checks.",
+            None,
+        );
+        return 1;
+    }
+
+    check_database_file(&mut report);
+    if let Some(store) = check_store(&mut report) {
+        check_clock(&mut report, &store);
+        check_schema(&mut report, &store);
+    }
+    check_colors(&mut report, colors);
+
+    if report.failed { 1 } else { 0 }
+}
+
+fn check_database_file(report: &mut Report) {
+    let path = get_path_to_store_file();
+    let source = &config::get().database_path.source;
+    report.line(
+        Status::Ok,
+        &format!("Database: {path} (from {source})"),
+        None,
+    );
+
+    let path = Path::new(&path);
+    let parent = match path.parent() {
+        Some(x) if !x.as_os_str().is_empty() => x,
+        _ => Path::new("."),
+    };
+    if !parent.is_dir() {
+        report.line(
+            Status::Fail,
+            &format!("The directory {} doesn't exist.", parent.display()),
+            Some("Create it, or point WTT_PATH_DATABASE / --db / storage.path elsewhere."),
+        );
+        return;
+    }
+
+    // Saving writes next to the database, so try the same in the same directory.
+    let probe = parent.join(".wtt-doctor-probe");
+    match fs::write(&probe, b"") {
+        Ok(()) => {
+            let _ = fs::remove_file(&probe);
+            report.line(Status::Ok, "The database directory is writable.", None);
+        }
+        Err(x) => report.line(
+            Status::Fail,
+            &format!("The directory {} is not writable. {x}", parent.display()),
+            Some("Fix the permissions, or keep the database somewhere else."),
+        ),
+    }
+
+    if path.exists() {
+        match fs::OpenOptions::new().append(true).open(path) {
+            Ok(_) => report.line(Status::Ok, "The database file is writable.", None),
+            Err(x) => report.line(
+                Status::Fail,
+                &format!("The database file can't be opened for writing. {x}"),
+                Some("Fix the permissions of the file."),
+            ),
+        }
+    } else {
+        report.line(
+            Status::Warn,
+            "The database file doesn't exist yet. It will be created by the first session.",
+            None,
+        );
+    }
+}
+
+fn check_store(report: &mut Report) -> Option<Store> {
+    let store = match Store::from_store_file() {
+        Ok(x) => x,
+        Err(x) => {
+            report.line(
+                Status::Fail,
+                &format!("The database can't be read. {x}"),
+                Some("Restore it from a backup, or fix the JSON (or the SQLite database) by hand."),
+            );
+            return None;
+        }
+    };
+    let problems = store.validate();
+    if problems.is_empty() {
+        report.line(
+            Status::Ok,
+            &format!("The database has {} valid sessions.", store.sessions.len()),
+            None,
+        );
+    } else {
+        for problem in problems {
+            report.line(
+                Status::Fail,
+                &problem,
+                Some("Edit the session, or fix the database file by hand."),
+            );
+        }
+    }
+    Some(store)
+}
+
+fn check_clock(report: &mut Report, store: &Store) {
+    let now = LocalTZ::now().timestamp();
+    let future = store.sessions.iter().filter(|x| x.start_at > now).count();
+    if future == 0 {
+        report.line(Status::Ok, "No sessions start in the future.", None);
+    } else {
+        report.line(
+            Status::Warn,
+            &format!("{future} sessions start in the future."),
+            Some("Check the system clock and the timezone, or edit those sessions."),
+        );
+    }
+}
+
+fn check_schema(report: &mut Report, store: &Store) {
+    let mut fields: Vec<&str> = store.extra.keys().map(|x| x.as_str()).collect();
+    for session in store.sessions.iter() {
+        fields.extend(session.extra.keys().map(|x| x.as_str()));
+    }
+    fields.sort_unstable();
+    fields.dedup();
+    if fields.is_empty() {
+        report.line(
+            Status::Ok,
+            "The database matches this version of the app.",
+            None,
+        );
+    } else {
+        report.line(
+            Status::Warn,
+            &format!(
+                "The database has fields unknown to version {}: {}.",
+                env!("CARGO_PKG_VERSION"),
+                fields.join(", ")
+            ),
+            Some("They are kept as is, but a newer version of the app may be needed to use them."),
+        );
+    }
+}
+
+fn check_colors(report: &mut Report, colors: bool) {
+    if !colors {
+        report.line(Status::Ok, "Colors are turned off.", None);
+        return;
+    }
+    let dumb = std::env::var("TERM").is_ok_and(|x| x == "dumb");
+    let no_color = std::env::var("NO_COLOR").is_ok_and(|x| !x.is_empty());
+    if !std::io::stdout().is_terminal() || dumb || no_color {
+        report.line(
+            Status::Warn,
+            "Colors are on, but the output is not a color terminal.",
+            Some("Set display.colors = false to get plain tables."),
+        );
+    } else {
+        report.line(Status::Ok, "The terminal supports colors.", None);
+    }
+}
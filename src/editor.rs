@@ -0,0 +1,46 @@
+//! Writing longer texts in the user's editor, like `git commit` does.
+
+use std::{env, fs, process::Command};
+
+use wtt::Result;
+
+/// Lines starting with this are instructions for the user and are not part of the text.
+const COMMENT: char = '#';
+
+/// Open $VISUAL or $EDITOR (vi if neither is set) on a file with the given comment lines.
+/// Returns the text without the comments, or None when it's empty or the editor failed.
+pub fn edit(comments: &[String]) -> Result<Option<String>> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .ok()
+        .filter(|x| !x.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string());
+    let path = env::temp_dir().join(format!("wtt-note-{}.txt", std::process::id()));
+
+    let mut content = String::from("\n");
+    for line in comments {
+        content.push_str(&format!("{COMMENT} {line}\n"));
+    }
+    fs::write(&path, content)
+        .map_err(|x| format!("Could not create the file {}. {x}", path.display()))?;
+
+    // The editor may come with arguments, e.g. "code --wait", so let the shell split it.
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{editor} \"$1\""))
+        .arg("sh")
+        .arg(&path)
+        .status();
+    let text = fs::read_to_string(&path);
+    let _ = fs::remove_file(&path);
+
+    let status = status.map_err(|x| format!("Could not run the editor {editor}. {x}"))?;
+    if !status.success() {
+        log::debug!("The editor {editor} exited with {status}.");
+        return Ok(None);
+    }
+    let text = text.map_err(|x| format!("Could not read the edited file. {x}"))?;
+    let text: Vec<&str> = text.lines().filter(|x| !x.starts_with(COMMENT)).collect();
+    let text = text.join("\n").trim().to_string();
+    Ok((!text.is_empty()).then_some(text))
+}
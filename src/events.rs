@@ -0,0 +1,82 @@
+//! The sessions a command started, ended, resumed or cancelled, for the hooks, the webhooks
+//! and Slack.
+//!
+//! The events are found by comparing the sessions before and after a command, so every way of
+//! changing a session (the commands, the UI, a batch) is covered without each of them knowing
+//! about the listeners. A switch is an end and a start. A command whose change only looks like
+//! one of these, like splitting a running session, states its events itself with
+//! `StoreHandle::set_events`.
+
+use std::collections::HashSet;
+
+use wtt::store::{Session, Store};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    Start,
+    End,
+    /// An ended session runs again.
+    Resume,
+    /// A running session was thrown away.
+    Cancel,
+}
+
+impl Event {
+    pub fn name(self) -> &'static str {
+        match self {
+            Event::Start => "start",
+            Event::End => "end",
+            Event::Resume => "resume",
+            Event::Cancel => "cancel",
+        }
+    }
+}
+
+/// The sessions as they were when a command opened the store, to compare with after a change.
+#[derive(Default)]
+pub struct Before {
+    running: Vec<Session>,
+    ended_ids: HashSet<String>,
+}
+
+impl Before {
+    pub fn new(store: &Store) -> Self {
+        let mut before = Self::default();
+        for session in &store.sessions {
+            match session.end_at {
+                None => before.running.push(session.clone()),
+                Some(_) => {
+                    before.ended_ids.insert(session.id.clone());
+                }
+            }
+        }
+        before
+    }
+}
+
+/// The sessions which started, ended, resumed or were cancelled since `before`.
+pub fn since(before: &Before, store: &Store) -> Vec<(Event, Session)> {
+    let was_running = |id: &str| before.running.iter().any(|x| x.id == id);
+    let mut events: Vec<(Event, Session)> = store
+        .sessions
+        .iter()
+        .filter_map(|session| {
+            let event = match (was_running(&session.id), session.end_at) {
+                (false, None) if before.ended_ids.contains(&session.id) => Event::Resume,
+                (false, None) => Event::Start,
+                (true, Some(_)) => Event::End,
+                _ => return None,
+            };
+            Some((event, session.clone()))
+        })
+        .collect();
+    // A running session which is gone was cancelled, or deleted, which is the same to listeners.
+    events.extend(
+        before
+            .running
+            .iter()
+            .filter(|x| !store.sessions.iter().any(|session| session.id == x.id))
+            .map(|x| (Event::Cancel, x.clone())),
+    );
+    events
+}
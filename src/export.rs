@@ -0,0 +1,360 @@
+//! Exporting the tracked time to other tools.
+
+use std::{collections::BTreeMap, fmt::Write};
+
+use chrono::{DateTime, Days, Local as LocalTZ, TimeZone};
+
+use crate::{
+    Result,
+    config::{self, LabelMap},
+    report::{GroupBy, ReportRow},
+    store::{Filter, Store},
+    time,
+};
+
+/// How many days, today included, get a daily total in the Prometheus metrics.
+const PROMETHEUS_DAYS: u64 = 7;
+/// The task of the time entries whose mapping doesn't name one.
+const DEFAULT_TASK: &str = "General";
+
+/// Gauges in the text format of the node exporter's textfile collector.
+///
+/// The labels of a session only ever go into label values, which are escaped, so any label is safe.
+/// Label and metric names are fixed. Sessions count towards the day they were started on,
+/// like in the reports.
+pub fn prometheus(store: &Store, now: DateTime<LocalTZ>) -> String {
+    let today = now.date_naive();
+    let first_day = today - Days::new(PROMETHEUS_DAYS - 1);
+    let from = LocalTZ
+        .from_local_datetime(&first_day.and_hms_opt(0, 0, 0).unwrap())
+        .earliest()
+        .map(|x| x.timestamp());
+    let filter = Filter {
+        from,
+        ..Default::default()
+    };
+    let report = store.report(&filter, GroupBy::None);
+
+    let mut running: Vec<(String, u64)> = vec![];
+    // Label -> day -> seconds.
+    let mut daily: BTreeMap<&str, BTreeMap<String, u64>> = BTreeMap::new();
+    for row in &report.rows {
+        if row.end_at.is_none() {
+            running.push((row.labels.join(","), row.duration));
+        }
+        let day = LocalTZ
+            .timestamp_opt(row.start_at, 0)
+            .unwrap()
+            .format("%Y-%m-%d")
+            .to_string();
+        for label in &row.labels {
+            *daily
+                .entry(label)
+                .or_default()
+                .entry(day.clone())
+                .or_default() += row.duration;
+        }
+    }
+    // Running sessions outside of the window still count as running.
+    for session in &store.sessions {
+        if session.end_at.is_none() && from.is_some_and(|x| session.start_at < x) {
+            let duration = (now.timestamp() - session.start_at).max(0) as u64;
+            running.push((session.labels.join(","), duration));
+        }
+    }
+
+    // The week never starts before the window of the daily totals.
+    let week_start = time::week_start(today, config::get().week_start.value)
+        .format("%Y-%m-%d")
+        .to_string();
+    let today = today.format("%Y-%m-%d").to_string();
+    let mut text = String::new();
+    header(
+        &mut text,
+        "wtt_running_sessions",
+        "The number of running sessions.",
+    );
+    writeln!(text, "wtt_running_sessions {}", running.len()).unwrap();
+
+    header(
+        &mut text,
+        "wtt_session_running",
+        "1 for every running session, by its comma-separated labels.",
+    );
+    for (labels, _) in &running {
+        writeln!(
+            text,
+            "wtt_session_running{{labels=\"{}\"}} 1",
+            escape(labels)
+        )
+        .unwrap();
+    }
+
+    header(
+        &mut text,
+        "wtt_running_session_seconds",
+        "How long the running sessions have been running.",
+    );
+    for (labels, duration) in &running {
+        writeln!(
+            text,
+            "wtt_running_session_seconds{{labels=\"{}\"}} {duration}",
+            escape(labels)
+        )
+        .unwrap();
+    }
+
+    header(
+        &mut text,
+        "wtt_tracked_seconds_total",
+        "Seconds tracked per label today and this week, running sessions up to now.",
+    );
+    for (label, days) in &daily {
+        let label = escape(label);
+        let today_total = days.get(&today).copied().unwrap_or(0);
+        let week_total: u64 = days.range(week_start.clone()..).map(|(_, x)| x).sum();
+        writeln!(
+            text,
+            "wtt_tracked_seconds_total{{label=\"{label}\",period=\"today\"}} {today_total}"
+        )
+        .unwrap();
+        writeln!(
+            text,
+            "wtt_tracked_seconds_total{{label=\"{label}\",period=\"week\"}} {week_total}"
+        )
+        .unwrap();
+    }
+
+    header(
+        &mut text,
+        "wtt_tracked_seconds_daily",
+        "Seconds tracked per label on each of the last 7 days.",
+    );
+    for (label, days) in &daily {
+        let label = escape(label);
+        for (day, seconds) in days {
+            writeln!(
+                text,
+                "wtt_tracked_seconds_daily{{label=\"{label}\",day=\"{day}\"}} {seconds}"
+            )
+            .unwrap();
+        }
+    }
+    text
+}
+
+fn header(text: &mut String, name: &str, help: &str) {
+    writeln!(text, "# HELP {name} {help}").unwrap();
+    writeln!(text, "# TYPE {name} gauge").unwrap();
+}
+
+/// Escape a label value as the exposition format requires.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// The bulk import formats of the timesheet export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dialect {
+    /// Date, Client, Project, Task, Notes, Hours, with the hours as decimals.
+    Harvest,
+    /// The columns of the Clockify import, with dates as mm/dd/yyyy and durations as hh:mm:ss.
+    Clockify,
+}
+
+pub struct Timesheet {
+    pub csv: String,
+    /// The numbers of the sessions which have no label mapped to a project. They are not in the CSV.
+    pub unmapped: Vec<u64>,
+}
+
+/// Where a session goes in the time tracker, and the labels left over for the tags.
+struct Target<'a> {
+    client: &'a str,
+    project: &'a str,
+    task: &'a str,
+    tags: Vec<&'a str>,
+}
+
+/// The ended sessions selected by the filter as a CSV for the bulk import of a time tracker.
+///
+/// The first label found in `projects` decides the client, project and task of a session,
+/// written as "Client/Project/Task" ("Project" and "Client/Project" work too). Without any
+/// mapping, the first label is the project. Sessions which can't be mapped are left out and
+/// listed in `unmapped`.
+pub fn timesheet(
+    store: &Store,
+    filter: &Filter,
+    dialect: Dialect,
+    projects: &LabelMap,
+    email: &str,
+) -> Result<Timesheet> {
+    let report = store.report(filter, GroupBy::None);
+    let mut writer = csv::Writer::from_writer(vec![]);
+    match dialect {
+        Dialect::Harvest => {
+            writer.write_record(["Date", "Client", "Project", "Task", "Notes", "Hours"])?
+        }
+        Dialect::Clockify => writer.write_record([
+            "Project",
+            "Client",
+            "Description",
+            "Task",
+            "Email",
+            "Tags",
+            "Billable",
+            "Start Date",
+            "Start Time",
+            "End Date",
+            "End Time",
+            "Duration (h)",
+        ])?,
+    }
+
+    let mut unmapped = vec![];
+    for row in report.rows.iter().filter(|x| x.end_at.is_some()) {
+        let Some(target) = target(row, projects) else {
+            unmapped.push(row.seq);
+            continue;
+        };
+        let note = row.note.as_deref().unwrap_or("");
+        let start = LocalTZ.timestamp_opt(row.start_at, 0).unwrap();
+        match dialect {
+            Dialect::Harvest => writer.write_record([
+                start.format("%Y-%m-%d").to_string().as_str(),
+                target.client,
+                target.project,
+                target.task,
+                note,
+                &format!("{:.2}", row.duration as f64 / 3600.0),
+            ])?,
+            Dialect::Clockify => {
+                let end = LocalTZ.timestamp_opt(row.end_at.unwrap(), 0).unwrap();
+                let duration = row.duration;
+                writer.write_record([
+                    target.project,
+                    target.client,
+                    note,
+                    target.task,
+                    email,
+                    &target.tags.join(", "),
+                    if row.rate.is_some() { "Yes" } else { "No" },
+                    &start.format("%m/%d/%Y").to_string(),
+                    &start.format("%H:%M:%S").to_string(),
+                    &end.format("%m/%d/%Y").to_string(),
+                    &end.format("%H:%M:%S").to_string(),
+                    &format!(
+                        "{:02}:{:02}:{:02}",
+                        duration / 3600,
+                        duration % 3600 / 60,
+                        duration % 60
+                    ),
+                ])?
+            }
+        }
+    }
+    let csv = String::from_utf8(writer.into_inner().map_err(|x| x.to_string())?)?;
+    Ok(Timesheet { csv, unmapped })
+}
+
+fn target<'a>(row: &'a ReportRow, projects: &'a LabelMap) -> Option<Target<'a>> {
+    let (label, mapping) = if projects.0.is_empty() {
+        let label = row.labels.first()?;
+        (label, label.as_str())
+    } else {
+        row.labels
+            .iter()
+            .find_map(|label| projects.get(label).map(|x| (label, x)))?
+    };
+    let parts: Vec<&str> = mapping.split('/').map(str::trim).collect();
+    let (client, project, task) = match parts.as_slice() {
+        [project] => ("", *project, DEFAULT_TASK),
+        [client, project] => (*client, *project, DEFAULT_TASK),
+        [client, project, task, ..] => (*client, *project, *task),
+        [] => return None,
+    };
+    Some(Target {
+        client,
+        project,
+        task,
+        tags: row
+            .labels
+            .iter()
+            .filter(|x| *x != label)
+            .map(String::as_str)
+            .collect(),
+    })
+}
+
+/// An org-mode document with the sessions as CLOCK lines, under a heading per label or per day.
+///
+/// Org counts whole minutes between the two timestamps of a CLOCK line, so the durations are
+/// computed the same way and `org-clock-report` agrees with them. A running session is an open
+/// clock, which org leaves out of its sums. The notes follow the drawer, oldest first.
+pub fn org(store: &Store, filter: &Filter, group_by: GroupBy) -> String {
+    let report = store.report(filter, GroupBy::None);
+    let mut groups: BTreeMap<String, Vec<&ReportRow>> = BTreeMap::new();
+    for row in &report.rows {
+        let keys = match group_by {
+            GroupBy::Day => vec![org_date(row.start_at)],
+            _ if row.labels.is_empty() => vec!["(no label)".to_string()],
+            _ => row.labels.clone(),
+        };
+        for key in keys {
+            groups.entry(key).or_default().push(row);
+        }
+    }
+
+    let mut text = String::from("#+TITLE: wtt\n");
+    for (key, rows) in groups {
+        writeln!(text, "\n* {key}").unwrap();
+        text.push_str(":LOGBOOK:\n");
+        // Org adds new clocks at the top of the drawer.
+        for row in rows.iter().rev() {
+            let start = org_timestamp(row.start_at);
+            match row.end_at {
+                Some(end_at) => {
+                    let minutes = end_at.div_euclid(60) - row.start_at.div_euclid(60);
+                    writeln!(
+                        text,
+                        "CLOCK: {start}--{} => {:>2}:{:02}",
+                        org_timestamp(end_at),
+                        minutes / 60,
+                        minutes % 60
+                    )
+                    .unwrap();
+                }
+                None => writeln!(text, "CLOCK: {start}").unwrap(),
+            }
+        }
+        text.push_str(":END:\n");
+        for row in rows {
+            if let Some(note) = row.note.as_deref().filter(|x| !x.trim().is_empty()) {
+                // Indented, so a line of the note can't start a heading.
+                let note = note.trim().lines().collect::<Vec<&str>>().join("\n  ");
+                writeln!(text, "- {} {note}", org_timestamp(row.start_at)).unwrap();
+            }
+        }
+    }
+    text
+}
+
+fn org_date(timestamp: i64) -> String {
+    LocalTZ
+        .timestamp_opt(timestamp, 0)
+        .unwrap()
+        .format("%Y-%m-%d %a")
+        .to_string()
+}
+
+/// An inactive org timestamp, e.g. [2025-03-21 Fri 09:12].
+fn org_timestamp(timestamp: i64) -> String {
+    LocalTZ
+        .timestamp_opt(timestamp, 0)
+        .unwrap()
+        .format("[%Y-%m-%d %a %H:%M]")
+        .to_string()
+}
@@ -0,0 +1,73 @@
+//! Recording which git branch a session was about and which commits it produced.
+//!
+//! Everything here is best-effort: without git, outside of a repository or on a detached HEAD,
+//! there is a warning and the session is started or ended as usual.
+
+use std::{path::Path, process::Command};
+
+use wtt::store::GitInfo;
+
+use crate::format_rfc3339;
+
+/// The repository and branch of the current directory.
+pub fn current() -> Option<GitInfo> {
+    let path = run_git(None, &["rev-parse", "--show-toplevel"])?;
+    let Some(branch) = run_git(None, &["symbolic-ref", "--quiet", "--short", "HEAD"]) else {
+        eprintln!("Warning: HEAD is detached, so there is no branch to record.");
+        return None;
+    };
+    let repository = Path::new(&path)
+        .file_name()
+        .map(|x| x.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.clone());
+    Some(GitInfo {
+        repository,
+        path,
+        branch,
+    })
+}
+
+/// The commits made on the branch between the timestamps, one "<hash> <subject>" per line.
+pub fn commits(info: &GitInfo, since: i64, until: i64) -> Option<Vec<String>> {
+    let since = format!("--since={}", format_rfc3339(since));
+    let until = format!("--until={}", format_rfc3339(until));
+    let log = run_git(
+        Some(&info.path),
+        &[
+            "log",
+            "--oneline",
+            "--no-decorate",
+            &since,
+            &until,
+            &info.branch,
+            "--",
+        ],
+    )?;
+    Some(log.lines().map(|x| x.to_string()).collect())
+}
+
+/// Run git and return its trimmed output. Any failure is reported as a warning.
+fn run_git(directory: Option<&str>, args: &[&str]) -> Option<String> {
+    let mut command = Command::new("git");
+    if let Some(directory) = directory {
+        command.arg("-C").arg(directory);
+    }
+    let output = match command.args(args).output() {
+        Ok(x) => x,
+        Err(x) => {
+            eprintln!("Warning: could not run git. {x}");
+            return None;
+        }
+    };
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = stderr.trim();
+        if reason.is_empty() {
+            log::debug!("git {} exited with {}.", args.join(" "), output.status);
+        } else {
+            eprintln!("Warning: git {} failed. {reason}", args[0]);
+        }
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
@@ -0,0 +1,192 @@
+//! Access to the store for the commands which modify it.
+//!
+//! With the global --dry-run flag such commands do all of their work and report it as usual,
+//! but nothing is written back to disk.
+//!
+//! In a batch the commands share one store in memory. Saving hands the changes over
+//! to the next command instead, and the batch writes the store once at the end.
+//!
+//! An open store holds an advisory lock on the file next to the database with the ".lock"
+//! suffix, so two processes can't load the same data and then overwrite each other's changes.
+
+use std::{
+    fs::{File, OpenOptions, TryLockError},
+    ops::{Deref, DerefMut},
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use wtt::{
+    Result,
+    store::{Session, Store, get_path_to_store_file},
+    undo::{UndoRecord, read_store_file},
+};
+
+use crate::{
+    events::{self, Event},
+    hooks, nag, slack, webhook,
+};
+
+static DRY_RUN: AtomicBool = AtomicBool::new(false);
+static BATCH: Mutex<Option<Store>> = Mutex::new(None);
+
+/// How long to wait for another process to finish changing the database.
+const LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub fn set_dry_run(value: bool) {
+    DRY_RUN.store(value, Ordering::Relaxed);
+}
+
+pub fn is_dry_run() -> bool {
+    DRY_RUN.load(Ordering::Relaxed)
+}
+
+/// Make the following commands work on this store instead of the database file.
+pub fn start_batch(store: Store) {
+    *BATCH.lock().unwrap() = Some(store);
+}
+
+/// Stop sharing the store and return it with the changes of the commands.
+pub fn finish_batch() -> Option<Store> {
+    BATCH.lock().unwrap().take()
+}
+
+/// Load the store for reading. In a batch it includes the changes of the previous commands.
+pub fn load() -> Result<Store> {
+    if let Some(store) = BATCH.lock().unwrap().as_ref() {
+        return Ok(store.clone());
+    }
+    let store = Store::from_store_file()?;
+    nag::check(&store);
+    Ok(store)
+}
+
+/// Take the lock of the database, waiting a little if another process holds it.
+/// It's released when the returned file is dropped.
+pub fn lock_store() -> Result<File> {
+    let path = format!("{}.lock", get_path_to_store_file());
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&path)
+        .map_err(|x| format!("Could not open the lock file {path}. {x}"))?;
+    let started = Instant::now();
+    loop {
+        match file.try_lock() {
+            Ok(()) => return Ok(file),
+            Err(TryLockError::WouldBlock) if started.elapsed() < LOCK_TIMEOUT => {
+                thread::sleep(Duration::from_millis(50));
+            }
+            Err(TryLockError::WouldBlock) => {
+                return Err(format!(
+                    "Another wtt command is changing the database (it may be waiting for input). \
+                    Try again when it's done. The lock file is {path}."
+                )
+                .into());
+            }
+            Err(TryLockError::Error(x)) => {
+                return Err(format!("Could not lock the database with {path}. {x}").into());
+            }
+        }
+    }
+}
+
+/// Print the outcome of a command. It's marked in a dry run, because nothing was saved.
+pub fn success(message: &str) {
+    if is_dry_run() {
+        println!("(dry-run) {message}");
+    } else {
+        println!("{message}");
+    }
+}
+
+/// A store opened for modification. Saving is a no-op in a dry run.
+/// Otherwise the database content it was opened from is kept for `wtt undo`,
+/// and the sessions which started or ended since go through the hooks, the webhooks and Slack.
+pub struct StoreHandle {
+    store: Store,
+    original: Option<String>,
+    before: events::Before,
+    events: Option<Vec<(Event, Session)>>,
+    _lock: Option<File>,
+}
+
+impl StoreHandle {
+    pub fn open() -> Result<Self> {
+        if let Some(store) = BATCH.lock().unwrap().as_ref() {
+            return Ok(Self {
+                store: store.clone(),
+                original: None,
+                before: events::Before::default(),
+                events: None,
+                _lock: None,
+            });
+        }
+        let lock = match is_dry_run() {
+            true => None,
+            false => Some(lock_store()?),
+        };
+        let store = Store::from_store_file()?;
+        nag::check(&store);
+        Ok(Self {
+            original: read_store_file()?,
+            before: events::Before::new(&store),
+            events: None,
+            store,
+            _lock: lock,
+        })
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let command: Vec<String> = std::env::args().skip(1).collect();
+        self.save_as(&command.join(" "))
+    }
+
+    /// Report these events instead of the ones found by comparing the sessions, for a change
+    /// which only looks like a start or an end.
+    pub fn set_events(&mut self, events: Vec<(Event, Session)>) {
+        self.events = Some(events);
+    }
+
+    /// Save with a custom description for `wtt undo`, for changes which aren't a whole command.
+    pub fn save_as(&self, command: &str) -> Result<()> {
+        if let Some(store) = BATCH.lock().unwrap().as_mut() {
+            *store = self.store.clone();
+            return Ok(());
+        }
+        if is_dry_run() {
+            log::debug!("Dry run, the changes are not saved.");
+            return Ok(());
+        }
+        let events = match &self.events {
+            Some(x) => x.clone(),
+            None => events::since(&self.before, &self.store),
+        };
+        hooks::run_pre(&events)?;
+        self.store.save()?;
+        UndoRecord::save(command.to_string(), self.original.clone())?;
+        hooks::run_post(&events);
+        webhook::notify(&events);
+        slack::update(&events);
+        Ok(())
+    }
+}
+
+impl Deref for StoreHandle {
+    type Target = Store;
+
+    fn deref(&self) -> &Store {
+        &self.store
+    }
+}
+
+impl DerefMut for StoreHandle {
+    fn deref_mut(&mut self) -> &mut Store {
+        &mut self.store
+    }
+}
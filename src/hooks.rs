@@ -0,0 +1,166 @@
+//! User scripts run around session events.
+//!
+//! An executable file in the hooks directory next to the config file (usually
+//! ~/.config/wtt/hooks) named pre-start, post-start, pre-end, post-end, pre-resume, post-resume,
+//! pre-cancel or post-cancel is run with the details of the session in environment variables.
+//! The pre hooks run before anything is saved, and a non-zero exit aborts the command, so they
+//! can enforce policies. The post hooks run after the save, and their failures are only warnings.
+
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+    thread,
+    time::{Duration, Instant},
+};
+
+use wtt::{Result, config::get_path_to_config_file, store::Session};
+
+use crate::{events::Event, format_rfc3339};
+
+/// How long a hook may run before it's killed.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Run the pre hooks of the events. Fails if any of them fails.
+pub fn run_pre(events: &[(Event, Session)]) -> Result<()> {
+    for (event, session) in events {
+        let name = format!("pre-{}", event.name());
+        if let Some(path) = find(&name) {
+            run(&path, session, TIMEOUT)
+                .map_err(|x| format!("The {name} hook stopped the command. {x}"))?;
+        }
+    }
+    Ok(())
+}
+
+/// Run the post hooks of the events. The changes are saved already, so failures are warnings.
+pub fn run_post(events: &[(Event, Session)]) {
+    for (event, session) in events {
+        let name = format!("post-{}", event.name());
+        if let Some(path) = find(&name)
+            && let Err(x) = run(&path, session, TIMEOUT)
+        {
+            eprintln!("Warning: the {name} hook failed. {x}");
+        }
+    }
+}
+
+/// The directory with the hooks, next to the config file.
+pub fn get_path_to_hooks_dir() -> PathBuf {
+    let config_path = get_path_to_config_file();
+    Path::new(&config_path)
+        .parent()
+        .unwrap_or(Path::new("."))
+        .join("hooks")
+}
+
+fn find(name: &str) -> Option<PathBuf> {
+    let path = get_path_to_hooks_dir().join(name);
+    let metadata = path.metadata().ok()?;
+    if !is_executable(&metadata) {
+        log::warn!(
+            "The hook {} is not executable, so it's skipped.",
+            path.display()
+        );
+        return None;
+    }
+    Some(path)
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(metadata: &std::fs::Metadata) -> bool {
+    metadata.is_file()
+}
+
+fn run(path: &Path, session: &Session, timeout: Duration) -> Result<()> {
+    log::debug!("Running the hook {}.", path.display());
+    let mut child = Command::new(path)
+        .env("WTT_SESSION_ID", &session.id)
+        .env("WTT_LABELS", session.labels.join(","))
+        .env("WTT_START", format_rfc3339(session.start_at))
+        .env(
+            "WTT_END",
+            session.end_at.map(format_rfc3339).unwrap_or_default(),
+        )
+        .env("WTT_NOTE", session.note.as_deref().unwrap_or(""))
+        .spawn()
+        .map_err(|x| format!("Could not run {}. {x}", path.display()))?;
+
+    let started = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait()? {
+            if status.success() {
+                return Ok(());
+            }
+            return Err(format!("{} exited with {status}.", path.display()).into());
+        }
+        if started.elapsed() > timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(format!(
+                "{} took longer than {} seconds and was killed.",
+                path.display(),
+                timeout.as_secs_f64()
+            )
+            .into());
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+
+    use super::*;
+
+    fn script(name: &str, body: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("wtt-hook-{}-{name}", std::process::id()));
+        std::fs::write(&path, format!("#!/bin/sh\n{body}\n")).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    fn session() -> Session {
+        serde_json::from_value(serde_json::json!({
+            "id": "abc",
+            "start_at": 0,
+            "end_at": null,
+            "note": null,
+            "labels": ["a", "b"],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn slow_hook_is_killed() {
+        let path = script("slow", "sleep 5");
+        let started = Instant::now();
+        let error = run(&path, &session(), Duration::from_millis(100)).unwrap_err();
+        assert!(started.elapsed() < Duration::from_secs(4));
+        assert!(
+            error.to_string().contains("took longer than 0.1 seconds"),
+            "{error}"
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn exit_status_decides() {
+        let path = script(
+            "status",
+            r#"test "$WTT_LABELS" = "a,b" && test -z "$WTT_END""#,
+        );
+        run(&path, &session(), TIMEOUT).unwrap();
+        let mut ended = session();
+        ended.end_at = Some(60);
+        let error = run(&path, &ended, TIMEOUT).unwrap_err();
+        assert!(error.to_string().contains("exited with"), "{error}");
+        std::fs::remove_file(path).unwrap();
+    }
+}
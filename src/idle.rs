@@ -0,0 +1,165 @@
+//! Noticing when the user is away from the computer with a session running.
+//!
+//! The idle time comes from xprintidle on X11, or from logind's IdleHint otherwise. When the user
+//! comes back after a long enough idle span, they're asked whether to keep it in the session.
+//! Discarding it records the span as a break of the session, which keeps running.
+
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::Duration as StdDuration,
+};
+
+use chrono::{Duration, Local as LocalTZ};
+use signal_hook::consts::SIGTERM;
+use wtt::{Result, store::Session};
+
+use crate::{format_duration, format_timestamp, handle, handle::StoreHandle, notification, prompt};
+
+const POLL_INTERVAL: StdDuration = StdDuration::from_secs(5);
+
+pub fn run(threshold: Duration, discard: bool) -> Result<()> {
+    // Fail right away on a system without a way to tell the idle time.
+    idle_seconds()?;
+    let stop = Arc::new(AtomicBool::new(false));
+    signal_hook::flag::register(SIGTERM, Arc::clone(&stop))
+        .map_err(|x| format!("Could not listen for SIGTERM. {x}"))?;
+    println!(
+        "Watching for idle spans longer than {}. Stop with Ctrl-C.",
+        format_duration(threshold.num_seconds().max(0) as u64, false, " ")
+    );
+
+    let threshold = threshold.num_seconds();
+    let mut idle_since: Option<i64> = None;
+    while !stop.load(Ordering::Relaxed) {
+        let idle = match idle_seconds() {
+            Ok(x) => x,
+            Err(x) => {
+                log::warn!("{x}");
+                thread::sleep(POLL_INTERVAL);
+                continue;
+            }
+        };
+        let now = LocalTZ::now().timestamp();
+        match idle_since {
+            None if idle >= threshold => {
+                idle_since = Some(now - idle);
+                log::debug!("Idle since {}.", now - idle);
+            }
+            Some(since) if idle < threshold => {
+                idle_since = None;
+                // The watch goes on, the next idle span may be handled fine.
+                if let Err(x) = back_from_idle(since, now - idle, discard) {
+                    eprintln!("{x}");
+                }
+            }
+            _ => {}
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+    println!("Stopped watching.");
+    Ok(())
+}
+
+/// Ask what to do with the idle span of the running session, and make it a break if it's discarded.
+fn back_from_idle(since: i64, until: i64, discard: bool) -> Result<()> {
+    let store = handle::load()?;
+    let Some(session) = store
+        .sessions
+        .iter()
+        // A paused session doesn't count the idle span anyway.
+        .filter(|x| x.end_at.is_none() && !x.is_paused() && x.start_at < since)
+        .max_by_key(|x| x.start_at)
+    else {
+        return Ok(());
+    };
+    let span = format!(
+        "{} idle from {} to {}",
+        format_duration((until - since).max(0) as u64, false, " "),
+        format_timestamp(since, "%H:%M"),
+        format_timestamp(until, "%H:%M"),
+    );
+
+    let discard = if discard {
+        true
+    } else if prompt::is_interactive() {
+        notification::send("Welcome back", &format!("{span}. Answer in the terminal."));
+        prompt::confirm(
+            &format!(
+                "You were {span}. Discard it from the session #{}?",
+                session.seq
+            ),
+            false,
+        )?
+    } else {
+        println!("You were {span}. It was kept, pass --discard to drop such spans.");
+        false
+    };
+    if !discard {
+        return Ok(());
+    }
+    add_break(session, since, until)
+}
+
+fn add_break(session: &Session, since: i64, until: i64) -> Result<()> {
+    let mut store = StoreHandle::open()?;
+    store.add_break(&session.id, since, until)?;
+    handle::success(&format!(
+        "Recorded the idle span as a break of the session #{}.",
+        session.seq
+    ));
+    store.save_as("watch-idle: discard an idle span")
+}
+
+/// How long the user has been idle, in seconds.
+#[cfg(target_os = "linux")]
+fn idle_seconds() -> Result<i64> {
+    use std::process::Command;
+
+    if let Ok(output) = Command::new("xprintidle").output()
+        && output.status.success()
+        && let Ok(milliseconds) = String::from_utf8_lossy(&output.stdout)
+            .trim()
+            .parse::<i64>()
+    {
+        return Ok(milliseconds / 1000);
+    }
+
+    let session = std::env::var("XDG_SESSION_ID").unwrap_or_else(|_| "auto".to_string());
+    let output = Command::new("loginctl")
+        .args([
+            "show-session",
+            &session,
+            "-p",
+            "IdleHint",
+            "-p",
+            "IdleSinceHint",
+        ])
+        .output()
+        .ok()
+        .filter(|x| x.status.success())
+        .ok_or(
+            "Could not tell the idle time. Install xprintidle on X11, or run in a logind session.",
+        )?;
+    let output = String::from_utf8_lossy(&output.stdout);
+    let property = |name: &str| {
+        output
+            .lines()
+            .find_map(|x| x.strip_prefix(&format!("{name}=")))
+            .unwrap_or("")
+            .to_string()
+    };
+    if property("IdleHint") != "yes" {
+        return Ok(0);
+    }
+    let since_micros: i64 = property("IdleSinceHint").parse().unwrap_or(0);
+    Ok((LocalTZ::now().timestamp() - since_micros / 1_000_000).max(0))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn idle_seconds() -> Result<i64> {
+    Err("Idle detection is not supported on this platform. It works on Linux only for now.".into())
+}
@@ -0,0 +1,240 @@
+//! Importing sessions exported by other tools.
+//!
+//! A CSV file is read with a header row. The mapping tells which column holds which session field,
+//! e.g. "start=Start,end=End,labels=Project,note=Description". Every row is validated on its own,
+//! so one bad row is reported and skipped instead of failing the whole import.
+
+use std::io::Read;
+
+use chrono::{DateTime, Local as LocalTZ, NaiveDateTime};
+
+use crate::{Result, config, store::Store};
+
+/// Session fields which can be filled from a column.
+pub const FIELDS: &[&str] = &["start", "end", "labels", "note", "rate", "kind"];
+
+/// Which column (by header name) holds each session field.
+#[derive(Debug, Default)]
+pub struct ColumnMap {
+    pub start: String,
+    pub end: String,
+    pub labels: Option<String>,
+    pub note: Option<String>,
+    pub rate: Option<String>,
+    pub kind: Option<String>,
+}
+
+/// Column indexes in the file, resolved from a `ColumnMap` and the header row.
+struct Columns {
+    start: usize,
+    end: usize,
+    labels: Option<usize>,
+    note: Option<usize>,
+    rate: Option<usize>,
+    kind: Option<usize>,
+}
+
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub imported: u32,
+    /// Rows which were not imported: the line in the file and the reason.
+    pub skipped: Vec<(u64, String)>,
+}
+
+impl ColumnMap {
+    /// Parse a mapping like "start=Start,end=End,labels=Project". start and end are required.
+    pub fn parse(value: &str) -> Result<Self> {
+        let mut start: Option<String> = None;
+        let mut end: Option<String> = None;
+        let mut map = ColumnMap::default();
+
+        for pair in value.split(',').filter(|x| !x.trim().is_empty()) {
+            let Some((field, column)) = pair.split_once('=') else {
+                return Err(format!("Invalid column mapping '{pair}'. Use field=Column.").into());
+            };
+            let column = Some(column.trim().to_string());
+            match field.trim() {
+                "start" => start = column,
+                "end" => end = column,
+                "labels" => map.labels = column,
+                "note" => map.note = column,
+                "rate" => map.rate = column,
+                "kind" => map.kind = column,
+                other => {
+                    return Err(format!(
+                        "Unknown session field {other} in the column mapping. Available: {}.",
+                        FIELDS.join(", ")
+                    )
+                    .into());
+                }
+            }
+        }
+
+        map.start = start.ok_or("The column mapping must have the start field.")?;
+        map.end = end.ok_or("The column mapping must have the end field.")?;
+        Ok(map)
+    }
+
+    fn resolve(&self, headers: &csv::StringRecord) -> Result<Columns> {
+        let find = |name: &str| -> Result<usize> {
+            headers
+                .iter()
+                .position(|x| x.trim() == name)
+                .ok_or_else(|| format!("There is no column {name} in the CSV file.").into())
+        };
+        let find_optional = |name: &Option<String>| -> Result<Option<usize>> {
+            name.as_deref().map(find).transpose()
+        };
+        Ok(Columns {
+            start: find(&self.start)?,
+            end: find(&self.end)?,
+            labels: find_optional(&self.labels)?,
+            note: find_optional(&self.note)?,
+            rate: find_optional(&self.rate)?,
+            kind: find_optional(&self.kind)?,
+        })
+    }
+}
+
+impl Store {
+    /// Import the sessions from CSV data. Fails only if the file itself can't be used.
+    pub fn import_csv(&mut self, reader: impl Read, map: &ColumnMap) -> Result<ImportReport> {
+        let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(reader);
+        let headers = reader
+            .headers()
+            .map_err(|x| format!("Could not read the header row of the CSV file. {x}"))?
+            .clone();
+        let columns = map.resolve(&headers)?;
+
+        let mut report = ImportReport::default();
+        for record in reader.records() {
+            let record = match record {
+                Ok(x) => x,
+                Err(x) => {
+                    let line = x.position().map_or(0, |x| x.line());
+                    report.skipped.push((line, x.to_string()));
+                    continue;
+                }
+            };
+            let line = record.position().map_or(0, |x| x.line());
+            match self.import_record(&record, &columns) {
+                Ok(()) => report.imported += 1,
+                Err(x) => report.skipped.push((line, x.to_string())),
+            }
+        }
+        Ok(report)
+    }
+
+    fn import_record(&mut self, record: &csv::StringRecord, columns: &Columns) -> Result<()> {
+        let cell = |index: usize| record.get(index).unwrap_or("").trim();
+        let optional_cell = |index: Option<usize>| index.map(cell).filter(|x| !x.is_empty());
+
+        let start_at = parse_datetime(cell(columns.start))?;
+        let end_at = parse_datetime(cell(columns.end))?;
+        let labels: Vec<String> = optional_cell(columns.labels)
+            .map(|x| {
+                x.split(',')
+                    .map(|x| x.trim().to_string())
+                    .filter(|x| !x.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let note = optional_cell(columns.note).map(|x| x.to_string());
+        let rate = optional_cell(columns.rate)
+            .map(|x| {
+                x.parse::<f64>()
+                    .map_err(|_| format!("'{x}' is not a valid rate."))
+            })
+            .transpose()?;
+        let kind = optional_cell(columns.kind).map(|x| x.to_string());
+
+        self.add_session(start_at, end_at, labels, note, rate, kind)?;
+        Ok(())
+    }
+}
+
+/// Parse a date with time in the configured format, or in RFC 3339 which many tools export.
+fn parse_datetime(value: &str) -> Result<i64> {
+    if value.is_empty() {
+        return Err("A date is missing.".into());
+    }
+    let format = &config::get().datetime_format.value;
+    if let Ok(x) = NaiveDateTime::parse_from_str(value, format)
+        && let Some(x) = x.and_local_timezone(LocalTZ).earliest()
+    {
+        return Ok(x.timestamp());
+    }
+    if let Ok(x) = DateTime::parse_from_rfc3339(value) {
+        return Ok(x.timestamp());
+    }
+    Err(format!("'{value}' is not a date in the format {format} or RFC 3339.").into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map() -> ColumnMap {
+        ColumnMap::parse("start=From,end=To,labels=Project,note=Description,rate=Rate").unwrap()
+    }
+
+    #[test]
+    fn mapping_names_the_columns() {
+        let map = map();
+        assert_eq!(map.start, "From");
+        assert_eq!(map.end, "To");
+        assert_eq!(map.labels.as_deref(), Some("Project"));
+        assert_eq!(map.kind, None);
+    }
+
+    #[test]
+    fn mapping_needs_start_and_end_and_known_fields() {
+        assert!(ColumnMap::parse("start=From").is_err());
+        assert!(ColumnMap::parse("end=To").is_err());
+        assert!(ColumnMap::parse("start=From,end=To,client=Client").is_err());
+        assert!(ColumnMap::parse("start=From,end").is_err());
+    }
+
+    #[test]
+    fn rows_become_sessions() {
+        let csv = "From,To,Project,Description,Rate\n\
+            2024-03-12T10:00:00Z,2024-03-12T11:30:00Z,\"acme, web\",\"Review, then \"\"ship\"\"\nit\",50\n\
+            2024-03-13T09:00:00Z,2024-03-13T09:45:00Z,,,\n";
+        let mut store = Store::default();
+        let report = store.import_csv(csv.as_bytes(), &map()).unwrap();
+        assert_eq!(report.imported, 2);
+        assert!(report.skipped.is_empty());
+
+        let first = &store.sessions[0];
+        assert_eq!(first.end_at.unwrap() - first.start_at, 5400);
+        assert_eq!(first.labels, vec!["acme", "web"]);
+        assert_eq!(first.note.as_deref(), Some("Review, then \"ship\"\nit"));
+        assert_eq!(first.rate, Some(50.0));
+        let second = &store.sessions[1];
+        assert!(second.labels.is_empty());
+        assert_eq!(second.note, None);
+        assert_ne!(first.id, second.id);
+    }
+
+    #[test]
+    fn bad_rows_are_skipped_with_their_line() {
+        let csv = "From,To,Project,Description,Rate\n\
+            yesterday,2024-03-12T11:30:00Z,,,\n\
+            2024-03-12T10:00:00Z,2024-03-12T11:00:00Z,,,cheap\n\
+            2024-03-12T12:00:00Z,2024-03-12T11:00:00Z,,,\n\
+            2024-03-12T10:00:00Z,2024-03-12T11:00:00Z,,,\n";
+        let mut store = Store::default();
+        let report = store.import_csv(csv.as_bytes(), &map()).unwrap();
+        assert_eq!(report.imported, 1);
+        let lines: Vec<u64> = report.skipped.iter().map(|x| x.0).collect();
+        assert_eq!(lines, vec![2, 3, 4]);
+        assert!(report.skipped[1].1.contains("cheap"));
+    }
+
+    #[test]
+    fn missing_column_fails_the_import() {
+        let mut store = Store::default();
+        let result = store.import_csv("From,Until\n".as_bytes(), &map());
+        assert!(result.is_err());
+    }
+}
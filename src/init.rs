@@ -0,0 +1,190 @@
+//! First-run setup: where the database lives, a starter config file and shell completions.
+//!
+//! Nothing is ever overwritten. An existing database is adopted as is, and an existing config file
+//! only gets the database path added when it doesn't have one.
+
+use std::{fs, path::Path};
+
+use clap::CommandFactory;
+use clap_complete::Shell;
+use wtt::{
+    Result,
+    config::{self, Config, SETTINGS, get_path_to_config_file},
+    store::Store,
+};
+
+use crate::{Cli, completions, prompt};
+
+pub fn run(path: Option<String>, shell: Option<Shell>) -> Result<()> {
+    let default_path = default_database_path();
+    let path = match path {
+        Some(x) => x,
+        None => prompt::ask("Where to keep the database?", &default_path)?,
+    };
+    let path = expand_home(&path);
+
+    let mut config = Config::load()?;
+    config.set_from_cli("storage.path", &path)?;
+    config::set(config)?;
+
+    if Path::new(&path).exists() {
+        // Reading it makes sure it's a database before it's adopted.
+        let store = Store::from_store_file()?;
+        println!(
+            "Found a database with {} sessions at {path}. It will be used as is.",
+            store.sessions.len()
+        );
+    } else {
+        if let Some(parent) = Path::new(&path).parent()
+            && !parent.as_os_str().is_empty()
+        {
+            fs::create_dir_all(parent)
+                .map_err(|x| format!("Could not create the directory {}. {x}", parent.display()))?;
+        }
+        Store::default().save()?;
+        println!("Created an empty database at {path}.");
+    }
+
+    write_config(&path)?;
+
+    let shell = match shell {
+        Some(x) => Some(x),
+        None if prompt::is_interactive() => match Shell::from_env() {
+            Some(x) if prompt::confirm(&format!("Install completions for {x}?"), false)? => Some(x),
+            _ => None,
+        },
+        None => None,
+    };
+    if let Some(shell) = shell {
+        install_completions(shell)?;
+    }
+
+    if let Ok(env) = std::env::var("WTT_PATH_DATABASE") {
+        println!(
+            "Note: WTT_PATH_DATABASE is set to {env}, and it takes precedence over the config file."
+        );
+    }
+    println!();
+    println!("Next steps:");
+    println!("  wtt start -l <label>    start tracking");
+    println!("  wtt stop --note \"...\"   end the session with a note");
+    println!("  wtt session table       see what you've done");
+    println!("  wtt doctor              check the setup if something is off");
+    Ok(())
+}
+
+/// Write a starter config with the database path, or add the path to an existing config.
+fn write_config(database_path: &str) -> Result<()> {
+    let config_path = get_path_to_config_file();
+    let path_line = format!("path = {}", toml::Value::String(database_path.to_string()));
+
+    if !Path::new(&config_path).exists() {
+        if let Some(parent) = Path::new(&config_path).parent() {
+            fs::create_dir_all(parent)
+                .map_err(|x| format!("Could not create the directory {}. {x}", parent.display()))?;
+        }
+        fs::write(&config_path, starter_config(&path_line))
+            .map_err(|x| format!("Could not write the config file {config_path}. {x}"))?;
+        println!("Created the config file {config_path}.");
+        return Ok(());
+    }
+
+    let content = fs::read_to_string(&config_path)
+        .map_err(|x| format!("Could not read the config file {config_path}. {x}"))?;
+    let table: toml::Table = content
+        .parse()
+        .map_err(|x| format!("Could not parse the config file {config_path} as TOML. {x}"))?;
+    let storage = table.get("storage").and_then(|x| x.as_table());
+    match storage.and_then(|x| x.get("path")).and_then(|x| x.as_str()) {
+        Some(x) if expand_home(x) == database_path => {
+            println!("The config file {config_path} already uses this database.");
+        }
+        Some(x) => {
+            println!(
+                "The config file {config_path} points to another database, {x}. It was left as is. \
+                 To switch, set `{path_line}` under [storage]."
+            );
+        }
+        None => {
+            // Edit the text instead of rewriting the table, so the comments are kept.
+            let content = match storage {
+                Some(_) => content.replacen("[storage]", &format!("[storage]\n{path_line}"), 1),
+                None => format!("{}\n[storage]\n{path_line}\n", content.trim_end()),
+            };
+            fs::write(&config_path, content)
+                .map_err(|x| format!("Could not update the config file {config_path}. {x}"))?;
+            println!("Added the database path to the config file {config_path}.");
+        }
+    }
+    Ok(())
+}
+
+/// The config with the database path and the rest of the settings commented out with their defaults.
+fn starter_config(path_line: &str) -> String {
+    let mut content = String::from(
+        "# See `wtt config show` for the effective settings and `wtt man` for all of them.\n",
+    );
+    let mut section = "";
+    for setting in SETTINGS {
+        let (setting_section, name) = setting.key.split_once('.').unwrap();
+        if setting_section != section {
+            section = setting_section;
+            content.push_str(&format!("\n[{section}]\n"));
+        }
+        if setting.key == "storage.path" {
+            content.push_str(&format!("{path_line}\n"));
+            continue;
+        }
+        let is_literal =
+            matches!(setting.default, "true" | "false") || setting.default.parse::<u64>().is_ok();
+        let value = if is_literal {
+            setting.default.to_string()
+        } else {
+            toml::Value::String(setting.default.to_string()).to_string()
+        };
+        content.push_str(&format!("# {}\n# {name} = {value}\n", setting.description));
+    }
+    content
+}
+
+fn install_completions(shell: Shell) -> Result<()> {
+    let home = std::env::var("HOME").unwrap_or_default();
+    let path = match shell {
+        Shell::Bash => format!("{home}/.local/share/bash-completion/completions/wtt"),
+        Shell::Zsh => format!("{home}/.zfunc/_wtt"),
+        Shell::Fish => format!("{home}/.config/fish/completions/wtt.fish"),
+        _ => {
+            println!(
+                "Completions for {shell} can't be installed automatically. See `wtt completions --help`."
+            );
+            return Ok(());
+        }
+    };
+    if let Some(parent) = Path::new(&path).parent() {
+        fs::create_dir_all(parent)
+            .map_err(|x| format!("Could not create the directory {}. {x}", parent.display()))?;
+    }
+    let script = completions::generate(shell, &mut Cli::command());
+    fs::write(&path, script).map_err(|x| format!("Could not write {path}. {x}"))?;
+    println!("Installed the {shell} completions to {path}.");
+    if shell == Shell::Zsh {
+        println!("Make sure ~/.zfunc is in $fpath before compinit in ~/.zshrc.");
+    }
+    Ok(())
+}
+
+/// `$XDG_DATA_HOME/wtt/db.json`, or `~/.local/share/wtt/db.json` when the variable is not set.
+fn default_database_path() -> String {
+    let data_home = match std::env::var("XDG_DATA_HOME") {
+        Ok(x) if !x.is_empty() => x,
+        _ => format!("{}/.local/share", std::env::var("HOME").unwrap_or_default()),
+    };
+    format!("{data_home}/wtt/db.json")
+}
+
+fn expand_home(path: &str) -> String {
+    match path.strip_prefix("~/") {
+        Some(rest) => format!("{}/{rest}", std::env::var("HOME").unwrap_or_default()),
+        None => path.to_string(),
+    }
+}
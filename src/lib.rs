@@ -0,0 +1,18 @@
+//! Woopzzz's Time Tracker.
+//!
+//! The library keeps the data model and the reports, so they can be used without the CLI.
+
+pub mod backend;
+pub mod config;
+pub mod export;
+pub mod import;
+pub mod messages;
+pub mod report;
+pub mod store;
+pub mod suggest;
+pub mod time;
+pub mod today;
+pub mod undo;
+
+pub type Error = Box<dyn std::error::Error>;
+pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,39 @@
+//! A small logger which writes to stderr, so it never mixes with the output of a command.
+//!
+//! Nothing but warnings is logged by default. `-v` enables debug messages and `-vv` trace ones.
+//! Without the flags, the level can be set with WTT_LOG (error, warn, info, debug or trace).
+
+use log::{LevelFilter, Log, Metadata, Record};
+
+struct StderrLogger;
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        // Dependencies have their own logs, which are only noise here.
+        metadata.level() <= log::max_level() && metadata.target().starts_with("wtt")
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!("[{}] {}", record.level(), record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: StderrLogger = StderrLogger;
+
+pub fn init(verbosity: u8) {
+    let level = match verbosity {
+        0 => std::env::var("WTT_LOG")
+            .ok()
+            .and_then(|x| x.parse().ok())
+            .unwrap_or(LevelFilter::Warn),
+        1 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    };
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(level);
+    }
+}
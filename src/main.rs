@@ -1,25 +1,80 @@
-use std::{collections::HashSet, fs};
+use std::{cmp::Reverse, fs, io::Write};
 
-use chrono::{DateTime, Duration, Local as LocalTZ, NaiveDate, NaiveTime, TimeDelta, TimeZone};
-use clap::{Args, Parser, Subcommand};
-use cli_table::{Cell, CellStruct, Style, Table};
-use uuid::Uuid;
+use chrono::{DateTime, Duration, Local as LocalTZ, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+use clap::{Args, CommandFactory, Parser, Subcommand};
+use cli_table::{Cell, CellStruct, Style};
+use unicode_width::UnicodeWidthChar;
+mod batch;
+mod caldav;
+mod clipboard;
+mod completions;
+mod doctor;
+mod editor;
+mod events;
+mod git;
+mod handle;
+mod hooks;
+mod idle;
+mod init;
+mod logger;
+mod man;
+mod nag;
+mod notification;
+mod output;
+mod picker;
+mod pomodoro;
+mod prompt;
+mod remind;
+mod serve;
+mod slack;
+mod status;
+mod template;
+mod toggl;
+mod ui;
+mod webhook;
 
-type Error = Box<dyn std::error::Error>;
-type Result<T> = std::result::Result<T, Error>;
-
-const DATE_FORMAT: &str = "%d.%m.%Y";
-const DATETIME_FORMAT: &str = "%d.%m.%Y %H:%M";
+use handle::StoreHandle;
+use output::Format;
+use template::Template;
+use wtt::{
+    Result,
+    config::{self, Config, TableColumn, TableOrder},
+    export,
+    import::ColumnMap,
+    msg,
+    report::{GroupBy, Report, ReportRow},
+    store::{
+        Filter, LabelMatch, NoteMode, Session, Store, get_path_to_store_file, sorted_labels,
+        write_file_atomically,
+    },
+    time,
+    today::Today,
+    undo::UndoRecord,
+};
 
 #[derive(Parser)]
 #[command(about=concat!(
     "A time tracker. Open a new session, do your job, close the session with a note.\n",
     "\n",
-    "You can specify where to store the database via the environment variable WTT_PATH_DATABASE.",
+    "You can specify where to store the database via the environment variable WTT_PATH_DATABASE\n",
+    "or the --db flag, which takes precedence.",
 ))]
 struct Cli {
     #[command(subcommand)]
     command: MainCommands,
+    /// Do everything and report the result, but don't save any changes.
+    #[arg(long, global = true)]
+    dry_run: bool,
+    /// Log what the app does to stderr. Repeat for more details (-vv).
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+    /// Use this database file instead of the configured one.
+    #[arg(long, global = true, value_name = "PATH")]
+    db: Option<String>,
+    /// The output format. Commands which display data support some of table, json, csv and plain
+    /// (see their help). The rest print plain messages only.
+    #[arg(long, global = true, value_enum)]
+    format: Option<Format>,
 }
 
 #[derive(Subcommand)]
@@ -28,6 +83,398 @@ enum MainCommands {
     Session(SessionArgs),
     /// Manage labels.
     Label(LabelArgs),
+    /// Start a new session. The same as `session start`.
+    Start(StartArgs),
+    /// End a running session. The same as `session end`.
+    Stop(EndArgs),
+    /// Update the note of the running session, or of another one with --id. The same as `session note`.
+    Note(NoteArgs),
+    /// Print the running session in one line for a status bar.
+    ///
+    /// Output formats: plain (default) for polybar and i3blocks, waybar for a custom waybar module
+    /// with "return-type": "json" (the class is running or idle), tmux for
+    /// `set -g status-right '#(wtt status --format tmux)'`.
+    Status {
+        /// The text for a running session. The placeholders are the ones of `session table --template`.
+        #[arg(long, default_value = status::DEFAULT_TEXT)]
+        text: String,
+        /// The text when no session is running.
+        #[arg(long, default_value = "idle")]
+        idle_text: String,
+        /// The tmux color of a running session, e.g. green, colour34 or #00af00.
+        #[arg(long, default_value = "green")]
+        running_color: String,
+        /// The tmux color of the idle text.
+        #[arg(long, default_value = "colour244")]
+        idle_color: String,
+    },
+    /// Work in timed blocks: start a session, count down, end it and take a break, for a number of cycles.
+    ///
+    /// Every work block is a normal session. When a block is over, a desktop notification is shown
+    /// and you're asked for a quick note (Enter skips it). Breaks are not tracked unless
+    /// --track-breaks is given. Ctrl-C ends the current session at that moment.
+    #[command(after_long_help = concat!(
+        "Examples:\n",
+        "  wtt pomodoro --labels deep\n",
+        "  wtt pomodoro --work 50m --break 10m --cycles 3 --track-breaks",
+    ))]
+    Pomodoro {
+        /// The length of a work block, e.g. 25m.
+        #[arg(long, value_name = "DURATION", default_value = "25m", value_parser = parse_duration_arg)]
+        work: Duration,
+        /// The length of a break, e.g. 5m.
+        #[arg(long = "break", value_name = "DURATION", default_value = "5m", value_parser = parse_duration_arg)]
+        pause: Duration,
+        /// The labels of the work sessions. You can provide several ones.
+        #[arg(short, long, value_name = completions::LABEL)]
+        labels: Vec<String>,
+        /// How many work blocks to do. There is a break between each two.
+        #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+        cycles: u32,
+        /// Track the breaks as sessions labeled "break".
+        #[arg(long)]
+        track_breaks: bool,
+    },
+    /// Show a desktop notification when the running session has been running for too long.
+    ///
+    /// Keeps running in the foreground until it gets Ctrl-C or SIGTERM, e.g. as a user service
+    /// or with `wtt remind &`. A new session, or the end of the current one, starts the reminders over.
+    /// The notifications are shown by notify-send on Linux and osascript on macOS.
+    #[command(after_long_help = concat!(
+        "Examples:\n",
+        "  wtt remind --after 50m\n",
+        "  wtt remind --after 1h --repeat 10m &",
+    ))]
+    Remind {
+        /// Remind when the session has been running this long, e.g. 50m or 2h.
+        #[arg(long, value_name = "DURATION", value_parser = parse_duration_arg)]
+        after: Duration,
+        /// Keep reminding this often after the first reminder, until the session ends.
+        #[arg(long, value_name = "DURATION", value_parser = parse_duration_arg)]
+        repeat: Option<Duration>,
+    },
+    /// Watch for the times you're away with a session running, and offer to drop them from it.
+    ///
+    /// Keeps running in the foreground. When you come back after being idle longer than the threshold,
+    /// it asks whether to keep the idle span. Dropping it records the span as a break of the session,
+    /// which keeps running. Linux only: the idle time comes from xprintidle on X11, or from logind.
+    #[command(name = "watch-idle")]
+    WatchIdle {
+        /// How long being idle counts as being away, e.g. 10m.
+        #[arg(long, value_name = "DURATION", default_value = "10m", value_parser = parse_duration_arg)]
+        threshold: Duration,
+        /// Drop the idle spans without asking.
+        #[arg(long)]
+        discard: bool,
+    },
+    /// Open an interactive terminal UI with today's sessions.
+    Ui,
+    /// Revert the last command which modified the database. Only one command can be undone.
+    Undo {
+        /// Only describe what would be undone.
+        #[arg(long)]
+        show: bool,
+    },
+    /// Run commands from a file or stdin, one per line, against the database loaded once.
+    ///
+    /// Every line has the same syntax as the command line without the leading `wtt`,
+    /// e.g. `start -l work` or `session note --id '#12' "Fixed it"`. Empty lines and lines starting
+    /// with # are skipped. The database is saved once, after the last line, and `wtt undo` reverts
+    /// the whole batch. A failed line doesn't change anything. By default it stops the batch
+    /// and nothing is saved.
+    Batch {
+        /// The file with the commands, or - for stdin.
+        #[arg(value_name = "PATH")]
+        input: String,
+        /// Skip the failed lines, save the changes of the rest and list the failures at the end.
+        #[arg(long)]
+        keep_going: bool,
+    },
+    /// Set up the database, a starter config file and optionally shell completions.
+    ///
+    /// Asks where to keep the database, unless --path is given. An existing database at the path
+    /// is used as is, and an existing config file is only extended, never overwritten.
+    Init {
+        /// Where to keep the database.
+        #[arg(long)]
+        path: Option<String>,
+        /// Install the completions for this shell.
+        #[arg(long, value_name = "SHELL")]
+        completions: Option<clap_complete::Shell>,
+    },
+    /// Serve a JSON HTTP API over the sessions, for web UIs and scripts.
+    ///
+    /// Routes: GET /sessions (the filters of `session table` as parameters: from, to, labels,
+    /// match_all, label_prefix, exclude_labels, search, running, last), POST /sessions/start,
+    /// POST /sessions/{id}/end, PATCH /sessions/{id}/note, GET /labels and GET /status. The bodies
+    /// are JSON: {"labels": [...], "kind": ..., "rate": ...} to start and {"note": ...} to end or to
+    /// update the note. Changes are saved like the commands save them, so the CLI can be used at
+    /// the same time. Set WTT_SERVE_TOKEN to require `Authorization: Bearer <token>` for all but
+    /// the GET routes.
+    #[command(after_long_help = concat!(
+        "Examples:\n",
+        "  WTT_SERVE_TOKEN=secret wtt serve --listen 127.0.0.1:7878\n",
+        "  curl -H 'Authorization: Bearer secret' -d '{\"labels\": [\"acme\"]}' localhost:7878/sessions/start\n",
+        "  curl 'localhost:7878/sessions?from=today&labels=acme'",
+    ))]
+    Serve {
+        /// The address and port to listen on, port 0 for any free one. Keep it local unless
+        /// WTT_SERVE_TOKEN is set.
+        #[arg(long, default_value = "127.0.0.1:7878", value_name = "ADDRESS")]
+        listen: String,
+    },
+    /// Export the tracked time to other tools.
+    Export(ExportArgs),
+    /// Send sessions to other time trackers and calendars.
+    Push(PushArgs),
+    /// Check the setup: the config, the database file and the terminal.
+    /// Exits with a non-zero code if anything is broken.
+    Doctor,
+    /// Copy the sessions of a JSON database into the SQLite one.
+    ///
+    /// Set storage.backend to sqlite first. The JSON file is only read, so switching the backend
+    /// back to json gives the old data again.
+    MigrateJson {
+        /// The JSON database file. The default is storage.path.
+        #[arg(long, value_name = "PATH")]
+        from: Option<String>,
+        /// Replace the sessions which are in the SQLite database already.
+        #[arg(long)]
+        force: bool,
+    },
+    /// Inspect the configuration.
+    Config(ConfigArgs),
+    /// Print a shell completion script to stdout.
+    #[command(long_about = concat!(
+        "Print a shell completion script to stdout.\n",
+        "\n",
+        "Bash: add `eval \"$(wtt completions bash)\"` to ~/.bashrc.\n",
+        "Zsh: run `wtt completions zsh > ~/.zfunc/_wtt` and make sure ~/.zfunc is in $fpath before compinit.\n",
+        "Fish: run `wtt completions fish > ~/.config/fish/completions/wtt.fish`.\n",
+        "PowerShell: add `wtt completions powershell | Out-String | Invoke-Expression` to your $PROFILE.",
+    ))]
+    Completions { shell: clap_complete::Shell },
+    /// Print the man page to stdout.
+    #[command(long_about = concat!(
+        "Print the man page to stdout. It covers every command, the settings, the environment variables, ",
+        "the files and the exit codes.\n",
+        "\n",
+        "Install it with `wtt man > ~/.local/share/man/man1/wtt.1`, then read it with `man wtt`.",
+    ))]
+    Man,
+    /// Print values for shell completion scripts.
+    #[command(name = "__complete", hide = true)]
+    Complete { kind: CompleteKind },
+}
+
+#[derive(Args)]
+struct ExportArgs {
+    #[command(subcommand)]
+    command: ExportCommands,
+}
+
+#[derive(Subcommand)]
+enum ExportCommands {
+    /// Write gauges for the node exporter's textfile collector.
+    ///
+    /// The metrics are the running sessions and the seconds tracked per label today, in the last
+    /// 7 days and on each of them. Run it from cron every minute with --output pointing into
+    /// the collector's directory. The file is replaced atomically, so the collector never reads
+    /// a half-written one.
+    #[command(after_long_help = concat!(
+        "Examples:\n",
+        "  wtt export prometheus --output /var/lib/node_exporter/textfile/wtt.prom\n",
+        "  * * * * * wtt export prometheus -o /var/lib/node_exporter/textfile/wtt.prom   (crontab)",
+    ))]
+    Prometheus {
+        /// Write the metrics to this file instead of stdout. It should end with .prom.
+        #[arg(short, long, value_name = "PATH")]
+        output: Option<String>,
+    },
+    /// Write the ended sessions as a CSV for the bulk import of Harvest or Clockify.
+    ///
+    /// The first label of a session found in timesheet.projects decides where it goes, written
+    /// as "Client/Project/Task" (the task is "General" when it's left out). Without any mapping,
+    /// the first label is the project. Sessions which can't be mapped are listed on stderr after
+    /// the export, or fail it with --strict. For Clockify, set timesheet.email to your email.
+    #[command(after_long_help = concat!(
+        "Examples:\n",
+        "  wtt export timesheet --dialect harvest --from 01.06.2025 --to 30.06.2025 -o june.csv\n",
+        "  wtt export timesheet --dialect clockify --from yesterday > clockify.csv",
+    ))]
+    Timesheet {
+        /// The time tracker to import the CSV into.
+        #[arg(long)]
+        dialect: TimesheetDialect,
+        /// Export the sessions which were started this day or later. The range is inclusive.
+        #[arg(
+            long,
+            value_name = time::DAY_VALUE_NAME,
+            allow_hyphen_values = true
+        )]
+        from: Option<String>,
+        /// Export the sessions which were started this day or earlier. The range is inclusive.
+        #[arg(
+            long,
+            value_name = time::DAY_VALUE_NAME,
+            allow_hyphen_values = true
+        )]
+        to: Option<String>,
+        /// Write the CSV to this file instead of stdout.
+        #[arg(short, long, value_name = "PATH")]
+        output: Option<String>,
+        /// Fail without writing anything when some sessions can't be mapped to a project.
+        #[arg(long)]
+        strict: bool,
+    },
+    /// Write the sessions as an org-mode document with CLOCK lines in :LOGBOOK: drawers.
+    ///
+    /// There is a heading per label (a session with several labels is under each of them)
+    /// or per day, and the notes of its sessions follow the drawer. The durations are counted
+    /// in whole minutes the way org does, so `org-clock-report` shows the same totals.
+    /// Running sessions are open clocks.
+    #[command(after_long_help = concat!(
+        "Examples:\n",
+        "  wtt export org --from 01.03.2025 -o ~/org/time.org\n",
+        "  wtt export org --group-by day --from yesterday",
+    ))]
+    Org {
+        /// Make a heading per label or per day.
+        #[arg(long, default_value = "label")]
+        group_by: OrgGroupBy,
+        /// Export the sessions which were started this day or later. The range is inclusive.
+        #[arg(
+            long,
+            value_name = time::DAY_VALUE_NAME,
+            allow_hyphen_values = true
+        )]
+        from: Option<String>,
+        /// Export the sessions which were started this day or earlier. The range is inclusive.
+        #[arg(
+            long,
+            value_name = time::DAY_VALUE_NAME,
+            allow_hyphen_values = true
+        )]
+        to: Option<String>,
+        /// Export the sessions which have at least one of these labels.
+        #[arg(short, long, value_name = completions::LABEL)]
+        labels: Vec<String>,
+        /// Write the document to this file instead of stdout.
+        #[arg(short, long, value_name = "PATH")]
+        output: Option<String>,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum TableGroupBy {
+    Day,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum OrgGroupBy {
+    Label,
+    Day,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum TimesheetDialect {
+    Harvest,
+    Clockify,
+}
+
+#[derive(Args)]
+struct PushArgs {
+    #[command(subcommand)]
+    command: PushCommands,
+}
+
+#[derive(Subcommand)]
+enum PushCommands {
+    /// Create time entries in Toggl Track for the ended sessions, or update the ones pushed before.
+    ///
+    /// The API token is read from WTT_TOGGL_TOKEN and the workspace from toggl.workspace_id.
+    /// A label mapped to a project in toggl.projects sets the project of the entry, the other labels
+    /// become tags and the note becomes the description. With --dry-run it lists what would be sent.
+    /// Exits with 1 when some sessions were not pushed, after listing them with the reasons.
+    #[command(after_long_help = concat!(
+        "Examples:\n",
+        "  wtt --dry-run push toggl --from today\n",
+        "  WTT_TOGGL_TOKEN=... wtt push toggl --from 01.06.2025 --to 30.06.2025",
+    ))]
+    Toggl {
+        /// Push the sessions which were started this day or later. The range is inclusive.
+        #[arg(
+            long,
+            value_name = time::DAY_VALUE_NAME,
+            allow_hyphen_values = true
+        )]
+        from: Option<String>,
+        /// Push the sessions which were started this day or earlier. The range is inclusive.
+        #[arg(
+            long,
+            value_name = time::DAY_VALUE_NAME,
+            allow_hyphen_values = true
+        )]
+        to: Option<String>,
+        /// Push the sessions which have at least one of these labels.
+        #[arg(short, long, value_name = completions::LABEL)]
+        labels: Vec<String>,
+    },
+    /// Put the ended sessions into a CalDAV calendar as events, replacing the ones pushed before.
+    ///
+    /// Every session becomes wtt-<session id>.ics in the calendar, so pushing again is safe.
+    /// The labels are the summary and the categories, the note is the description.
+    /// The credentials are read from WTT_CALDAV_USER and WTT_CALDAV_PASSWORD. Every session
+    /// is reported as created, updated or failed; a refused login stops the push right away.
+    /// Exits with 1 when some sessions were not pushed.
+    #[command(after_long_help = concat!(
+        "Examples:\n",
+        "  wtt push caldav --url https://dav.example.com/calendars/me/work/ --from today\n",
+        "  wtt --dry-run push caldav --url https://dav.example.com/calendars/me/work/ --prune",
+    ))]
+    Caldav {
+        /// The URL of the calendar collection.
+        #[arg(long)]
+        url: String,
+        /// Push the sessions which were started this day or later. The range is inclusive.
+        #[arg(
+            long,
+            value_name = time::DAY_VALUE_NAME,
+            allow_hyphen_values = true
+        )]
+        from: Option<String>,
+        /// Push the sessions which were started this day or earlier. The range is inclusive.
+        #[arg(
+            long,
+            value_name = time::DAY_VALUE_NAME,
+            allow_hyphen_values = true
+        )]
+        to: Option<String>,
+        /// Push the sessions which have at least one of these labels.
+        #[arg(short, long, value_name = completions::LABEL)]
+        labels: Vec<String>,
+        /// Also delete the events pushed for sessions which were deleted since.
+        #[arg(long)]
+        prune: bool,
+    },
+}
+
+#[derive(Args)]
+struct ConfigArgs {
+    #[command(subcommand)]
+    command: ConfigCommands,
+}
+
+#[derive(Subcommand)]
+enum ConfigCommands {
+    /// Display the effective configuration and where each value came from.
+    Show {},
+}
+
+#[derive(clap::ValueEnum, Clone)]
+enum CompleteKind {
+    /// All labels, the most recently used first.
+    Labels,
 }
 
 #[derive(Args)]
@@ -39,42 +486,524 @@ struct SessionArgs {
 #[derive(Subcommand)]
 enum SessionCommands {
     /// Display all sessions in a table format.
+    ///
+    /// Output formats: table (default), json, csv, plain.
+    #[command(after_long_help = concat!(
+        "Examples:\n",
+        "  wtt session table --from today\n",
+        "  wtt session table --from 01.06.2025 --to 30.06.2025 -l acme --clamp-to-range\n",
+        "  wtt session table --label-prefix client --save-query   (then later: --repeat)\n",
+        "  wtt session table --template \"{start:%H:%M} {duration:hm} {labels} {note}\"",
+    ))]
     Table {
         /// Display the sessions which were started this day or later. The range is inclusive.
-        #[arg(long, value_name = "dd.mm.yyyy or today or yesterday")]
+        /// A time can follow the date, e.g. "12.03.2024 14:00".
+        #[arg(
+            long,
+            value_name = time::DAY_VALUE_NAME,
+            allow_hyphen_values = true
+        )]
         from: Option<String>,
         /// Display the sessions which were started this day or earlier. The range is inclusive.
-        #[arg(long, value_name = "dd.mm.yyyy")]
+        /// A time can follow the date, then the range ends at that time.
+        #[arg(
+            long,
+            value_name = time::DAY_VALUE_NAME,
+            allow_hyphen_values = true
+        )]
         to: Option<String>,
-        /// Display the sessions which have at least one of these labels.
-        #[arg(short, long)]
+        /// Display the sessions which have at least one of these labels,
+        /// or all of them with --match-all.
+        #[arg(short, long, value_name = completions::LABEL)]
+        labels: Vec<String>,
+        /// Display only the sessions which have every one of --labels.
+        #[arg(long, requires = "labels")]
+        match_all: bool,
+        /// Display the sessions which have at least one label under one of these prefixes.
+        /// For example, "client" matches "client", "client/acme" and "client/acme/web".
+        #[arg(long = "label-prefix", value_name = "PREFIX")]
+        label_prefixes: Vec<String>,
+        /// Hide the sessions which have any of these labels, even when they match --labels.
+        #[arg(long = "exclude-labels", value_name = completions::LABEL)]
+        exclude_labels: Vec<String>,
+        /// Display the sessions whose note contains this text, ignoring the case.
+        #[arg(long, value_name = "TEXT")]
+        search: Option<String>,
+        /// Display only the sessions which are still running.
+        #[arg(long, conflicts_with = "ended")]
+        running: bool,
+        /// Display only the sessions which have ended.
+        #[arg(long)]
+        ended: bool,
+        /// Display only this many sessions, the ones started last, after the other filters.
+        #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+        last: Option<u32>,
+        /// Count only the part of each session that falls inside the --from / --to range,
+        /// including the sessions which only partly fall inside it.
+        #[arg(long)]
+        clamp_to_range: bool,
+        /// Show a session which crosses midnight as a row per day, each with its part of the time.
+        #[arg(long)]
+        split_days: bool,
+        /// Add a subtotal after the sessions of each day. Implies --split-days.
+        #[arg(long, value_enum)]
+        group_by: Option<TableGroupBy>,
+        /// Also display the total as the length of the union of the sessions,
+        /// so overlapping sessions are not counted twice.
+        #[arg(long)]
+        wallclock: bool,
+        /// Reuse the filters saved by the last run with --save-query.
+        #[arg(long, conflicts_with_all = [
+            "from", "to", "labels", "match_all", "label_prefixes", "exclude_labels", "search",
+            "running", "ended", "last",
+        ])]
+        repeat: bool,
+        /// Remember the filters of this run, so they can be reapplied with --repeat.
+        #[arg(long)]
+        save_query: bool,
+        /// Print every session on its own line using this template instead of a table,
+        /// e.g. "{id:8} {start:%H:%M} {duration:hm} {labels} {note}".
+        /// Fields: id, start, end, duration (text, hm, short, m, s), labels, note, rate, kind.
+        #[arg(long)]
+        template: Option<String>,
+        /// The columns of the table, e.g. "weekday,start,labels,duration,note".
+        /// Columns: number, id, weekday, start, end, labels, duration, note, kind, rate.
+        /// The default comes from `display.table_columns` (WTT_TABLE_COLUMNS).
+        // The full path keeps clap from treating the Vec as repeated values, it's parsed at once.
+        #[arg(long, value_name = "COLUMN,...", value_parser = config::parse_table_columns)]
+        columns: Option<std::vec::Vec<TableColumn>>,
+        /// The same as --format json: the sessions with their durations in seconds.
+        #[arg(long, conflicts_with = "format")]
+        json: bool,
+    },
+    /// Display the amounts to bill per label.
+    ///
+    /// Output formats: table (default), json, csv.
+    #[command(after_long_help = concat!(
+        "Examples:\n",
+        "  wtt session billing --from 01.06.2025 --to 30.06.2025 --rate 50\n",
+        "  wtt session billing -l acme --format csv > acme.csv",
+    ))]
+    Billing {
+        /// Bill the sessions which were started this day or later. The range is inclusive.
+        #[arg(
+            long,
+            value_name = time::DAY_VALUE_NAME,
+            allow_hyphen_values = true
+        )]
+        from: Option<String>,
+        /// Bill the sessions which were started this day or earlier. The range is inclusive.
+        #[arg(
+            long,
+            value_name = time::DAY_VALUE_NAME,
+            allow_hyphen_values = true
+        )]
+        to: Option<String>,
+        /// Bill the sessions which have at least one of these labels.
+        #[arg(short, long, value_name = completions::LABEL)]
         labels: Vec<String>,
+        /// An hourly rate for the sessions that don't have their own rate.
+        #[arg(long)]
+        rate: Option<f64>,
+    },
+    /// Display the total duration per day, label or kind.
+    ///
+    /// Days are listed in order, labels and kinds from the longest to the shortest.
+    /// Output formats: table (default), json, csv.
+    #[command(after_long_help = concat!(
+        "Examples:\n",
+        "  wtt session summary --by day --from yesterday\n",
+        "  wtt session summary --by label --split --from -7d\n",
+        "  wtt session summary --by kind -l acme",
+    ))]
+    Summary {
+        /// What to split the total by.
+        #[arg(long, value_enum)]
+        by: SummaryBy,
+        /// Summarize the sessions which were started this day or later. The range is inclusive.
+        #[arg(
+            long,
+            value_name = time::DAY_VALUE_NAME,
+            allow_hyphen_values = true
+        )]
+        from: Option<String>,
+        /// Summarize the sessions which were started this day or earlier. The range is inclusive.
+        #[arg(
+            long,
+            value_name = time::DAY_VALUE_NAME,
+            allow_hyphen_values = true
+        )]
+        to: Option<String>,
+        /// Summarize the sessions which have at least one of these labels.
+        #[arg(short, long, value_name = completions::LABEL)]
+        labels: Vec<String>,
+        /// Count the time of a session which crosses midnight towards the day it fell on.
+        #[arg(long)]
+        split_days: bool,
+        /// With --by label, divide a session with several labels evenly between them instead of
+        /// counting its whole time for each, so the label totals add up to the total.
+        #[arg(long)]
+        split: bool,
+    },
+    /// Import ended sessions from a CSV file with a header row.
+    ///
+    /// Every row becomes an ended session. Rows which can't be imported (a missing date,
+    /// an end before the start, an unknown kind) are reported and skipped.
+    #[command(after_long_help = concat!(
+        "Examples:\n",
+        "  wtt session import --csv toggl.csv --map \"start=Start date,end=End date,labels=Project,note=Description\"\n",
+        "  wtt --dry-run session import --csv export.csv --map start=From,end=To   (check before importing)",
+    ))]
+    Import {
+        /// The CSV file to import.
+        #[arg(long, value_name = "PATH")]
+        csv: String,
+        /// Which column holds which session field, e.g. "start=Start,end=End,labels=Project,note=Description".
+        /// Fields: start and end (required), labels, note, rate, kind.
+        /// Dates are parsed with the configured datetime format or as RFC 3339.
+        #[arg(long, value_name = "FIELD=COLUMN,...")]
+        map: String,
+    },
+    /// Display how much time was tracked today.
+    ///
+    /// Output formats: plain (default), json.
+    Today {
+        /// Read the totals from the sidecar file kept with `storage.today_file`,
+        /// instead of the whole database. It is recomputed if it's out of date.
+        #[arg(long)]
+        fast: bool,
     },
     /// Start a new session.
-    Start {
+    Start(StartArgs),
+    /// End a running session.
+    End(EndArgs),
+    /// Update the note of a session.
+    Note(NoteArgs),
+    /// Display the running session that was started last.
+    ///
+    /// Output formats: plain (default), json. The --pomodoro watch is plain only.
+    Current {
+        /// Keep watching the session in blocks of this many minutes, announcing every completed block.
+        /// Stops when the session ends.
+        #[arg(long, value_name = "MINUTES", value_parser = clap::value_parser!(u32).range(1..))]
+        pomodoro: Option<u32>,
+    },
+    /// Add a session which has already ended, e.g. one tracked on paper.
+    Add {
+        /// When the session started: "2h ago", "14:30" or a date with time.
+        #[arg(long, value_name = "TIME")]
+        start: String,
+        /// When the session ended, after the start and not in the future.
+        #[arg(long, value_name = "TIME")]
+        end: String,
         /// A way to categorize sessions. You can provide several ones.
-        #[arg(short, long)]
+        #[arg(short, long, value_name = completions::LABEL)]
+        labels: Vec<String>,
+        /// A message describing what you've done.
+        #[arg(long)]
+        note: Option<String>,
+        /// An hourly rate of this session. It overrides the rate given to the billing command.
+        #[arg(long)]
+        rate: Option<f64>,
+        /// The type of activity, e.g. focus or meeting.
+        #[arg(long)]
+        kind: Option<String>,
+    },
+    /// End the running session that was started last and start a new one, saved at once.
+    /// With nothing running, only the new session is started.
+    Switch {
+        /// The labels of the new session. The default comes from `behavior.default_labels`.
+        #[arg(short, long, value_name = completions::LABEL)]
         labels: Vec<String>,
+        /// The note of the ended session. Its current note is kept if not given.
+        #[arg(long)]
+        note: Option<String>,
+        /// The kind of the new session, e.g. focus or meeting.
+        #[arg(long)]
+        kind: Option<String>,
     },
-    /// End a running session.
-    End {
-        /// A running session identifier. If not provided, the running session that was started last will be ended.
+    /// Display the sessions which run at the same time, so their time is counted twice.
+    Overlaps {},
+    /// Display the running sessions and how long they have been running, for shell prompts.
+    /// Exits with 1 when nothing is running. See also `wtt status` for status bars.
+    Status {
+        /// Print nothing, only tell by the exit code whether a session is running.
+        #[arg(short, long)]
+        quiet: bool,
+    },
+    /// Display one session in full, with the whole note.
+    ///
+    /// Output formats: plain (default), json.
+    Show {
+        /// A session identifier, its number (#12) or a unique prefix of the id (4+ characters).
+        #[arg(long)]
+        id: String,
+    },
+    /// Take a break in a running session. The break doesn't count towards its duration.
+    Pause {
+        /// A running session identifier, its number (#12) or a unique prefix of the id (4+ characters).
+        /// If not provided, the running session that was started last is paused.
         #[arg(long)]
         id: Option<String>,
-        /// Leave a message describing what you've done.
+    },
+    /// End the break of a paused session. Ending a paused session ends the break too.
+    Unpause {
+        /// A running session identifier, its number (#12) or a unique prefix of the id (4+ characters).
+        /// If not provided, the running session that was started last is unpaused.
         #[arg(long)]
-        note: Option<String>,
+        id: Option<String>,
     },
-    /// Update the note of a session.
-    Note {
-        /// A running session identifier.
+    /// Split a session in two, e.g. when it turns out to be two different tasks.
+    /// The second part is a new session with the same labels and note.
+    Split {
+        /// A session identifier, its number (#12) or a unique prefix of the id (4+ characters).
+        #[arg(long)]
+        id: String,
+        /// Where to split: "10m ago", "14:30" or a date with time. It must be inside the session.
+        #[arg(long, value_name = "TIME")]
+        at: String,
+        /// Split a locked session too.
+        #[arg(long)]
+        force: bool,
+    },
+    /// Merge ended sessions into one, e.g. back-to-back sessions of the same work.
+    /// The first one is kept and spans all of them; the others are deleted.
+    Merge {
+        /// The sessions to merge, by id, number (#12) or a unique prefix of the id. Repeat it.
+        #[arg(long = "id", required = true)]
+        ids: Vec<String>,
+        /// Refuse to merge when there is a longer gap between the sessions.
+        #[arg(long, value_name = "MINUTES", default_value_t = 15)]
+        max_gap: u32,
+        /// Merge despite a gap, and merge locked sessions too.
+        #[arg(long)]
+        force: bool,
+    },
+    /// Continue an ended session, e.g. after a break. It runs again from its original start,
+    /// or with --new a new session with the same labels is started.
+    Resume {
+        /// A session identifier, its number (#12) or a unique prefix of the id (4+ characters).
+        /// If not provided, the session that ended last is resumed.
+        #[arg(long)]
+        id: Option<String>,
+        /// Start a new session with the labels, rate and kind of the ended one.
+        #[arg(long)]
+        new: bool,
+        /// Reopen a locked session too.
+        #[arg(long, conflicts_with = "new")]
+        force: bool,
+    },
+    /// Start a new session with the labels, rate and kind of an ended one.
+    /// The same as `session resume --new`.
+    Restart {
+        /// A session identifier, its number (#12) or a unique prefix of the id (4+ characters).
+        /// If not provided, the labels of the session that ended last are used.
+        #[arg(long)]
+        id: Option<String>,
+    },
+    /// Discard a running session, e.g. one started by mistake. It is removed, not ended.
+    Cancel {
+        /// A running session identifier, its number (#12) or a unique prefix of the id (4+ characters).
+        /// If not provided, the running session that was started last is discarded.
+        #[arg(long)]
+        id: Option<String>,
+    },
+    /// Change when a session started or ended, e.g. after starting the tracker late.
+    /// Giving --end to a running session ends it, after a confirmation.
+    #[command(group(clap::ArgGroup::new("times").required(true).multiple(true).args(["start", "end"])))]
+    Edit {
+        /// A session identifier, its number (#12) or a unique prefix of the id (4+ characters).
         #[arg(long)]
         id: String,
+        /// The new start: "10m ago", "14:30" or a date with time.
+        #[arg(long, value_name = "TIME")]
+        start: Option<String>,
+        /// The new end: "10m ago", "14:30" or a date with time.
+        #[arg(long, value_name = "TIME")]
+        end: Option<String>,
+        /// Do not ask for confirmation before ending a running session.
+        #[arg(short, long)]
+        yes: bool,
+        /// Edit a locked session too.
+        #[arg(long)]
+        force: bool,
+    },
+    /// Delete one session by its id, or the ended sessions which were started before a date.
+    /// Running sessions are deleted only by id and with --force.
+    #[command(group(clap::ArgGroup::new("target").required(true).args(["older_than", "id"])))]
+    Delete {
+        /// Delete the sessions which were started before this day.
+        #[arg(
+            long,
+            value_name = time::DAY_VALUE_NAME,
+            allow_hyphen_values = true
+        )]
+        older_than: Option<String>,
+        /// Delete one session, given by its id, number (#12) or a unique prefix of the id.
+        #[arg(long, conflicts_with = "label")]
+        id: Option<String>,
+        /// Delete only the sessions which have this label.
+        #[arg(short, long, value_name = completions::LABEL)]
+        label: Option<String>,
+        /// Do not ask for confirmation.
+        #[arg(short, long)]
+        yes: bool,
+        /// Delete locked sessions too, and with --id a running session.
+        #[arg(long)]
+        force: bool,
+    },
+    /// Lock ended sessions, so they are not modified by accident (e.g. after they were invoiced).
+    /// Locked sessions can still be modified with --force.
+    #[command(group(clap::ArgGroup::new("target").required(true).args(["before", "id"])))]
+    Lock {
+        /// Lock the ended sessions which were started before this day.
+        #[arg(
+            long,
+            value_name = time::DAY_VALUE_NAME,
+            allow_hyphen_values = true
+        )]
+        before: Option<String>,
+        /// Lock one ended session, given by its id, number (#12) or a unique prefix of the id.
+        #[arg(long)]
+        id: Option<String>,
+    },
+    /// Unlock sessions locked by the lock command.
+    #[command(group(clap::ArgGroup::new("target").required(true).args(["before", "id"])))]
+    Unlock {
+        /// Unlock the sessions which were started before this day.
+        #[arg(
+            long,
+            value_name = time::DAY_VALUE_NAME,
+            allow_hyphen_values = true
+        )]
+        before: Option<String>,
+        /// Unlock one session, given by its id, number (#12) or a unique prefix of the id.
+        #[arg(long)]
+        id: Option<String>,
+    },
+    /// Add or remove a label of one session, e.g. to fix a typo.
+    Label(SessionLabelArgs),
+}
 
-        text: String,
+#[derive(Args)]
+struct SessionLabelArgs {
+    #[command(subcommand)]
+    command: SessionLabelCommands,
+}
+
+#[derive(Subcommand)]
+enum SessionLabelCommands {
+    /// Add a label to a session. Nothing changes if the session has it already.
+    Add {
+        /// A session identifier, its number (#12) or a unique prefix of the id (4+ characters).
+        #[arg(long)]
+        id: String,
+        #[arg(value_name = completions::LABEL)]
+        name: String,
+        /// Change a locked session too.
+        #[arg(long)]
+        force: bool,
+    },
+    /// Remove a label from a session.
+    Remove {
+        /// A session identifier, its number (#12) or a unique prefix of the id (4+ characters).
+        #[arg(long)]
+        id: String,
+        #[arg(value_name = completions::LABEL)]
+        name: String,
+        /// Change a locked session too.
+        #[arg(long)]
+        force: bool,
     },
 }
 
+#[derive(Args)]
+struct StartArgs {
+    /// A way to categorize sessions. You can provide several ones.
+    #[arg(short, long, value_name = completions::LABEL)]
+    labels: Vec<String>,
+    /// An hourly rate of this session. It overrides the rate given to the billing command.
+    #[arg(long)]
+    rate: Option<f64>,
+    /// The type of activity, e.g. focus or meeting. The allowed kinds are set by `behavior.kinds`.
+    #[arg(long)]
+    kind: Option<String>,
+    /// Copy the id of the new session to the clipboard. See also `behavior.copy_id_on_start`.
+    #[arg(long)]
+    copy_id: bool,
+    /// When the session started, if not now: "10m ago", "2h ago", "14:30" or a date with time.
+    #[arg(long, value_name = "TIME")]
+    at: Option<String>,
+    /// Record the git repository and branch of the current directory.
+    #[arg(long)]
+    git: bool,
+    /// Show the session in the Slack status until it ends. See also `slack.enabled`.
+    #[arg(long)]
+    slack: bool,
+    /// The text of the Slack status, e.g. "{label} until {eta}". Implies --slack.
+    #[arg(long, value_name = "TEMPLATE")]
+    slack_template: Option<String>,
+}
+
+#[derive(Args)]
+struct EndArgs {
+    /// A running session identifier, its number (#12) or a unique prefix of the id (4+ characters).
+    /// If not provided, the running session that was started last will be ended.
+    #[arg(long)]
+    id: Option<String>,
+    /// Choose the running session to end interactively.
+    #[arg(long, conflicts_with = "id")]
+    pick: bool,
+    /// Leave a message describing what you've done.
+    #[arg(long)]
+    note: Option<String>,
+    /// When the session ended, if not now: "10m ago", "2h ago", "14:30" or a date with time.
+    #[arg(long, value_name = "TIME")]
+    at: Option<String>,
+    /// Write the note in $VISUAL or $EDITOR, for longer write-ups.
+    #[arg(long, conflicts_with = "note")]
+    edit: bool,
+    /// With --edit, end the session without a note when the note is left empty, without asking.
+    #[arg(short, long, requires = "edit")]
+    yes: bool,
+    /// Add the commits made on the session's git branch while it ran to the note.
+    #[arg(long)]
+    git: bool,
+    /// Snap the stored start time down to a grid of this many minutes.
+    #[arg(long, value_name = "MINUTES", value_parser = clap::value_parser!(u32).range(1..))]
+    round_start: Option<u32>,
+    /// Snap the stored end time up to a grid of this many minutes.
+    #[arg(long, value_name = "MINUTES", value_parser = clap::value_parser!(u32).range(1..))]
+    round_end: Option<u32>,
+}
+
+#[derive(Args)]
+struct NoteArgs {
+    /// A session identifier, its number (#12) or a unique prefix of the id (4+ characters).
+    /// If not provided, the only running session is updated.
+    #[arg(long)]
+    id: Option<String>,
+    /// Choose one of the recent sessions interactively.
+    #[arg(long, conflicts_with = "id")]
+    pick: bool,
+
+    text: String,
+    /// Add the text to the end of the note instead of replacing it, after behavior.note_separator
+    /// (a line break by default).
+    #[arg(long)]
+    append: bool,
+    /// Update the note even if the session is locked.
+    #[arg(long)]
+    force: bool,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum SummaryBy {
+    Day,
+    Label,
+    Kind,
+}
+
 #[derive(Args)]
 struct LabelArgs {
     #[command(subcommand)]
@@ -84,396 +1013,2172 @@ struct LabelArgs {
 #[derive(Subcommand)]
 enum LabelCommands {
     /// Display a list of all labels.
+    ///
+    /// Output formats: plain (default), json, csv, table.
     List {},
     /// Remove a label from all sessions.
-    Remove { name: String },
+    Remove {
+        #[arg(value_name = completions::LABEL)]
+        name: String,
+        /// Remove the label from locked sessions too.
+        #[arg(long)]
+        force: bool,
+        /// Do not ask for confirmation.
+        #[arg(short, long)]
+        yes: bool,
+    },
     /// Merge one or more source labels into one target label.
     /// Source labels will be removed from all sessions that have them,
     /// and the target label will be added to those sessions.
     Merge {
         /// Labels that will be removed.
-        #[arg(short, long)]
+        #[arg(short, long, value_name = completions::LABEL)]
         source: Vec<String>,
         /// A label that will be added to the sessions that have source labels.
+        #[arg(value_name = completions::LABEL)]
         target: String,
+        /// Merge the labels of locked sessions too.
+        #[arg(long)]
+        force: bool,
+        /// Do not ask for confirmation.
+        #[arg(short, long)]
+        yes: bool,
     },
 }
 
+/// The filters of the table command that can be saved and reapplied later.
+/// They are kept in a separate file next to the database.
 #[derive(serde::Deserialize, serde::Serialize, Debug)]
-struct Store {
-    sessions: Vec<Session>,
+struct LastQuery {
+    from: Option<String>,
+    to: Option<String>,
+    labels: Vec<String>,
+    #[serde(default)]
+    match_all: bool,
+    label_prefixes: Vec<String>,
+    #[serde(default)]
+    exclude_labels: Vec<String>,
+    search: Option<String>,
+    /// Only the running sessions (true) or only the ended ones (false).
+    running: Option<bool>,
+    #[serde(default)]
+    last: Option<u32>,
 }
 
-impl Store {
-    fn from_store_file() -> Result<Self> {
-        let path = get_path_to_store_file();
+impl LastQuery {
+    fn from_state_file() -> Result<Self> {
+        let path = get_path_to_last_query_file();
 
-        let file_exists = fs::exists(&path)
-            .map_err(|x| format!("Could not check the database file {}. {}", &path, x))?;
+        let file_exists =
+            fs::exists(&path).map_err(|x| msg!("query.check_failed", path = path, error = x))?;
         if !file_exists {
-            return Ok(Self { sessions: vec![] });
+            return Err(msg!("query.none_saved").into());
         }
 
-        let file = std::fs::File::open(&path)
-            .map_err(|x| format!("Could not open the database file {}. {}", &path, x))?;
-        let reader = std::io::BufReader::new(file);
-        let store: Store = serde_json::from_reader(reader)
-            .map_err(|x| format!("Could not parse the database file as JSON data. {x}"))?;
-        Ok(store)
+        let content = std::fs::read_to_string(&path)
+            .map_err(|x| msg!("query.open_failed", path = path, error = x))?;
+        let query: LastQuery =
+            serde_json::from_str(&content).map_err(|x| msg!("query.parse_failed", error = x))?;
+        Ok(query)
     }
 
     fn save(&self) -> Result<()> {
-        let path = get_path_to_store_file();
-        let store_json = serde_json::to_string(self)
-            .map_err(|x| format!("Could not create a JSON string from the store. {x}"))?;
-        std::fs::write(&path, store_json).map_err(|x| {
-            format!(
-                "Could not dump the JSON string into the database file {}. {}",
-                &path, x
-            )
-        })?;
+        let path = get_path_to_last_query_file();
+        let query_json =
+            serde_json::to_string(self).map_err(|x| msg!("query.serialize_failed", error = x))?;
+        std::fs::write(&path, query_json)
+            .map_err(|x| msg!("query.write_failed", path = path, error = x))?;
         Ok(())
     }
+}
 
-    fn get_all_sessions(
-        &self,
-        from_timestamp: Option<i64>,
-        to_timestamp: Option<i64>,
-        labels: &[String],
-    ) -> Vec<&Session> {
-        let labelset: HashSet<&str> = labels.iter().map(|x| x.as_str()).collect();
-        let mut sessions: Vec<&Session> = self
-            .sessions
-            .iter()
-            .filter(|session| {
-                if let Some(ft) = from_timestamp
-                    && ft > session.start_at
-                {
-                    return false;
-                }
-
-                if let Some(tt) = to_timestamp
-                    && let Some(ttx) = session.end_at
-                    && tt < ttx
-                {
-                    return false;
-                }
-
-                if labelset.len() > 0
-                    && !session.labels.iter().any(|x| labelset.contains(x.as_str()))
-                {
-                    return false;
-                }
+fn get_path_to_last_query_file() -> String {
+    format!("{}.last-query", get_path_to_store_file())
+}
 
-                return true;
-            })
-            .collect();
-        sessions.sort_by_key(|x| x.start_at);
-        sessions
-    }
-
-    fn start_session(&mut self, labels: Vec<String>) -> Result<&Session> {
-        let id = Uuid::new_v4();
-        let now: DateTime<_> = LocalTZ::now();
-        let session = Session {
-            id: id.to_string(),
-            start_at: now.timestamp(),
-            end_at: None,
-            note: None,
-            labels: labels,
-        };
-        self.sessions.push(session);
-        Ok(self.sessions.last().unwrap())
-    }
+fn get_pprint_note_cell_maxlength() -> u16 {
+    config::get().note_width.value
+}
 
-    fn end_session(&mut self, id: Option<&str>, note: Option<String>) -> Result<&Session> {
-        let session: &mut Session = match id {
-            Some(session_id) => {
-                let session = self.get_session_by_id(session_id)?;
-                if session.end_at.is_some() {
-                    return Err(format!("The session {session_id} has already ended.").into());
-                }
-                session
-            }
-            None => self.get_newest_running_session()?,
-        };
+fn date_format() -> &'static str {
+    &config::get().date_format.value
+}
 
-        let now: DateTime<_> = LocalTZ::now();
-        session.end_at = Some(now.timestamp());
-        session.note = note;
+fn datetime_format() -> &'static str {
+    &config::get().datetime_format.value
+}
 
-        Ok(session)
-    }
+fn print_sessions(
+    query: LastQuery,
+    clamp_to_range: bool,
+    split_days: bool,
+    group_by: Option<TableGroupBy>,
+    wallclock: bool,
+    template: Option<&Template>,
+    columns: Option<Vec<TableColumn>>,
+) -> wtt::Result<()> {
+    let LastQuery {
+        from,
+        to,
+        labels,
+        match_all,
+        label_prefixes,
+        exclude_labels,
+        search,
+        running,
+        last,
+    } = query;
+    let from_timestamp: Option<i64> = from.as_deref().map(try_get_from_timestamp).transpose()?;
+    let to_timestamp: Option<i64> = to.as_deref().map(try_get_to_timestamp).transpose()?;
+    log::debug!(
+        "Parsed --from {from:?} as {from_timestamp:?} and --to {to:?} as {to_timestamp:?}."
+    );
 
-    fn update_note(&mut self, id: &str, note: String) -> Result<()> {
-        let session = self.get_session_by_id(id)?;
-        session.note = Some(note);
-        Ok(())
+    let filter = Filter {
+        from: from_timestamp,
+        to: to_timestamp,
+        labels,
+        label_match: match match_all {
+            true => LabelMatch::All,
+            false => LabelMatch::Any,
+        },
+        label_prefixes,
+        exclude_labels,
+        search,
+        running,
+        last,
+        clamp_to_range,
+        split_days,
+    };
+    let store = handle::load()?;
+    hint_unknown_labels(&store, &filter.labels);
+    hint_unknown_labels(&store, &filter.exclude_labels);
+    let mut report = match group_by {
+        Some(TableGroupBy::Day) => {
+            let mut report = store.report(&filter, GroupBy::Day);
+            // The parts of a session split at midnight follow it, so put them among their days.
+            report.rows.sort_by_key(|x| x.start_at);
+            report
+        }
+        None => store.report(&filter, GroupBy::None),
+    };
+    if config::get().table_order.value == TableOrder::Desc {
+        report.rows.reverse();
     }
 
-    fn get_session_by_id(&mut self, id: &str) -> Result<&mut Session> {
-        match self.sessions.iter_mut().find(|x| x.id == id) {
-            Some(x) => Ok(x),
-            None => Err(format!("The session {id} was not found.").into()),
+    if let Some(template) = template {
+        for row in report.rows.iter() {
+            println!("{}", template.render(row));
         }
+        return Ok(());
     }
 
-    fn get_newest_running_session(&mut self) -> Result<&mut Session> {
-        let mut running_session_info: Vec<&mut Session> = self
-            .sessions
-            .iter_mut()
-            .filter_map(|x| x.end_at.is_none().then_some(x))
-            .collect();
-        running_session_info.sort_by_key(|x| x.start_at);
-        match running_session_info.pop() {
-            Some(x) => Ok(x),
-            None => Err("There is no running session.".into()),
+    match output::format() {
+        Format::Json => output::print_json(&report)?,
+        Format::Csv => {
+            let rows: Vec<Vec<String>> = report
+                .rows
+                .iter()
+                .map(|row| {
+                    vec![
+                        row.id.clone(),
+                        row.seq.to_string(),
+                        format_rfc3339(row.start_at),
+                        row.end_at.map(format_rfc3339).unwrap_or_default(),
+                        row.duration.to_string(),
+                        row.labels.join(","),
+                        row.note.clone().unwrap_or_default(),
+                        row.rate.map(|x| x.to_string()).unwrap_or_default(),
+                        row.kind.clone().unwrap_or_default(),
+                    ]
+                })
+                .collect();
+            output::print_csv(
+                &[
+                    "id", "seq", "start", "end", "duration", "labels", "note", "rate", "kind",
+                ],
+                &rows,
+            )?;
+        }
+        Format::Plain => {
+            for (index, row) in report.rows.iter().enumerate() {
+                let end = row.end_at.map(|x| format_timestamp(x, datetime_format()));
+                let note = row.note.as_deref().unwrap_or("").replace('\n', " ");
+                println!(
+                    "#{}\t{}\t{}\t{}\t{}\t{}",
+                    row.seq,
+                    format_timestamp(row.start_at, datetime_format()),
+                    end.unwrap_or_default(),
+                    format_duration(row.duration, row.end_at.is_none(), " "),
+                    row.labels.join(", "),
+                    note,
+                );
+                if let Some(subtotal) = day_subtotal(&report, index) {
+                    println!(
+                        "{}",
+                        msg!(
+                            "total.day",
+                            day = format_timestamp(row.start_at, date_format()),
+                            duration = format_duration(subtotal, false, " ")
+                        )
+                    );
+                }
+            }
+        }
+        _ if filter.running == Some(true) && report.rows.is_empty() => {
+            println!("{}", msg!("session.none_running_table"));
+        }
+        _ => {
+            let columns = columns.unwrap_or_else(|| config::get().table_columns.value.clone());
+            let mut rows: Vec<Vec<CellStruct>> = vec![];
+            for (index, row) in report.rows.iter().enumerate() {
+                rows.push(columns.iter().map(|x| table_cell(row, *x)).collect());
+                if let Some(subtotal) = day_subtotal(&report, index) {
+                    rows.push(subtotal_row(&columns, row.start_at, subtotal));
+                }
+            }
+            let headers: Vec<String> = columns.iter().map(|x| table_header(*x)).collect();
+            let headers: Vec<&str> = headers.iter().map(|x| x.as_str()).collect();
+            output::print_table(&headers, rows)?;
+            println!(
+                "{}",
+                msg!(
+                    "total.duration",
+                    duration = format_duration(report.total, false, " ")
+                )
+            );
+            if wallclock {
+                println!(
+                    "{}",
+                    msg!(
+                        "total.wallclock",
+                        duration = format_duration(report.wallclock_total, false, " ")
+                    )
+                );
+            }
         }
     }
+    Ok(())
+}
 
-    fn get_all_labels(&self) -> HashSet<&str> {
-        self.sessions
-            .iter()
-            .flat_map(|x| &x.labels)
-            .map(|x| x.as_str())
-            .collect::<HashSet<&str>>()
+/// The subtotal of the day of a row, if the report is grouped by day and the row is the last
+/// one of its day.
+fn day_subtotal(report: &Report, index: usize) -> Option<u64> {
+    if report.subtotals.is_empty() {
+        return None;
     }
-
-    fn remove_label(&mut self, name: &str) -> Result<u32> {
-        let mut count: u32 = 0;
-        for session in &mut self.sessions {
-            let count_before = session.labels.len();
-            session.labels.retain(|x| *x != name);
-            count += u32::try_from(count_before - session.labels.len()).unwrap();
-        }
-        Ok(count)
+    let day = |x: &ReportRow| format_timestamp(x.start_at, "%Y-%m-%d");
+    let key = day(&report.rows[index]);
+    if report.rows.get(index + 1).map(day).as_ref() == Some(&key) {
+        return None;
     }
+    report
+        .subtotals
+        .iter()
+        .find(|x| x.key == key)
+        .map(|x| x.duration)
+}
 
-    fn merge_labels(&mut self, source: Vec<String>, target: String) -> Result<u32> {
-        let mut count: u32 = 0;
-        let sourceset: HashSet<&str> = source.iter().map(|x| x.as_str()).collect();
+/// A row with the day in the first column and its subtotal under the duration.
+fn subtotal_row(columns: &[TableColumn], start_at: i64, subtotal: u64) -> Vec<CellStruct> {
+    let day_column = columns.iter().position(|x| *x != TableColumn::Duration);
+    columns
+        .iter()
+        .enumerate()
+        .map(|(index, column)| match column {
+            TableColumn::Duration => format_duration(subtotal, false, "\n").cell().bold(true),
+            _ if Some(index) == day_column => {
+                format_timestamp(start_at, date_format()).cell().bold(true)
+            }
+            _ => "".cell(),
+        })
+        .collect()
+}
 
-        for session in &mut self.sessions {
-            let count_before = session.labels.len();
-            session.labels.retain(|x| !sourceset.contains(x.as_str()));
+fn table_header(column: TableColumn) -> String {
+    let key = match column {
+        TableColumn::Number => "column.number",
+        TableColumn::Id => "column.id",
+        TableColumn::Weekday => "column.weekday",
+        TableColumn::Start => "column.start",
+        TableColumn::End => "column.end",
+        TableColumn::Labels => "column.labels",
+        TableColumn::Duration => "column.duration",
+        TableColumn::Note => "column.note",
+        TableColumn::Kind => "column.kind",
+        TableColumn::Rate => "column.rate",
+    };
+    msg!(key)
+}
 
-            let removed_count = u32::try_from(count_before - session.labels.len()).unwrap();
-            if (removed_count) > 0 {
-                count += removed_count;
-                session.labels.push(target.clone());
+fn table_cell(row: &ReportRow, column: TableColumn) -> CellStruct {
+    match column {
+        // The number is enough to refer to a session in the other commands, e.g. --id '#12'.
+        TableColumn::Number => format!("#{}", row.seq).cell(),
+        TableColumn::Id => row.id.as_str().cell(),
+        TableColumn::Weekday => format_timestamp(row.start_at, "%a").cell(),
+        TableColumn::Start => format_timestamp(row.start_at, datetime_format()).cell(),
+        TableColumn::End => row
+            .end_at
+            .map(|x| format_timestamp(x, datetime_format()))
+            .unwrap_or_default()
+            .cell(),
+        TableColumn::Labels => row.labels.join(", ").cell(),
+        TableColumn::Duration => format_duration(row.duration, row.end_at.is_none(), "\n").cell(),
+        TableColumn::Note => match row.note {
+            Some(ref x) => {
+                let max_width = get_pprint_note_cell_maxlength();
+                built_multilined_note(x, usize::from(max_width)).cell()
             }
-        }
-
-        Ok(count)
+            None => "".cell(),
+        },
+        TableColumn::Kind => row.kind.as_deref().unwrap_or("").cell(),
+        TableColumn::Rate => row.rate.map(|x| x.to_string()).unwrap_or_default().cell(),
     }
 }
 
-#[derive(serde::Deserialize, serde::Serialize, Debug)]
-struct Session {
-    id: String,
-    start_at: i64,
-    end_at: Option<i64>,
-    note: Option<String>,
-    labels: Vec<String>,
+fn format_timestamp(timestamp: i64, format: &str) -> String {
+    LocalTZ
+        .timestamp_opt(timestamp, 0)
+        .unwrap()
+        .format(format)
+        .to_string()
 }
 
-fn get_path_to_store_file() -> String {
-    std::env::var("WTT_PATH_DATABASE").unwrap_or("db.json".to_string())
+/// Machine-readable formats use RFC 3339, whatever the configured display format is.
+fn format_rfc3339(timestamp: i64) -> String {
+    LocalTZ.timestamp_opt(timestamp, 0).unwrap().to_rfc3339()
 }
 
-fn get_pprint_note_cell_maxlength() -> u16 {
-    if let Ok(value_string) = std::env::var("WTT_PPRINT_NOTE_CELL_MAXLENGTH") {
-        return value_string
-            .parse()
-            .expect("The value for WTT_PPRINT_NOTE_CELL_MAXLENGTH is not a valid u16 number.");
-    }
-    40
-}
-
-fn print_sessions(from: Option<String>, to: Option<String>, labels: Vec<String>) {
-    let from_timestamp: Option<i64> = from.as_ref().and_then(|x| {
-        if x == "today" {
-            return Some(
-                LocalTZ::now()
-                    .with_time(NaiveTime::from_hms_opt(0, 0, 0).unwrap())
-                    .unwrap()
-                    .timestamp(),
-            );
+/// Follow the running session in fixed blocks, printing the progress of the current block
+/// and a notice with a bell every time a block completes.
+fn watch_pomodoro(minutes: u32) -> wtt::Result<()> {
+    let block = i64::from(minutes) * 60;
+    let mut store = handle::load()?;
+    let session = store.get_newest_running_session()?;
+    let (id, start_at) = (session.id.clone(), session.start_at);
+    println!("{}", msg!("pomodoro.watching", id = id, minutes = minutes));
+
+    let mut last_block: Option<i64> = None;
+    loop {
+        let store = handle::load()?;
+        let running = store
+            .sessions
+            .iter()
+            .any(|x| x.id == id && x.end_at.is_none());
+        if !running {
+            println!("\n{}", msg!("pomodoro.ended", id = id));
+            return Ok(());
         }
-        if x == "yesterday" {
-            let now = LocalTZ::now()
-                .with_time(NaiveTime::from_hms_opt(0, 0, 0).unwrap())
-                .unwrap();
-            return Some((now - Duration::days(1)).timestamp());
+
+        let elapsed = LocalTZ::now().timestamp() - start_at;
+        let (index, progress) = (elapsed / block, elapsed % block);
+        if let Some(last) = last_block
+            && index > last
+        {
+            println!(
+                "\n\x07{}",
+                msg!("pomodoro.block_completed", number = last + 1)
+            );
         }
-        Some(get_datetime_from_date_str(x, NaiveTime::from_hms_opt(0, 0, 0).unwrap()).timestamp())
-    });
-    let to_timestamp: Option<i64> = to.as_ref().and_then(|x| {
-        Some(
-            get_datetime_from_date_str(x, NaiveTime::from_hms_opt(23, 59, 59).unwrap()).timestamp(),
-        )
-    });
+        last_block = Some(index);
 
-    let store = Store::from_store_file().unwrap();
-    let sessions = store.get_all_sessions(from_timestamp, to_timestamp, &labels);
+        let elapsed = format!("{:02}:{:02}", progress / 60, progress % 60);
+        let length = format!("{minutes:02}:00");
+        print!(
+            "\r{}",
+            msg!(
+                "pomodoro.progress",
+                number = index + 1,
+                elapsed = elapsed,
+                length = length
+            )
+        );
+        std::io::stdout().flush()?;
+        std::thread::sleep(std::time::Duration::from_secs(1));
+    }
+}
 
-    let mut total_duration: u32 = 0;
-    let mut rows: Vec<Vec<CellStruct>> = vec![];
-    let now = LocalTZ::now();
-    for session in sessions.into_iter() {
-        let start_dt = LocalTZ.timestamp_opt(session.start_at, 0).unwrap();
-
-        let mut end_string: Option<String> = None;
-        let duration_delta: TimeDelta;
-        if let Some(end_at) = session.end_at {
-            let end_dt = LocalTZ.timestamp_opt(end_at, 0).unwrap();
-            end_string = Some(end_dt.format(DATETIME_FORMAT).to_string());
-            duration_delta = end_dt - start_dt;
-        } else {
-            duration_delta = now - start_dt;
-        }
-        let duration = duration_delta.num_minutes() as u32;
-        total_duration += duration;
-
-        rows.push(vec![
-            session.id.as_str().cell(),
-            start_dt.format(DATETIME_FORMAT).cell(),
-            session.labels.join(", ").cell(),
-            match end_string {
-                Some(x) => x.cell(),
-                None => "".cell(),
-            },
-            format_duration(duration, session.end_at.is_none(), "\n").cell(),
-            match session.note {
-                Some(ref x) => {
-                    let max_width = get_pprint_note_cell_maxlength();
-                    built_multilined_note(x, usize::from(max_width)).cell()
-                }
-                None => "".cell(),
-            },
-        ])
-    }
-    let table = rows.table().title(vec![
-        "ID".cell().bold(true),
-        "Start".cell().bold(true),
-        "Labels".cell().bold(true),
-        "End".cell().bold(true),
-        "Duration".cell().bold(true),
-        "Note".cell().bold(true),
-    ]);
-    println!(
-        "{}\nTotal duration: {}.",
-        table
-            .display()
-            .expect("Could not build a table with sessions."),
-        format_duration(total_duration, false, " "),
-    );
+fn set_locked(before: Option<String>, id: Option<String>, locked: bool) -> wtt::Result<()> {
+    let mut store = StoreHandle::open()?;
+    if let Some(id) = id {
+        store.set_locked_by_id(&id, locked)?;
+        handle::success(&match locked {
+            true => msg!("session.locked_one", id = id),
+            false => msg!("session.unlocked_one", id = id),
+        });
+    } else if let Some(before) = before {
+        let before = try_get_from_timestamp(&before)?;
+        let count = store.set_locked_before(before, locked);
+        handle::success(&match locked {
+            true => msg!("session.locked_many", count = count),
+            false => msg!("session.unlocked_many", count = count),
+        });
+    }
+    store.save()?;
+    Ok(())
 }
 
-fn get_datetime_from_date_str(date_str: &str, time: NaiveTime) -> DateTime<LocalTZ> {
-    let date = NaiveDate::parse_from_str(date_str, "%d.%m.%Y").expect(&format!(
-        "The date '{date_str}' must be provided in the format '{DATE_FORMAT}'."
-    ));
-    date.and_time(time).and_local_timezone(LocalTZ).unwrap()
+/// Tell on stderr about the labels no session has, which are likely typos.
+fn hint_unknown_labels(store: &Store, labels: &[String]) {
+    for hint in store.describe_unknown_labels(labels) {
+        eprintln!("{hint}");
+    }
 }
 
-fn format_duration(value: u32, still_running: bool, separator: &str) -> String {
+fn print_config() {
+    let config = config::get();
+    match config.path_exists {
+        true => println!("{}", msg!("config.file", path = config.path)),
+        false => println!("{}", msg!("config.file_missing", path = config.path)),
+    }
+    for (setting, raw, source) in config.entries() {
+        let source = match (source, setting.env) {
+            (config::Source::Env, Some(env)) => format!("{source} {env}"),
+            _ => source.to_string(),
+        };
+        println!("{} = {} ({})", setting.key, raw, source);
+    }
+}
+
+fn print_billing(
+    from: Option<String>,
+    to: Option<String>,
+    labels: Vec<String>,
+    rate: Option<f64>,
+) -> wtt::Result<()> {
+    let filter = Filter {
+        from: from.as_deref().map(try_get_from_timestamp).transpose()?,
+        to: to.as_deref().map(try_get_to_timestamp).transpose()?,
+        labels,
+        ..Default::default()
+    };
+    let store = handle::load()?;
+    hint_unknown_labels(&store, &filter.labels);
+    let billing = store.report(&filter, GroupBy::None).billing(rate);
+
+    match output::format() {
+        Format::Json => output::print_json(&billing)?,
+        Format::Csv => {
+            let rows: Vec<Vec<String>> = billing
+                .labels
+                .iter()
+                .map(|x| {
+                    vec![
+                        x.label.clone(),
+                        x.duration.to_string(),
+                        format!("{:.2}", x.amount),
+                    ]
+                })
+                .collect();
+            output::print_csv(&["label", "duration", "amount"], &rows)?;
+        }
+        _ => {
+            let rows: Vec<Vec<CellStruct>> = billing
+                .labels
+                .iter()
+                .map(|x| {
+                    vec![
+                        x.label.as_str().cell(),
+                        format_duration(x.duration, false, " ").cell(),
+                        format!("{:.2}", x.amount).cell(),
+                    ]
+                })
+                .collect();
+            output::print_table(
+                &[
+                    &msg!("column.label"),
+                    &msg!("column.duration"),
+                    &msg!("column.amount"),
+                ],
+                rows,
+            )?;
+            println!(
+                "{}",
+                msg!(
+                    "total.billing",
+                    amount = format!("{:.2}", billing.total_amount),
+                    duration = format_duration(billing.total_duration, false, " ")
+                )
+            );
+            if billing.unrated_count > 0 {
+                println!("{}", msg!("total.unrated", count = billing.unrated_count));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn start(args: StartArgs) -> wtt::Result<()> {
+    let labels = if args.labels.is_empty() {
+        config::get().default_labels.value.clone()
+    } else {
+        args.labels
+    };
+    let mut store = StoreHandle::open()?;
+    if let Some(running) = store.newest_running_session() {
+        eprintln!(
+            "{}",
+            msg!(
+                "session.running_warning",
+                seq = running.seq,
+                labels = running.labels.join(", ")
+            )
+        );
+    }
+    let at = args.at.as_deref().map(parse_at).transpose()?;
+    let session = store.start_session(labels, args.rate, args.kind, at)?;
+    let id = session.id.clone();
+    if args.git
+        && let Some(info) = git::current()
+    {
+        println!(
+            "{}",
+            msg!(
+                "git.recorded",
+                branch = info.branch,
+                repository = info.repository
+            )
+        );
+        store.get_session_by_id(&id)?.git = Some(info);
+    }
+    if args.slack || args.slack_template.is_some() {
+        slack::request(args.slack_template)?;
+    }
+    // Saving runs the pre-start hook, which may refuse the session.
+    store.save()?;
+    handle::success(&msg!("session.started", id = id));
+
+    if (args.copy_id || config::get().copy_id_on_start.value) && !handle::is_dry_run() {
+        copy_id(&id);
+    }
+    Ok(())
+}
+
+fn export_timesheet(
+    dialect: TimesheetDialect,
+    from: Option<String>,
+    to: Option<String>,
+    output: Option<String>,
+    strict: bool,
+) -> wtt::Result<()> {
+    let filter = Filter {
+        from: from.as_deref().map(try_get_from_timestamp).transpose()?,
+        to: to.as_deref().map(try_get_to_timestamp).transpose()?,
+        ..Default::default()
+    };
+    let dialect = match dialect {
+        TimesheetDialect::Harvest => export::Dialect::Harvest,
+        TimesheetDialect::Clockify => export::Dialect::Clockify,
+    };
+    let store = handle::load()?;
+    let timesheet = export::timesheet(
+        &store,
+        &filter,
+        dialect,
+        &config::get().timesheet_projects.value,
+        &config::get().timesheet_email.value,
+    )?;
+
+    let unmapped: Vec<String> = timesheet.unmapped.iter().map(|x| format!("#{x}")).collect();
+    if strict && !unmapped.is_empty() {
+        return Err(msg!("timesheet.unmapped_strict", sessions = unmapped.join(", ")).into());
+    }
+    match output {
+        Some(path) => write_file_atomically(&path, &timesheet.csv)?,
+        None => print!("{}", timesheet.csv),
+    }
+    if !unmapped.is_empty() {
+        eprintln!(
+            "{}",
+            msg!(
+                "timesheet.unmapped",
+                count = unmapped.len(),
+                sessions = unmapped.join(", ")
+            )
+        );
+    }
+    Ok(())
+}
+
+fn delete_session(id: &str, yes: bool, force: bool) -> wtt::Result<()> {
+    let mut store = StoreHandle::open()?;
+    let session = store.delete_session(id, force)?;
+    let description = msg!(
+        "session.description",
+        seq = session.seq,
+        id = session.id,
+        state = match session.end_at {
+            Some(_) => msg!("session.state_ended"),
+            None => msg!("session.state_running"),
+        },
+        start = format_timestamp(session.start_at, datetime_format()),
+        labels = session.labels.join(", "),
+        note = session.note.as_deref().unwrap_or("-"),
+    );
+    let question = msg!("session.delete_question", description = description);
+    if !prompt::confirm(&question, yes || handle::is_dry_run())? {
+        println!("{}", msg!("common.nothing_changed"));
+        return Ok(());
+    }
+    store.save()?;
+    handle::success(&msg!("session.deleted", description = description));
+    Ok(())
+}
+
+fn add_session(
+    start: &str,
+    end: &str,
+    labels: Vec<String>,
+    note: Option<String>,
+    rate: Option<f64>,
+    kind: Option<String>,
+) -> wtt::Result<()> {
+    let now = LocalTZ::now();
+    let start_at = time::parse_time(start, now)?;
+    let end_at = time::parse_time(end, now)?;
+    let labels = match labels.is_empty() {
+        true => config::get().default_labels.value.clone(),
+        false => labels,
+    };
+    let mut store = StoreHandle::open()?;
+    let session = store.add_session(start_at, end_at, labels, note, rate, kind)?;
+    let (id, seq) = (session.id.clone(), session.seq);
+    let overlapping: Vec<String> = store
+        .overlaps(now.timestamp())
+        .into_iter()
+        .filter_map(|(first, second)| match (first.id == id, second.id == id) {
+            (true, _) => Some(format!("#{}", second.seq)),
+            (_, true) => Some(format!("#{}", first.seq)),
+            _ => None,
+        })
+        .collect();
+    store.save()?;
+    if !overlapping.is_empty() {
+        eprintln!(
+            "{}",
+            msg!("session.overlap_warning", sessions = overlapping.join(", "))
+        );
+    }
+    handle::success(&msg!("session.added", seq = seq, id = id));
+    Ok(())
+}
+
+fn switch_session(
+    labels: Vec<String>,
+    note: Option<String>,
+    kind: Option<String>,
+) -> wtt::Result<()> {
+    let labels = match labels.is_empty() {
+        true => config::get().default_labels.value.clone(),
+        false => labels,
+    };
+    let mut store = StoreHandle::open()?;
+    let ended = match store.newest_running_session() {
+        Some(running) => {
+            let note = note.or_else(|| running.note.clone());
+            if note.is_none() && config::get().require_note.value {
+                return Err(msg!("session.note_required").into());
+            }
+            let id = running.id.clone();
+            let session = store.end_session(Some(&id), note, None, None, None)?;
+            let duration: i64 = session
+                .worked_intervals(session.start_at, session.end_at.unwrap())
+                .iter()
+                .map(|(start, end)| end - start)
+                .sum();
+            Some((id, duration))
+        }
+        None => None,
+    };
+    let id = store.start_session(labels, None, kind, None)?.id.clone();
+    store.save()?;
+
+    match ended {
+        Some((ended_id, duration)) => println!(
+            "{}",
+            msg!(
+                "session.switch_ended",
+                id = ended_id,
+                duration = format_duration(duration.max(0) as u64, false, " ")
+            )
+        ),
+        None => println!("{}", msg!("session.switch_none_running")),
+    }
+    handle::success(&msg!("session.started", id = id));
+    Ok(())
+}
+
+fn print_overlaps() -> wtt::Result<()> {
+    let store = handle::load()?;
+    let now = LocalTZ::now().timestamp();
+    let pairs = store.overlaps(now);
+    if pairs.is_empty() {
+        println!("{}", msg!("overlap.none"));
+        return Ok(());
+    }
+    let describe = |session: &Session| {
+        msg!(
+            "overlap.session",
+            seq = session.seq,
+            labels = session.labels.join(", "),
+            start = format_timestamp(session.start_at, datetime_format()),
+            end = match session.end_at {
+                Some(x) => format_timestamp(x, datetime_format()),
+                None => msg!("overlap.now"),
+            }
+        )
+    };
+    for (first, second) in &pairs {
+        let overlap = first
+            .end_at
+            .unwrap_or(now)
+            .min(second.end_at.unwrap_or(now))
+            - second.start_at;
+        println!(
+            "{}",
+            msg!(
+                "overlap.pair",
+                first = describe(first),
+                second = describe(second),
+                duration = format_duration(overlap.max(0) as u64, false, " ")
+            )
+        );
+    }
+    Ok(())
+}
+
+fn print_running_sessions(quiet: bool) -> wtt::Result<()> {
+    let store = handle::load()?;
+    let now = LocalTZ::now().timestamp();
+    let mut running: Vec<&Session> = store
+        .sessions
+        .iter()
+        .filter(|x| x.end_at.is_none())
+        .collect();
+    if running.is_empty() {
+        return Err(ExitStatus(1).into());
+    }
+    if quiet {
+        return Ok(());
+    }
+    running.sort_by_key(|x| x.start_at);
+    for session in running {
+        let elapsed: i64 = session
+            .worked_intervals(session.start_at, now)
+            .iter()
+            .map(|(start, end)| end - start)
+            .sum();
+        println!(
+            "#{} [{}] {}{}",
+            session.seq,
+            session.labels.join(", "),
+            format_duration(elapsed.max(0) as u64, false, " "),
+            if session.is_paused() { ", paused" } else { "" }
+        );
+    }
+    Ok(())
+}
+
+fn show_session(id: &str) -> wtt::Result<()> {
+    let store = handle::load()?;
+    let session = store.find_session(id)?;
+    let end_at = session.end_at.unwrap_or(LocalTZ::now().timestamp());
+    let duration: i64 = session
+        .worked_intervals(session.start_at, end_at)
+        .iter()
+        .map(|(start, end)| end - start)
+        .sum();
+    if output::format() == Format::Json {
+        output::print_json(&serde_json::json!({
+            "session": session,
+            "duration": duration,
+        }))?;
+        return Ok(());
+    }
+
+    println!("#{} {}", session.seq, session.id);
+    println!(
+        "{}",
+        msg!(
+            "show.start",
+            start = format_timestamp(session.start_at, datetime_format())
+        )
+    );
+    match session.end_at {
+        Some(x) => println!(
+            "{}",
+            msg!("show.end", end = format_timestamp(x, datetime_format()))
+        ),
+        None if session.is_paused() => println!("{}", msg!("show.end_paused")),
+        None => println!("{}", msg!("show.end_running")),
+    }
+    println!(
+        "{}",
+        msg!(
+            "show.duration",
+            duration = format_duration(duration.max(0) as u64, session.end_at.is_none(), " "),
+            clock = format!(
+                "{}:{:02}:{:02}",
+                duration / 3600,
+                duration / 60 % 60,
+                duration % 60
+            )
+        )
+    );
+    if !session.breaks.is_empty() {
+        println!("{}", msg!("show.breaks", count = session.breaks.len()));
+    }
+    println!(
+        "{}",
+        msg!("show.labels", labels = session.labels.join(", "))
+    );
+    if let Some(kind) = &session.kind {
+        println!("{}", msg!("show.kind", kind = kind));
+    }
+    if let Some(rate) = session.rate {
+        println!("{}", msg!("show.rate", rate = rate));
+    }
+    if session.locked {
+        println!("{}", msg!("show.locked"));
+    }
+    match session.note.as_deref().filter(|x| !x.is_empty()) {
+        Some(note) => println!("{}", msg!("show.note", note = note)),
+        None => println!("{}", msg!("show.no_note")),
+    }
+    Ok(())
+}
+
+fn pause_session(id: Option<&str>, pause: bool) -> wtt::Result<()> {
+    let mut store = StoreHandle::open()?;
+    let session = match pause {
+        true => store.pause_session(id)?,
+        false => store.unpause_session(id)?,
+    };
+    let message = match pause {
+        true => msg!("session.paused", id = session.id),
+        false => {
+            let (start, end) = *session.breaks.last().unwrap();
+            msg!(
+                "session.unpaused",
+                id = session.id,
+                duration = format_duration((end.unwrap() - start).max(0) as u64, false, " ")
+            )
+        }
+    };
+    store.save()?;
+    handle::success(&message);
+    Ok(())
+}
+
+fn split_session(id: &str, at: &str, force: bool) -> wtt::Result<()> {
+    let at = time::parse_time(at, LocalTZ::now())?;
+    let mut store = StoreHandle::open()?;
+    let (first_id, second_id) = store.split_session(id, at, force)?;
+    let first_seq = store.get_session_by_id(&first_id)?.seq;
+    let second_seq = store.get_session_by_id(&second_id)?.seq;
+    // The work goes on, even though one part ends and another starts.
+    store.set_events(vec![]);
+    store.save()?;
+    handle::success(&msg!(
+        "session.split",
+        at = format_timestamp(at, datetime_format()),
+        first_seq = first_seq,
+        first_id = first_id,
+        second_seq = second_seq,
+        second_id = second_id
+    ));
+    Ok(())
+}
+
+fn merge_sessions(ids: &[String], max_gap: u32, force: bool) -> wtt::Result<()> {
+    let ids: Vec<&str> = ids.iter().map(String::as_str).collect();
+    let mut store = StoreHandle::open()?;
+    let session = store.merge_sessions(&ids, i64::from(max_gap) * 60, force)?;
+    let message = msg!("session.merged", seq = session.seq, id = session.id);
+    store.save()?;
+    handle::success(&message);
+    Ok(())
+}
+
+fn resume_session(id: Option<&str>, new: bool, force: bool) -> wtt::Result<()> {
+    let mut store = StoreHandle::open()?;
+    let session = store.resume_session(id, new, force)?;
+    let message = match new {
+        true => msg!(
+            "session.resumed_new",
+            id = session.id,
+            labels = session.labels.join(", ")
+        ),
+        false => msg!("session.reopened", id = session.id),
+    };
+    store.save()?;
+    handle::success(&message);
+    Ok(())
+}
+
+fn cancel_session(id: Option<&str>) -> wtt::Result<()> {
+    let mut store = StoreHandle::open()?;
+    let session = store.cancel_session(id)?;
+    let elapsed = (LocalTZ::now().timestamp() - session.start_at).max(0) as u64;
+    store.save()?;
+    handle::success(&msg!(
+        "session.cancelled",
+        id = session.id,
+        duration = format_duration(elapsed, false, " ")
+    ));
+    Ok(())
+}
+
+fn add_session_label(id: &str, name: &str, force: bool) -> wtt::Result<()> {
+    let mut store = StoreHandle::open()?;
+    hint_unknown_labels(&store, &[name.to_string()]);
+    let id = store.resolve_id(id)?;
+    if !store.add_session_label(&id, name, force)? {
+        println!(
+            "{}",
+            msg!("session.label_already_on", id = id, label = name)
+        );
+        return Ok(());
+    }
+    store.save()?;
+    handle::success(&msg!("session.label_added", id = id, label = name));
+    Ok(())
+}
+
+fn remove_session_label(id: &str, name: &str, force: bool) -> wtt::Result<()> {
+    let mut store = StoreHandle::open()?;
+    let id = store.resolve_id(id)?;
+    store.remove_session_label(&id, name, force)?;
+    store.save()?;
+    handle::success(&msg!("session.label_removed", id = id, label = name));
+    Ok(())
+}
+
+fn edit_session(
+    id: &str,
+    start: Option<&str>,
+    end: Option<&str>,
+    yes: bool,
+    force: bool,
+) -> wtt::Result<()> {
+    let now = LocalTZ::now();
+    let start_at = start.map(|x| time::parse_time(x, now)).transpose()?;
+    let end_at = end.map(|x| time::parse_time(x, now)).transpose()?;
+
+    let mut store = StoreHandle::open()?;
+    let was_running = store.get_session_by_id(id)?.end_at.is_none();
+    // The edit is checked before asking, and only saved after the answer.
+    let session = store.edit_session(id, start_at, end_at, force)?;
+    if let Some(end_at) = end_at
+        && was_running
+    {
+        let question = msg!(
+            "session.edit_end_question",
+            seq = session.seq,
+            at = format_timestamp(end_at, datetime_format())
+        );
+        if !prompt::confirm(&question, yes || handle::is_dry_run())? {
+            println!("{}", msg!("common.nothing_changed"));
+            return Ok(());
+        }
+    }
+    let start = format_timestamp(session.start_at, datetime_format());
+    let description = match session.end_at {
+        Some(x) => msg!(
+            "session.edited",
+            seq = session.seq,
+            start = start,
+            end = format_timestamp(x, datetime_format())
+        ),
+        None => msg!("session.edited_running", seq = session.seq, start = start),
+    };
+    store.save()?;
+    handle::success(&description);
+    Ok(())
+}
+
+/// Copy a session id for the next command. The session is already saved, so a failure is only a warning.
+fn copy_id(id: &str) {
+    if clipboard::copy(id) {
+        println!("{}", msg!("session.id_copied"));
+    } else {
+        eprintln!("{}", msg!("session.id_not_copied"));
+    }
+}
+
+fn end(args: EndArgs) -> wtt::Result<()> {
+    let mut store = StoreHandle::open()?;
+    let id = if args.pick {
+        let mut running: Vec<&Session> = store
+            .sessions
+            .iter()
+            .filter(|x| x.end_at.is_none())
+            .collect();
+        running.sort_by_key(|x| Reverse(x.start_at));
+        Some(picker::pick_session(&running)?)
+    } else {
+        args.id
+    };
+    let session = match id.as_deref() {
+        Some(x) => store.get_session_by_id(x)?,
+        None => store.get_newest_running_session()?,
+    };
+    // Without a new note, the session keeps the one added while it ran, e.g. by `wtt note`.
+    let has_note = session.note.is_some();
+    let note = if args.edit {
+        match edit_closing_note(session)? {
+            Some(x) => Some(x),
+            None if has_note => None,
+            None if config::get().require_note.value => {
+                return Err(msg!("session.note_required_edited").into());
+            }
+            None => {
+                if !prompt::confirm(
+                    &msg!("session.empty_note_question"),
+                    args.yes || handle::is_dry_run(),
+                )? {
+                    println!("{}", msg!("common.nothing_changed"));
+                    return Ok(());
+                }
+                None
+            }
+        }
+    } else {
+        if args.note.is_none() && !has_note && config::get().require_note.value {
+            return Err(msg!("session.note_required").into());
+        }
+        args.note
+    };
+    let session = store.end_session(
+        id.as_deref(),
+        note,
+        args.round_start,
+        args.round_end,
+        args.at.as_deref().map(parse_at).transpose()?,
+    )?;
+    let id = session.id.clone();
+    if args.git {
+        append_commits(store.get_session_by_id(&id)?);
+    }
+    store.save()?;
+    handle::success(&msg!("session.ended", id = id));
+    Ok(())
+}
+
+/// Add the commits made on the session's branch while it ran to its note.
+/// A session started without --git uses the branch of the current directory.
+fn append_commits(session: &mut Session) {
+    let Some(info) = session.git.clone().or_else(git::current) else {
+        return;
+    };
+    let Some(commits) = git::commits(&info, session.start_at, session.end_at.unwrap()) else {
+        return;
+    };
+    if commits.is_empty() {
+        println!("{}", msg!("git.no_commits", branch = info.branch));
+        return;
+    }
+    let summary = msg!(
+        "git.commits_note",
+        branch = info.branch,
+        commits = commits.join("\n")
+    );
+    println!("{}", msg!("git.commits_added", count = commits.len()));
+    session.note = Some(match session.note.take() {
+        Some(note) => format!("{note}\n\n{summary}"),
+        None => summary,
+    });
+}
+
+/// Let the user write the note of a session which is about to end in their editor.
+fn edit_closing_note(session: &Session) -> wtt::Result<Option<String>> {
+    let start = LocalTZ.timestamp_opt(session.start_at, 0).unwrap();
+    let elapsed = (LocalTZ::now() - start).num_seconds().max(0) as u64;
+    let comments = [
+        msg!("editor.help"),
+        match session.note {
+            Some(_) => msg!("editor.empty_keeps"),
+            None => msg!("editor.empty_ends"),
+        },
+        String::new(),
+        msg!("editor.session", seq = session.seq, id = session.id),
+        msg!("editor.labels", labels = session.labels.join(", ")),
+        msg!("editor.started", start = start.format(datetime_format())),
+        msg!(
+            "editor.running_for",
+            duration = format_duration(elapsed, false, " ")
+        ),
+    ];
+    editor::edit(&comments)
+}
+
+/// How many of the latest sessions are offered by --pick when any session can be chosen.
+const PICK_RECENT_COUNT: usize = 50;
+
+fn update_note(args: NoteArgs) -> wtt::Result<()> {
+    let mut store = StoreHandle::open()?;
+    let id = match args.id {
+        Some(x) => x,
+        None if args.pick => {
+            let mut recent: Vec<&Session> = store.sessions.iter().collect();
+            recent.sort_by_key(|x| Reverse(x.start_at));
+            recent.truncate(PICK_RECENT_COUNT);
+            picker::pick_session(&recent)?
+        }
+        None => store.get_single_running_session()?.id.clone(),
+    };
+    let separator = config::get().note_separator.value.replace("\\n", "\n");
+    let mode = match args.append {
+        true => NoteMode::Append {
+            separator: &separator,
+        },
+        false => NoteMode::Replace,
+    };
+    store.update_note(&id, args.text, mode, args.force)?;
+    store.save()?;
+    handle::success(&msg!("session.note_updated"));
+    Ok(())
+}
+
+fn print_summary(
+    by: SummaryBy,
+    from: Option<String>,
+    to: Option<String>,
+    labels: Vec<String>,
+    split_days: bool,
+    split: bool,
+) -> wtt::Result<()> {
+    let filter = Filter {
+        from: from.as_deref().map(try_get_from_timestamp).transpose()?,
+        to: to.as_deref().map(try_get_to_timestamp).transpose()?,
+        labels,
+        split_days,
+        ..Default::default()
+    };
+    let (group_by, key) = match by {
+        SummaryBy::Day => (GroupBy::Day, "day"),
+        SummaryBy::Label if split => (GroupBy::LabelSplit, "label"),
+        SummaryBy::Label => (GroupBy::Label, "label"),
+        SummaryBy::Kind => (GroupBy::Kind, "kind"),
+    };
+    let store = handle::load()?;
+    hint_unknown_labels(&store, &filter.labels);
+    let mut report = store.report(&filter, group_by);
+    if group_by != GroupBy::Day {
+        report
+            .subtotals
+            .sort_by(|a, b| b.duration.cmp(&a.duration).then(a.key.cmp(&b.key)));
+    }
+
+    match output::format() {
+        Format::Json => output::print_json(&serde_json::json!({
+            "subtotals": report.subtotals,
+            "total": report.total,
+        }))?,
+        Format::Csv => {
+            let rows: Vec<Vec<String>> = report
+                .subtotals
+                .iter()
+                .map(|x| vec![x.key.clone(), x.count.to_string(), x.duration.to_string()])
+                .collect();
+            output::print_csv(&[key, "sessions", "duration"], &rows)?;
+        }
+        _ => {
+            let rows: Vec<Vec<CellStruct>> = report
+                .subtotals
+                .iter()
+                .map(|x| {
+                    vec![
+                        x.key.as_str().cell(),
+                        x.count.cell(),
+                        format_duration(x.duration, false, " ").cell(),
+                    ]
+                })
+                .collect();
+            output::print_table(
+                &[
+                    &msg!(&format!("column.{key}")),
+                    &msg!("column.sessions"),
+                    &msg!("column.duration"),
+                ],
+                rows,
+            )?;
+            println!(
+                "{}",
+                msg!(
+                    "total.duration",
+                    duration = format_duration(report.total, false, " ")
+                )
+            );
+        }
+    }
+    Ok(())
+}
+
+/// A clap parser for lengths of time like "50m". They must not be zero.
+fn parse_duration_arg(value: &str) -> std::result::Result<Duration, String> {
+    match time::parse_duration(value) {
+        Ok(x) if x > Duration::zero() => Ok(x),
+        Ok(_) => Err(msg!("time.not_positive", value = value)),
+        Err(x) => Err(x.to_string()),
+    }
+}
+
+/// Parse the value of --at.
+fn parse_at(value: &str) -> wtt::Result<i64> {
+    time::parse_time(value, LocalTZ::now())
+}
+
+/// Parse the start of a range: a date, "today", "yesterday", "week", "month", "-7d" or "-2w".
+fn try_get_from_timestamp(value: &str) -> std::result::Result<i64, String> {
+    let today = LocalTZ::now().date_naive();
+    try_get_range_timestamp(value, NaiveTime::from_hms_opt(0, 0, 0).unwrap(), today)
+}
+
+/// Parse the end of a range. The whole day is included.
+fn try_get_to_timestamp(value: &str) -> std::result::Result<i64, String> {
+    let today = LocalTZ::now().date_naive();
+    try_get_range_timestamp(value, NaiveTime::from_hms_opt(23, 59, 59).unwrap(), today)
+}
+
+/// Parse a bound of a range, so --from and --to accept the same values.
+/// A day without a time, relative or not, gets the time given. Relative days count from `today`.
+fn try_get_range_timestamp(
+    value: &str,
+    time: NaiveTime,
+    today: NaiveDate,
+) -> std::result::Result<i64, String> {
+    match time::parse_relative_day(value, today).map_err(|x| x.to_string())? {
+        Some(date) => date
+            .and_time(time)
+            .and_local_timezone(LocalTZ)
+            .earliest()
+            .map(|x| x.timestamp())
+            .ok_or_else(|| msg!("time.nonexistent", value = value)),
+        None => match try_get_datetime_from_date_str(value, time) {
+            Ok(x) => Ok(x.timestamp()),
+            Err(_) => Err(msg!(
+                "time.not_a_day",
+                value = value,
+                date_format = date_format(),
+                datetime_format = datetime_format(),
+                relative = time::RELATIVE_DAYS
+            )),
+        },
+    }
+}
+
+/// Parse a date, or a date with time. A date alone gets the time given.
+fn try_get_datetime_from_date_str(
+    date_str: &str,
+    time: NaiveTime,
+) -> std::result::Result<DateTime<LocalTZ>, String> {
+    let datetime = match NaiveDateTime::parse_from_str(date_str, datetime_format()) {
+        Ok(x) => x,
+        Err(_) => NaiveDate::parse_from_str(date_str, date_format())
+            .map_err(|_| {
+                msg!(
+                    "time.date_format",
+                    value = date_str,
+                    date_format = date_format(),
+                    datetime_format = datetime_format()
+                )
+            })?
+            .and_time(time),
+    };
+    datetime
+        .and_local_timezone(LocalTZ)
+        .earliest()
+        .ok_or_else(|| msg!("time.nonexistent", value = date_str))
+}
+
+/// Format a duration given in seconds. Seconds are not displayed,
+/// but a non-zero duration shorter than a minute is shown as "<1 minute".
+fn format_duration(value: u64, still_running: bool, separator: &str) -> String {
     let mut parts: Vec<String> = vec![];
 
     if still_running {
-        parts.push("for now".to_string());
+        parts.push(msg!("duration.running"));
+    }
+
+    if value > 0 && value < 60 {
+        parts.push(msg!("duration.under_minute"));
+        return parts.join(separator);
     }
 
-    let hours = value / 60;
+    let total_minutes = value / 60;
+
+    let hours = total_minutes / 60;
     if hours > 0 {
-        parts.push(format!("{hours} hours"));
+        parts.push(msg!("duration.hours", count = hours));
     }
 
-    let minutes = value % 60;
-    parts.push(format!("{minutes} minutes"));
+    let minutes = total_minutes % 60;
+    parts.push(msg!("duration.minutes", count = minutes));
 
-    return parts.join(separator);
+    parts.join(separator)
 }
 
 fn built_multilined_note(text: &str, max_width: usize) -> String {
-    let mut text: &str = text;
-    let mut tmp: &str;
-    let mut parts: Vec<&str> = vec![];
-    while text.len() > 0 {
-        if text.len() <= max_width {
-            parts.push(text);
-            break;
+    // Keep the line breaks entered by the user and wrap each line on its own.
+    text.split('\n')
+        .map(|line| wrap_note_line(line.trim_end_matches('\r'), max_width))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Wrap one line of a note at whitespace, so no line is wider than `max_width` terminal
+/// columns (CJK characters and most emoji take two). Words wider than that (e.g. URLs) are
+/// broken between characters.
+fn wrap_note_line(text: &str, max_width: usize) -> String {
+    let max_width = max_width.max(2);
+    let mut lines: Vec<String> = vec![];
+    let mut line = String::new();
+    let mut line_width: usize = 0;
+
+    for word in text.split_whitespace() {
+        let word_width: usize = word.chars().map(char_width).sum();
+
+        if line_width > 0 && line_width + 1 + word_width <= max_width {
+            line.push(' ');
+            line.push_str(word);
+            line_width += 1 + word_width;
+            continue;
+        }
+        if line_width > 0 {
+            lines.push(std::mem::take(&mut line));
+            line_width = 0;
         }
 
-        let mut last_whitespace_index: Option<usize> = None;
-        for (index, char) in text.char_indices() {
-            if index >= max_width {
-                break;
-            }
-            if char == ' ' {
-                last_whitespace_index = Some(index);
+        for c in word.chars() {
+            let width = char_width(c);
+            if line_width + width > max_width {
+                lines.push(std::mem::take(&mut line));
+                line_width = 0;
             }
+            line.push(c);
+            line_width += width;
         }
+    }
+    if line_width > 0 {
+        lines.push(line);
+    }
+    lines.join("\n")
+}
+
+fn char_width(c: char) -> usize {
+    c.width().unwrap_or(0)
+}
 
-        if let Some(ws_index) = last_whitespace_index {
-            (tmp, text) = text.split_at(ws_index);
-            text = text.trim_start();
-            parts.push(tmp);
-        } else {
-            (tmp, text) = text.split_at(max_width - 1);
-            parts.push(tmp);
+/// The format requested with --format, or with the shorthand of a command like `session table --json`.
+fn requested_format(format: Option<Format>, command: &MainCommands) -> Option<Format> {
+    match command {
+        MainCommands::Session(x)
+            if matches!(x.command, SessionCommands::Table { json: true, .. }) =>
+        {
+            Some(Format::Json)
         }
+        _ => format,
+    }
+}
+
+/// The output formats of each command. The first one is the default.
+fn supported_formats(command: &MainCommands) -> &'static [Format] {
+    match command {
+        MainCommands::Session(x) => match x.command {
+            SessionCommands::Table { .. } => {
+                &[Format::Table, Format::Json, Format::Csv, Format::Plain]
+            }
+            SessionCommands::Billing { .. } | SessionCommands::Summary { .. } => {
+                &[Format::Table, Format::Json, Format::Csv]
+            }
+            SessionCommands::Today { .. } | SessionCommands::Show { .. } => {
+                &[Format::Plain, Format::Json]
+            }
+            SessionCommands::Current { pomodoro: None } => &[Format::Plain, Format::Json],
+            _ => &[Format::Plain],
+        },
+        MainCommands::Label(x) => match x.command {
+            LabelCommands::List {} => &[Format::Plain, Format::Json, Format::Csv, Format::Table],
+            _ => &[Format::Plain],
+        },
+        MainCommands::Status { .. } => &[Format::Plain, Format::Waybar, Format::Tmux],
+        _ => &[Format::Plain],
     }
-    parts.join("\n")
 }
 
 fn main() {
     let cli = Cli::parse();
-    match cli.command {
+    logger::init(cli.verbose);
+    // The doctor loads the config on its own, so a broken one is reported instead of panicking.
+    if let MainCommands::Doctor = cli.command {
+        std::process::exit(doctor::run(cli.db.as_deref()));
+    }
+    if let Err(x) = prepare_and_run(cli) {
+        if let Some(ExitStatus(code)) = x.downcast_ref::<ExitStatus>() {
+            std::process::exit(*code);
+        }
+        eprintln!("{x}");
+        std::process::exit(1);
+    }
+}
+
+/// A non-zero exit status of a command which printed its outcome already, like `status` when
+/// nothing is running, so a shell can test it. It goes up through `run` like any error, so a
+/// batch reports the line instead of being cut short.
+#[derive(Debug)]
+struct ExitStatus(i32);
+
+impl std::fmt::Display for ExitStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", msg!("common.exit_status", code = self.0))
+    }
+}
+
+impl std::error::Error for ExitStatus {}
+
+/// Load the config, set up the output and run the command.
+fn prepare_and_run(cli: Cli) -> wtt::Result<()> {
+    // Init chooses the database itself, so it runs before the configured one is used.
+    if let MainCommands::Init { path, completions } = cli.command {
+        return init::run(path.or(cli.db), completions);
+    }
+    // Loaded here, so a broken config is reported instead of panicking on first use.
+    let mut config = Config::load()?;
+    if let Some(db) = cli.db.as_deref() {
+        config.set_from_cli("storage.path", db)?;
+    }
+    config::set(config)?;
+    let database_path = &config::get().database_path;
+    log::debug!(
+        "Database: {} ({})",
+        database_path.value,
+        database_path.source
+    );
+    handle::set_dry_run(cli.dry_run);
+    output::set_format(
+        requested_format(cli.format, &cli.command),
+        supported_formats(&cli.command),
+    )?;
+    if !matches!(
+        cli.command,
+        MainCommands::Completions { .. }
+            | MainCommands::Complete { .. }
+            | MainCommands::Man
+            | MainCommands::Export(_)
+            | MainCommands::Status { .. }
+            | MainCommands::Remind { .. }
+            | MainCommands::WatchIdle { .. }
+            | MainCommands::Pomodoro { .. }
+            | MainCommands::Serve { .. }
+            | MainCommands::Ui
+            // It feeds shell prompts.
+            | MainCommands::Session(SessionArgs {
+                command: SessionCommands::Status { .. }
+            })
+    ) {
+        nag::enable();
+    }
+    run(cli.command)
+}
+
+fn run(command: MainCommands) -> wtt::Result<()> {
+    match command {
         MainCommands::Session(session) => match session.command {
-            SessionCommands::Table { from, to, labels } => print_sessions(from, to, labels),
-            SessionCommands::Start { labels } => {
-                let mut store = Store::from_store_file().unwrap();
-                let session = store.start_session(labels).unwrap();
-                println!("New session was successfully started: {}", &session.id);
-                store.save().unwrap();
-            }
-            SessionCommands::End { id, note } => {
-                let mut store = Store::from_store_file().unwrap();
-                let session = store.end_session(id.as_deref(), note).unwrap();
-                println!("The session {} was successfully ended.", &session.id);
-                store.save().unwrap();
-            }
-            SessionCommands::Note { id, text } => {
-                let mut store = Store::from_store_file().unwrap();
-                store.update_note(&id, text).unwrap();
-                println!("Updated.");
-                store.save().unwrap();
+            SessionCommands::Table {
+                from,
+                to,
+                labels,
+                match_all,
+                label_prefixes,
+                exclude_labels,
+                search,
+                running,
+                ended,
+                last,
+                clamp_to_range,
+                split_days,
+                group_by,
+                wallclock,
+                repeat,
+                save_query,
+                template,
+                columns,
+                ..
+            } => {
+                let template = template.as_deref().map(Template::parse).transpose()?;
+                if template.is_some() && !matches!(output::format(), Format::Table | Format::Plain)
+                {
+                    return Err(msg!("table.template_format", format = output::format()).into());
+                }
+                let query = if repeat {
+                    LastQuery::from_state_file()?
+                } else {
+                    LastQuery {
+                        from,
+                        to,
+                        labels,
+                        match_all,
+                        label_prefixes,
+                        exclude_labels,
+                        search,
+                        running: match (running, ended) {
+                            (true, _) => Some(true),
+                            (_, true) => Some(false),
+                            _ => None,
+                        },
+                        last,
+                    }
+                };
+                if save_query && !handle::is_dry_run() {
+                    query.save()?;
+                }
+                print_sessions(
+                    query,
+                    clamp_to_range,
+                    split_days || group_by == Some(TableGroupBy::Day),
+                    group_by,
+                    wallclock,
+                    template.as_ref(),
+                    columns,
+                )?
+            }
+            SessionCommands::Billing {
+                from,
+                to,
+                labels,
+                rate,
+            } => print_billing(from, to, labels, rate)?,
+            SessionCommands::Today { fast } => {
+                let today = if fast {
+                    Today::load_fast()?
+                } else {
+                    Today::compute(&handle::load()?)
+                };
+                match output::format() {
+                    Format::Json => output::print_json(&serde_json::json!({
+                        "date": today.date,
+                        "total": today.total(),
+                        "running": today.is_running(),
+                    }))?,
+                    _ => println!(
+                        "{}",
+                        msg!(
+                            "total.today",
+                            duration = format_duration(today.total(), today.is_running(), " ")
+                        )
+                    ),
+                }
+            }
+            SessionCommands::Import { csv, map } => {
+                let map = ColumnMap::parse(&map)?;
+                let file = fs::File::open(&csv)
+                    .map_err(|x| msg!("import.open_failed", path = csv, error = x))?;
+                let mut store = StoreHandle::open()?;
+                let report = store.import_csv(file, &map)?;
+                for (line, reason) in report.skipped.iter() {
+                    eprintln!(
+                        "{}",
+                        msg!("import.skipped_row", line = line, reason = reason)
+                    );
+                }
+                handle::success(&msg!(
+                    "import.imported",
+                    imported = report.imported,
+                    skipped = report.skipped.len()
+                ));
+                store.save()?;
+            }
+            SessionCommands::Summary {
+                by,
+                from,
+                to,
+                labels,
+                split_days,
+                split,
+            } => print_summary(by, from, to, labels, split_days, split)?,
+            SessionCommands::Start(args) => start(args)?,
+            SessionCommands::End(args) => end(args)?,
+            SessionCommands::Note(args) => update_note(args)?,
+            SessionCommands::Add {
+                start,
+                end,
+                labels,
+                note,
+                rate,
+                kind,
+            } => {
+                add_session(&start, &end, labels, note, rate, kind)?;
+            }
+            SessionCommands::Switch { labels, note, kind } => {
+                switch_session(labels, note, kind)?;
+            }
+            SessionCommands::Status { quiet } => print_running_sessions(quiet)?,
+            SessionCommands::Overlaps {} => print_overlaps()?,
+            SessionCommands::Show { id } => {
+                show_session(&id)?;
+            }
+            SessionCommands::Pause { id } => {
+                pause_session(id.as_deref(), true)?;
+            }
+            SessionCommands::Unpause { id } => {
+                pause_session(id.as_deref(), false)?;
             }
+            SessionCommands::Split { id, at, force } => {
+                split_session(&id, &at, force)?;
+            }
+            SessionCommands::Merge {
+                ids,
+                max_gap,
+                force,
+            } => {
+                merge_sessions(&ids, max_gap, force)?;
+            }
+            SessionCommands::Resume { id, new, force } => {
+                resume_session(id.as_deref(), new, force)?;
+            }
+            SessionCommands::Restart { id } => {
+                resume_session(id.as_deref(), true, false)?;
+            }
+            SessionCommands::Cancel { id } => {
+                cancel_session(id.as_deref())?;
+            }
+            SessionCommands::Label(label_args) => {
+                let result = match label_args.command {
+                    SessionLabelCommands::Add { id, name, force } => {
+                        add_session_label(&id, &name, force)
+                    }
+                    SessionLabelCommands::Remove { id, name, force } => {
+                        remove_session_label(&id, &name, force)
+                    }
+                };
+                result?;
+            }
+            SessionCommands::Edit {
+                id,
+                start,
+                end,
+                yes,
+                force,
+            } => {
+                edit_session(&id, start.as_deref(), end.as_deref(), yes, force)?;
+            }
+            SessionCommands::Delete {
+                id: Some(id),
+                yes,
+                force,
+                ..
+            } => delete_session(&id, yes, force)?,
+            SessionCommands::Delete {
+                older_than,
+                label,
+                yes,
+                force,
+                ..
+            } => {
+                let before = try_get_from_timestamp(&older_than.unwrap())?;
+
+                let mut store = StoreHandle::open()?;
+                let count = store.get_old_sessions(before, label.as_deref()).len();
+                if count == 0 {
+                    println!("{}", msg!("session.delete_many_none"));
+                    return Ok(());
+                }
+                let ids: Vec<&str> = store
+                    .get_old_sessions(before, label.as_deref())
+                    .iter()
+                    .map(|x| x.id.as_str())
+                    .collect();
+                let question = msg!(
+                    "session.delete_many_question",
+                    count = count,
+                    ids = ids.join("\n")
+                );
+                if !prompt::confirm(&question, yes || handle::is_dry_run())? {
+                    println!("{}", msg!("common.nothing_changed"));
+                    return Ok(());
+                }
+
+                let deleted_count = store.delete_old_sessions(before, label.as_deref(), force)?;
+                store.save()?;
+                handle::success(&msg!("session.deleted_many", count = deleted_count));
+            }
+            SessionCommands::Current { pomodoro } => match pomodoro {
+                Some(minutes) => watch_pomodoro(minutes)?,
+                None => {
+                    let store = handle::load()?;
+                    let Some(session) = store.newest_running_session() else {
+                        match output::format() {
+                            Format::Json => output::print_json(&serde_json::json!({
+                                "session": null,
+                                "elapsed": 0,
+                            }))?,
+                            _ => println!("{}", msg!("session.current_none")),
+                        }
+                        // Non-zero, so a shell prompt can tell that nothing is running.
+                        return Err(ExitStatus(1).into());
+                    };
+                    let start_dt = LocalTZ.timestamp_opt(session.start_at, 0).unwrap();
+                    let elapsed = session
+                        .worked_intervals(session.start_at, LocalTZ::now().timestamp())
+                        .iter()
+                        .map(|(start, end)| end - start)
+                        .sum::<i64>()
+                        .max(0) as u64;
+                    if output::format() == Format::Json {
+                        output::print_json(&serde_json::json!({
+                            "session": session,
+                            "elapsed": elapsed,
+                        }))?;
+                        return Ok(());
+                    }
+                    println!(
+                        "{}",
+                        msg!(
+                            "session.current",
+                            id = session.id,
+                            labels = session.labels.join(", "),
+                            start = start_dt.format(datetime_format()),
+                            duration = format_duration(elapsed, false, " "),
+                        )
+                    );
+                }
+            },
+            SessionCommands::Lock { before, id } => set_locked(before, id, true)?,
+            SessionCommands::Unlock { before, id } => set_locked(before, id, false)?,
         },
         MainCommands::Label(label) => match label.command {
             LabelCommands::List {} => {
-                let store = Store::from_store_file().unwrap();
-                let labels = store.get_all_labels();
-                println!("{}", labels.into_iter().collect::<Vec<&str>>().join("\n"));
-            }
-            LabelCommands::Remove { name } => {
-                let mut store = Store::from_store_file().unwrap();
-                let removed_count = store.remove_label(&name).unwrap();
-                store.save().unwrap();
-                println!("Removed {} labels.", removed_count);
-            }
-            LabelCommands::Merge { source, target } => {
-                if (source.len()) == 0 {
-                    println!("No source labels were provided. Nothing changed.");
-                    return;
+                let store = handle::load()?;
+                let labels = sorted_labels(store.get_all_labels());
+                match output::format() {
+                    Format::Json => output::print_json(&labels)?,
+                    Format::Csv => {
+                        let rows: Vec<Vec<String>> =
+                            labels.iter().map(|x| vec![x.to_string()]).collect();
+                        output::print_csv(&["label"], &rows)?;
+                    }
+                    Format::Table => output::print_table(
+                        &[&msg!("column.label")],
+                        labels.iter().map(|x| vec![x.cell()]).collect(),
+                    )?,
+                    _ if labels.is_empty() => println!("{}", msg!("label.none")),
+                    _ => println!("{}", labels.join("\n")),
+                }
+            }
+            LabelCommands::Remove { name, force, yes } => {
+                let mut store = StoreHandle::open()?;
+                let count = store.count_sessions_with_any_label(&[name.as_str()]);
+                if count == 0 {
+                    hint_unknown_labels(&store, &[name]);
+                    println!("{}", msg!("common.nothing_changed"));
+                    return Ok(());
+                }
+                let question = msg!("label.remove_question", label = name, count = count);
+                if !prompt::confirm(&question, yes || handle::is_dry_run())? {
+                    println!("{}", msg!("common.nothing_changed"));
+                    return Ok(());
+                }
+                let removed_count = store.remove_label(&name, force)?;
+                store.save()?;
+                handle::success(&msg!("label.removed", count = removed_count));
+            }
+            LabelCommands::Merge {
+                source,
+                target,
+                force,
+                yes,
+            } => {
+                if source.is_empty() {
+                    println!("{}", msg!("label.no_sources"));
+                    return Ok(());
                 }
 
-                let mut store = Store::from_store_file().unwrap();
-                let replaced_count = store.merge_labels(source, target).unwrap();
-                store.save().unwrap();
-                println!("Replaced {} labels.", replaced_count,);
+                let mut store = StoreHandle::open()?;
+                hint_unknown_labels(&store, &source);
+                let sources: Vec<&str> = source.iter().map(|x| x.as_str()).collect();
+                let count = store.count_sessions_with_any_label(&sources);
+                let question = msg!(
+                    "label.merge_question",
+                    sources = sources.join(", "),
+                    target = target,
+                    count = count
+                );
+                if !prompt::confirm(&question, yes || handle::is_dry_run())? {
+                    println!("{}", msg!("common.nothing_changed"));
+                    return Ok(());
+                }
+
+                let replaced_count = store.merge_labels(source, target, force)?;
+                store.save()?;
+                handle::success(&msg!("label.replaced", count = replaced_count));
+            }
+        },
+        MainCommands::Start(args) => start(args)?,
+        MainCommands::Stop(args) => end(args)?,
+        MainCommands::Note(args) => update_note(args)?,
+        MainCommands::Status {
+            text,
+            idle_text,
+            running_color,
+            idle_color,
+        } => {
+            let colors = status::TmuxColors {
+                running: running_color,
+                idle: idle_color,
+            };
+            status::run(&Template::parse(&text)?, &idle_text, &colors)
+        }
+        MainCommands::Pomodoro {
+            work,
+            pause,
+            labels,
+            cycles,
+            track_breaks,
+        } => pomodoro::run(pomodoro::Options {
+            work,
+            pause,
+            labels,
+            cycles,
+            track_breaks,
+        })?,
+        MainCommands::Remind { after, repeat } => remind::run(after, repeat)?,
+        MainCommands::WatchIdle { threshold, discard } => idle::run(threshold, discard)?,
+        MainCommands::Ui => ui::run()?,
+        MainCommands::Undo { show } => {
+            let _lock = handle::lock_store()?;
+            let record = UndoRecord::load()?;
+            record.check_applicable()?;
+            if show || handle::is_dry_run() {
+                println!("{}", msg!("undo.available", command = record.command));
+                return Ok(());
+            }
+            let command = record.command.clone();
+            record.apply()?;
+            println!("{}", msg!("undo.done", command = command));
+        }
+        MainCommands::Batch { input, keep_going } => batch::run(&input, keep_going)?,
+        MainCommands::Doctor | MainCommands::Init { .. } => {
+            unreachable!("The command is handled before the config is loaded.")
+        }
+        MainCommands::Serve { listen } => serve::run(&listen)?,
+        MainCommands::MigrateJson { from, force } => {
+            let from = from.unwrap_or_else(|| config::get().database_path.value.clone());
+            let _lock = handle::lock_store()?;
+            let count = wtt::backend::migrate_json(&from, force)?;
+            println!(
+                "{}",
+                msg!(
+                    "migrate.copied",
+                    count = count,
+                    from = from,
+                    to = get_path_to_store_file()
+                )
+            );
+        }
+        MainCommands::Export(export_args) => match export_args.command {
+            ExportCommands::Prometheus { output } => {
+                let store = handle::load()?;
+                let metrics = export::prometheus(&store, LocalTZ::now());
+                match output {
+                    Some(path) => write_file_atomically(&path, &metrics)?,
+                    None => print!("{metrics}"),
+                }
+            }
+            ExportCommands::Timesheet {
+                dialect,
+                from,
+                to,
+                output,
+                strict,
+            } => export_timesheet(dialect, from, to, output, strict)?,
+            ExportCommands::Org {
+                group_by,
+                from,
+                to,
+                labels,
+                output,
+            } => {
+                let filter = Filter {
+                    from: from.as_deref().map(try_get_from_timestamp).transpose()?,
+                    to: to.as_deref().map(try_get_to_timestamp).transpose()?,
+                    labels,
+                    ..Default::default()
+                };
+                let group_by = match group_by {
+                    OrgGroupBy::Label => GroupBy::Label,
+                    OrgGroupBy::Day => GroupBy::Day,
+                };
+                let store = handle::load()?;
+                let document = export::org(&store, &filter, group_by);
+                match output {
+                    Some(path) => write_file_atomically(&path, &document)?,
+                    None => print!("{document}"),
+                }
+            }
+        },
+        MainCommands::Push(push_args) => match push_args.command {
+            PushCommands::Toggl { from, to, labels } => {
+                let filter = Filter {
+                    from: from.as_deref().map(try_get_from_timestamp).transpose()?,
+                    to: to.as_deref().map(try_get_to_timestamp).transpose()?,
+                    labels,
+                    ..Default::default()
+                };
+                let code = toggl::push(&filter)?;
+                if code != 0 {
+                    std::process::exit(code);
+                }
+            }
+            PushCommands::Caldav {
+                url,
+                from,
+                to,
+                labels,
+                prune,
+            } => {
+                let filter = Filter {
+                    from: from.as_deref().map(try_get_from_timestamp).transpose()?,
+                    to: to.as_deref().map(try_get_to_timestamp).transpose()?,
+                    labels,
+                    ..Default::default()
+                };
+                let code = caldav::push(&url, &filter, prune)?;
+                if code != 0 {
+                    std::process::exit(code);
+                }
+            }
+        },
+        MainCommands::Config(config_args) => match config_args.command {
+            ConfigCommands::Show {} => print_config(),
+        },
+        MainCommands::Completions { shell } => {
+            print!("{}", completions::generate(shell, &mut Cli::command()));
+        }
+        MainCommands::Man => print!("{}", man::generate(Cli::command())),
+        MainCommands::Complete { kind } => match kind {
+            CompleteKind::Labels => {
+                // Completion must never spill errors into the prompt, so a broken store completes nothing.
+                if let Ok(store) = Store::from_store_file() {
+                    for label in store.get_labels_by_recent_use() {
+                        println!("{label}");
+                    }
+                }
             }
         },
     }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn minutes(count: u64) -> String {
+        msg!("duration.minutes", count = count)
+    }
+
+    #[test]
+    fn durations_around_a_minute() {
+        assert_eq!(format_duration(0, false, " "), minutes(0));
+        assert_eq!(
+            format_duration(59, false, " "),
+            msg!("duration.under_minute")
+        );
+        assert_eq!(format_duration(60, false, " "), minutes(1));
+        assert_eq!(format_duration(61, false, " "), minutes(1));
+    }
+
+    #[test]
+    fn durations_beyond_u32_minutes() {
+        let minutes_total = u64::from(u32::MAX) + 61;
+        assert_eq!(
+            format_duration(minutes_total * 60 + 59, false, " "),
+            format!(
+                "{} {}",
+                msg!("duration.hours", count = minutes_total / 60),
+                minutes(minutes_total % 60)
+            )
+        );
+    }
+
+    #[test]
+    fn durations_with_hours_and_running() {
+        assert_eq!(
+            format_duration(3 * 3600 + 5 * 60 + 7, false, " "),
+            format!("{} {}", msg!("duration.hours", count = 3), minutes(5))
+        );
+        assert_eq!(
+            format_duration(40, true, " "),
+            format!(
+                "{} {}",
+                msg!("duration.running"),
+                msg!("duration.under_minute")
+            )
+        );
+    }
+
+    #[test]
+    fn note_keeps_the_line_breaks() {
+        assert_eq!(built_multilined_note("one\ntwo", 20), "one\ntwo");
+        assert_eq!(built_multilined_note("one\r\ntwo", 20), "one\ntwo");
+    }
+
+    #[test]
+    fn note_keeps_leading_trailing_and_blank_lines() {
+        assert_eq!(
+            built_multilined_note("\nfirst\n\nsecond\n", 20),
+            "\nfirst\n\nsecond\n"
+        );
+    }
+
+    #[test]
+    fn note_wraps_every_line_on_its_own() {
+        let note = built_multilined_note("aaa bbb ccc\nddd eee fff", 7);
+        assert_eq!(note, "aaa bbb\nccc\nddd eee\nfff");
+        assert!(note.lines().all(|x| x.chars().count() <= 7));
+    }
+
+    #[test]
+    fn note_breaks_a_long_word_in_each_paragraph() {
+        let note = built_multilined_note("abcdefghij\nklmnopqrst", 4);
+        assert_eq!(note, "abcd\nefgh\nij\nklmn\nopqr\nst");
+    }
+
+    #[test]
+    fn long_url_is_broken_at_the_width() {
+        let url = format!("https://example.com/{}", "a".repeat(180));
+        assert_eq!(url.chars().count(), 200);
+        let note = built_multilined_note(&url, 20);
+        let lines: Vec<&str> = note.lines().collect();
+        assert_eq!(lines.len(), 10);
+        assert!(lines.iter().all(|x| x.chars().count() == 20));
+        assert_eq!(lines.concat(), url);
+    }
+
+    #[test]
+    fn long_word_after_short_ones_starts_a_new_line() {
+        let note = built_multilined_note(&format!("see {}", "x".repeat(45)), 20);
+        assert_eq!(
+            note.lines()
+                .map(|x| x.chars().count())
+                .collect::<Vec<usize>>(),
+            vec![3, 20, 20, 5]
+        );
+    }
+
+    fn widths(note: &str) -> Vec<usize> {
+        note.lines()
+            .map(|x| x.chars().map(char_width).sum())
+            .collect()
+    }
+
+    #[test]
+    fn emoji_are_two_columns_wide() {
+        let text = "🎉".repeat(15);
+        let note = built_multilined_note(&text, 10);
+        assert_eq!(widths(&note), vec![10, 10, 10]);
+        assert_eq!(note.replace('\n', ""), text);
+        // An odd width can't split an emoji, so the line is a column short.
+        assert_eq!(widths(&built_multilined_note(&text, 7)), vec![6; 5]);
+    }
+
+    #[test]
+    fn cjk_text_wraps_by_display_width() {
+        let text = "日本語のテキストを折り返す";
+        let note = built_multilined_note(text, 8);
+        assert_eq!(note, "日本語の\nテキスト\nを折り返\nす");
+        assert!(widths(&note).iter().all(|x| *x <= 8));
+    }
+
+    #[test]
+    fn accented_words_wrap_at_spaces() {
+        let note = built_multilined_note("Café crème brûlée à la façon de grand-mère", 12);
+        assert_eq!(note, "Café crème\nbrûlée à la\nfaçon de\ngrand-mère");
+        // Combining accents take no column and stay with their letter.
+        let note = built_multilined_note(&"e\u{301}".repeat(12), 10);
+        assert_eq!(
+            note,
+            format!("{}\n{}", "e\u{301}".repeat(10), "e\u{301}".repeat(2))
+        );
+    }
+
+    #[test]
+    fn range_bound_with_a_time() {
+        let datetime = NaiveDate::from_ymd_opt(2024, 3, 12)
+            .unwrap()
+            .and_hms_opt(14, 0, 0)
+            .unwrap();
+        let value = datetime.format(datetime_format()).to_string();
+        let midnight = NaiveTime::from_hms_opt(0, 0, 0).unwrap();
+        let parsed = try_get_datetime_from_date_str(&value, midnight).unwrap();
+        assert_eq!(parsed.naive_local(), datetime);
+        // The time given wins over the time for a day alone, on both sides of a range.
+        assert_eq!(try_get_from_timestamp(&value), Ok(parsed.timestamp()));
+        assert_eq!(try_get_to_timestamp(&value), Ok(parsed.timestamp()));
+    }
+
+    #[test]
+    fn range_bound_without_a_time() {
+        let date = NaiveDate::from_ymd_opt(2024, 3, 12).unwrap();
+        let value = date.format(date_format()).to_string();
+        let end_of_day = NaiveTime::from_hms_opt(23, 59, 59).unwrap();
+        let parsed = try_get_datetime_from_date_str(&value, end_of_day).unwrap();
+        assert_eq!(parsed.naive_local(), date.and_time(end_of_day));
+    }
+
+    #[test]
+    fn range_bound_which_is_no_date() {
+        let midnight = NaiveTime::from_hms_opt(0, 0, 0).unwrap();
+        for value in ["", "12.03", "32.13.2024", "12.03.2024 25:00", "soon"] {
+            let error = try_get_datetime_from_date_str(value, midnight).unwrap_err();
+            assert!(error.contains(date_format()), "{value}: {error}");
+            assert!(try_get_from_timestamp(value).is_err(), "{value}");
+        }
+    }
+
+    fn range_bound(
+        value: &str,
+        time: (u32, u32, u32),
+    ) -> std::result::Result<NaiveDateTime, String> {
+        let today = NaiveDate::from_ymd_opt(2024, 3, 12).unwrap();
+        let time = NaiveTime::from_hms_opt(time.0, time.1, time.2).unwrap();
+        try_get_range_timestamp(value, time, today)
+            .map(|x| LocalTZ.timestamp_opt(x, 0).unwrap().naive_local())
+    }
+
+    fn day(month: u32, day: u32, time: (u32, u32, u32)) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, month, day)
+            .unwrap()
+            .and_hms_opt(time.0, time.1, time.2)
+            .unwrap()
+    }
+
+    #[test]
+    fn relative_range_bounds_against_a_fixed_day() {
+        let start = (0, 0, 0);
+        assert_eq!(range_bound("today", start), Ok(day(3, 12, start)));
+        assert_eq!(range_bound("yesterday", start), Ok(day(3, 11, start)));
+        assert_eq!(range_bound("-7d", start), Ok(day(3, 5, start)));
+        assert_eq!(range_bound("-2w", start), Ok(day(2, 27, start)));
+        assert_eq!(range_bound(" Today ", start), Ok(day(3, 12, start)));
+    }
+
+    #[test]
+    fn to_side_gets_the_end_of_the_day() {
+        let end = (23, 59, 59);
+        assert_eq!(range_bound("today", end), Ok(day(3, 12, end)));
+        assert_eq!(range_bound("week", end), Ok(day(3, 11, end)));
+        assert_eq!(range_bound("month", end), Ok(day(3, 1, end)));
+        assert_eq!(range_bound("-7d", end), Ok(day(3, 5, end)));
+    }
+
+    #[test]
+    fn unknown_range_bound_lists_the_accepted_forms() {
+        for value in ["fortnight", "last week", "-xd"] {
+            let error = range_bound(value, (0, 0, 0)).unwrap_err();
+            assert!(error.contains(time::RELATIVE_DAYS), "{value}: {error}");
+        }
+    }
 }
@@ -0,0 +1,201 @@
+//! The man page.
+//!
+//! The page is generated from the CLI definition and the settings table, so every subcommand, flag,
+//! setting and environment variable is documented without being written down twice.
+
+use clap::{Arg, Command};
+use clap_mangen::Man;
+use roff::{Roff, bold, italic, roman};
+use wtt::config::{ENVIRONMENT, SETTINGS};
+
+pub fn generate(command: Command) -> String {
+    let mut command = command.name("wtt");
+    // Building fills in what the derive leaves to clap, like which arguments take values.
+    command.build();
+    let man = Man::new(command.clone()).date(env!("CARGO_PKG_VERSION"));
+    let mut buffer: Vec<u8> = vec![];
+    man.render_title(&mut buffer)
+        .and_then(|_| man.render_name_section(&mut buffer))
+        .and_then(|_| man.render_synopsis_section(&mut buffer))
+        .and_then(|_| man.render_description_section(&mut buffer))
+        .and_then(|_| man.render_options_section(&mut buffer))
+        .expect("Could not render the man page.");
+
+    let mut roff = Roff::new();
+    roff.control("SH", ["COMMANDS"]);
+    for subcommand in command.get_subcommands() {
+        render_command(&mut roff, subcommand, "wtt");
+    }
+    render_configuration(&mut roff);
+    render_environment(&mut roff);
+    render_files(&mut roff);
+    render_exit_status(&mut roff);
+
+    let mut page = String::from_utf8(buffer).expect("The man page is not valid UTF-8.");
+    page.push_str(&roff.to_roff());
+    page
+}
+
+fn render_command(roff: &mut Roff, command: &Command, parent: &str) {
+    if command.is_hide_set() {
+        return;
+    }
+    let path = format!("{parent} {}", command.get_name());
+    let subcommands: Vec<&Command> = command.get_subcommands().collect();
+    // Commands which only group others have nothing to show but their children.
+    if subcommands.is_empty() {
+        roff.control("SS", [path.as_str()]);
+        if let Some(about) = command.get_long_about().or(command.get_about()) {
+            paragraphs(roff, &about.to_string());
+        }
+        for arg in command.get_arguments().filter(|x| !x.is_hide_set()) {
+            render_arg(roff, arg);
+        }
+        if let Some(help) = command.get_after_long_help() {
+            roff.control("PP", []);
+            roff.control("nf", []);
+            roff.text([roman(help.to_string())]);
+            roff.control("fi", []);
+        }
+    }
+    for subcommand in subcommands {
+        render_command(roff, subcommand, &path);
+    }
+}
+
+fn render_arg(roff: &mut Roff, arg: &Arg) {
+    let mut names: Vec<String> = vec![];
+    if let Some(short) = arg.get_short() {
+        names.push(format!("-{short}"));
+    }
+    if let Some(long) = arg.get_long() {
+        names.push(format!("--{long}"));
+    }
+    let takes_value = arg.get_num_args().is_some_and(|x| x.takes_values());
+    let value_name = arg
+        .get_value_names()
+        .and_then(|x| x.first())
+        .map(|x| x.to_string())
+        .unwrap_or_else(|| arg.get_id().to_string().to_uppercase());
+
+    roff.control("TP", []);
+    if names.is_empty() {
+        roff.text([italic(format!("<{value_name}>"))]);
+    } else if takes_value && !matches!(arg.get_action(), clap::ArgAction::Count) {
+        roff.text([
+            bold(names.join(", ")),
+            roman(" "),
+            italic(format!("<{value_name}>")),
+        ]);
+    } else {
+        roff.text([bold(names.join(", "))]);
+    }
+    if let Some(help) = arg.get_long_help().or(arg.get_help()) {
+        roff.text([roman(help.to_string())]);
+    }
+}
+
+fn render_configuration(roff: &mut Roff) {
+    roff.control("SH", ["CONFIGURATION"]);
+    roff.text([roman(
+        "The settings are read from a command-line flag, an environment variable, the config file \
+         or the default, in this order of precedence. In the TOML config file the part of a key \
+         before the dot is a table. `wtt config show` displays the effective values.",
+    )]);
+    for setting in SETTINGS {
+        roff.control("TP", []);
+        roff.text([
+            bold(setting.key),
+            roman(format!(" (default: {:?})", setting.default)),
+        ]);
+        roff.text([roman(setting.description)]);
+    }
+}
+
+fn render_environment(roff: &mut Roff) {
+    roff.control("SH", ["ENVIRONMENT"]);
+    for setting in SETTINGS {
+        if let Some(env) = setting.env {
+            roff.control("TP", []);
+            roff.text([bold(env)]);
+            roff.text([roman(format!(
+                "{} The same as the {} setting.",
+                setting.description, setting.key
+            ))]);
+        }
+    }
+    for (name, description) in ENVIRONMENT {
+        roff.control("TP", []);
+        roff.text([bold(*name)]);
+        roff.text([roman(*description)]);
+    }
+}
+
+fn render_files(roff: &mut Roff) {
+    roff.control("SH", ["FILES"]);
+    let files = [
+        ("~/.config/wtt/config.toml", "The config file."),
+        (
+            "~/.config/wtt/hooks/",
+            "Executable scripts named pre-start, post-start, pre-end and post-end, run around session \
+             events with WTT_SESSION_ID, WTT_LABELS, WTT_START, WTT_END and WTT_NOTE set. A failing \
+             pre hook aborts the command.",
+        ),
+        (
+            "<db>",
+            "The database, a JSON file. See storage.path for where it is.",
+        ),
+        (
+            "<db>.undo",
+            "The state before the last change, for `wtt undo`.",
+        ),
+        (
+            "<db>.last-query",
+            "The filters saved by `session table --save-query`.",
+        ),
+        (
+            "<db>.today.json",
+            "Today's totals, when storage.today_file is on.",
+        ),
+        (
+            "<db>.nag",
+            "When the long-running sessions were reminded about last.",
+        ),
+    ];
+    for (path, description) in files {
+        roff.control("TP", []);
+        roff.text([italic(path)]);
+        roff.text([roman(description)]);
+    }
+}
+
+fn render_exit_status(roff: &mut Roff) {
+    roff.control("SH", ["EXIT STATUS"]);
+    let codes = [
+        ("0", "Success."),
+        (
+            "1",
+            "The command failed, `wtt doctor` found a problem or a line of `wtt batch` failed.",
+        ),
+        ("2", "The command line is invalid."),
+        (
+            "101",
+            "An unexpected error, e.g. the database could not be read.",
+        ),
+    ];
+    for (code, description) in codes {
+        roff.control("TP", []);
+        roff.text([bold(code)]);
+        roff.text([roman(description)]);
+    }
+}
+
+/// Render text with blank lines between paragraphs as roff paragraphs.
+fn paragraphs(roff: &mut Roff, text: &str) {
+    for (index, paragraph) in text.split("\n\n").enumerate() {
+        if index > 0 {
+            roff.control("PP", []);
+        }
+        roff.text([roman(paragraph.replace('\n', " "))]);
+    }
+}
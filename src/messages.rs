@@ -0,0 +1,184 @@
+//! Translations of the user-facing messages.
+//!
+//! The messages live in TOML catalogs under src/locales, one per language, embedded into the binary.
+//! English has every message. A message missing from another catalog is shown in English.
+//! The language is taken from WTT_LANG, then from the usual LC_ALL, LC_MESSAGES and LANG.
+//!
+//! Look a message up with `msg!("section.key", name = value)`, which fills in the {name} placeholders.
+
+use std::{collections::HashMap, fmt::Display, sync::OnceLock};
+
+type Catalog = HashMap<String, String>;
+
+const ENGLISH: &str = "en";
+/// The embedded catalogs, English first.
+const CATALOGS: &[(&str, &str)] = &[
+    (ENGLISH, include_str!("locales/en.toml")),
+    ("uk", include_str!("locales/uk.toml")),
+];
+
+static LOADED: OnceLock<(Catalog, Option<Catalog>)> = OnceLock::new();
+
+/// Look up a message and fill in its placeholders, e.g. `msg!("session.not_found", id = id)`.
+#[macro_export]
+macro_rules! msg {
+    ($key:expr $(, $name:ident = $value:expr)* $(,)?) => {
+        $crate::messages::format(
+            $key,
+            &[$((stringify!($name), &$value as &dyn ::std::fmt::Display)),*],
+        )
+    };
+}
+
+/// The message in the current language, with the {name} placeholders replaced by the values.
+/// An unknown key is returned as is, so a typo is visible instead of fatal.
+pub fn format(key: &str, args: &[(&str, &dyn Display)]) -> String {
+    let (english, translation) = LOADED.get_or_init(load);
+    let Some(template) = lookup(key, english, translation.as_ref()) else {
+        log::warn!("There is no message {key}.");
+        return key.to_string();
+    };
+    fill(template, args)
+}
+
+/// The translated message, or the English one when the translation doesn't have it.
+fn lookup<'a>(
+    key: &str,
+    english: &'a Catalog,
+    translation: Option<&'a Catalog>,
+) -> Option<&'a str> {
+    translation
+        .and_then(|x| x.get(key))
+        .or_else(|| english.get(key))
+        .map(String::as_str)
+}
+
+/// The language code of the messages, e.g. "uk" for LANG=uk_UA.UTF-8.
+pub fn language() -> String {
+    ["WTT_LANG", "LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|x| std::env::var(x).ok())
+        .find(|x| !x.is_empty())
+        .map(|x| {
+            x.split(['_', '.', '@', '-'])
+                .next()
+                .unwrap_or_default()
+                .to_lowercase()
+        })
+        .filter(|x| x != "c" && x != "posix")
+        .unwrap_or_else(|| ENGLISH.to_string())
+}
+
+fn load() -> (Catalog, Option<Catalog>) {
+    let language = language();
+    log::debug!("Messages language: {language}.");
+    let english = parse(CATALOGS[0].1);
+    let translation = CATALOGS
+        .iter()
+        .skip(1)
+        .find(|(code, _)| *code == language)
+        .map(|(_, text)| parse(text));
+    (english, translation)
+}
+
+/// Flatten the sections of a catalog into "section.key" keys.
+fn parse(text: &str) -> Catalog {
+    let table: toml::Table = toml::from_str(text).expect("An embedded message catalog is broken.");
+    let mut catalog = Catalog::new();
+    for (section, values) in table.iter() {
+        let Some(values) = values.as_table() else {
+            continue;
+        };
+        for (key, value) in values.iter() {
+            if let Some(value) = value.as_str() {
+                catalog.insert(format!("{section}.{key}"), value.to_string());
+            }
+        }
+    }
+    catalog
+}
+
+fn fill(template: &str, args: &[(&str, &dyn Display)]) -> String {
+    let mut result = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(index) = rest.find(['{', '}']) {
+        result.push_str(&rest[..index]);
+        rest = &rest[index..];
+        if let Some(x) = rest.strip_prefix("{{") {
+            result.push('{');
+            rest = x;
+        } else if let Some(x) = rest.strip_prefix("}}") {
+            result.push('}');
+            rest = x;
+        } else if rest.starts_with('{')
+            && let Some(end) = rest.find('}')
+            && let Some((_, value)) = args.iter().find(|(name, _)| *name == &rest[1..end])
+        {
+            result.push_str(&value.to_string());
+            rest = &rest[end + 1..];
+        } else {
+            // An unknown placeholder is kept, so the message still shows what's missing.
+            result.push_str(&rest[..1]);
+            rest = &rest[1..];
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_translation_falls_back_to_english() {
+        let english = parse("[session]\nstarted = \"Started {id}.\"\nended = \"Ended.\"\n");
+        let translation = parse("[session]\nstarted = \"Почато {id}.\"\n");
+        assert_eq!(
+            lookup("session.started", &english, Some(&translation)),
+            Some("Почато {id}.")
+        );
+        assert_eq!(
+            lookup("session.ended", &english, Some(&translation)),
+            Some("Ended.")
+        );
+        assert_eq!(lookup("session.ended", &english, None), Some("Ended."));
+        assert_eq!(
+            lookup("session.unknown", &english, Some(&translation)),
+            None
+        );
+    }
+
+    #[test]
+    fn unknown_key_is_shown_as_is() {
+        assert_eq!(format("no_such.message", &[]), "no_such.message");
+    }
+
+    #[test]
+    fn placeholders_are_filled_in() {
+        assert_eq!(
+            fill(
+                "Ended {id} after {count}.",
+                &[("id", &"ab12"), ("count", &3)]
+            ),
+            "Ended ab12 after 3."
+        );
+        assert_eq!(
+            fill("Literal {{id}}, unknown {name}.", &[("id", &1)]),
+            "Literal {id}, unknown {name}."
+        );
+    }
+
+    #[test]
+    fn translations_have_only_english_keys() {
+        let english = parse(CATALOGS[0].1);
+        for (code, text) in &CATALOGS[1..] {
+            for key in parse(text).keys() {
+                assert!(
+                    english.contains_key(key),
+                    "{code} has {key}, which English doesn't"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,93 @@
+//! A reminder about sessions which were probably left running by mistake.
+//!
+//! The commands which load the store check it for sessions running longer than
+//! `behavior.nag_after_hours` and print a notice to stderr, so it never gets into JSON or CSV
+//! output. The store they loaded is reused, so the check costs no extra read of the database.
+//! A session is mentioned at most once an hour, which is tracked in a state file next to the
+//! database. Set WTT_NO_NAG=1 to silence it.
+
+use std::{
+    collections::HashMap,
+    fs,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use chrono::Local as LocalTZ;
+use wtt::{
+    config, msg,
+    store::{Store, get_path_to_store_file},
+};
+
+use crate::handle;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+const REPEAT_AFTER_SECONDS: i64 = 60 * 60;
+
+fn get_path_to_nag_file() -> String {
+    format!("{}.nag", get_path_to_store_file())
+}
+
+/// Let the next store loaded by the command be checked.
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Print the notices, once per run and only if enabled. Any problem here is ignored, it must not
+/// break the command itself.
+pub fn check(store: &Store) {
+    if !ENABLED.swap(false, Ordering::Relaxed) {
+        return;
+    }
+    let hours = config::get().nag_after_hours.value;
+    if hours == 0 || std::env::var("WTT_NO_NAG").is_ok_and(|x| x == "1") {
+        return;
+    }
+
+    let now = LocalTZ::now().timestamp();
+    let path = get_path_to_nag_file();
+    // When each session was mentioned last, by id.
+    let mut last_nagged: HashMap<String, i64> = fs::read_to_string(&path)
+        .ok()
+        .and_then(|x| serde_json::from_str(&x).ok())
+        .unwrap_or_default();
+    // Forget the sessions which have ended or are gone.
+    last_nagged.retain(|id, _| {
+        store
+            .sessions
+            .iter()
+            .any(|x| &x.id == id && x.end_at.is_none())
+    });
+
+    for session in store.sessions.iter().filter(|x| x.end_at.is_none()) {
+        let running_hours = (now - session.start_at) / 3600;
+        if running_hours < i64::from(hours) {
+            continue;
+        }
+        if last_nagged
+            .get(&session.id)
+            .is_some_and(|x| now - x < REPEAT_AFTER_SECONDS)
+        {
+            continue;
+        }
+        let short_id: String = session.id.chars().take(8).collect();
+        eprintln!(
+            "{}",
+            msg!(
+                "session.running_long",
+                seq = session.seq,
+                id = short_id,
+                labels = session.labels.join(", "),
+                hours = running_hours
+            )
+        );
+        last_nagged.insert(session.id.clone(), now);
+    }
+
+    if handle::is_dry_run() {
+        return;
+    }
+    if let Ok(json) = serde_json::to_string(&last_nagged) {
+        let _ = fs::write(&path, json);
+    }
+}
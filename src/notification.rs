@@ -0,0 +1,32 @@
+//! Desktop notifications.
+//!
+//! The notification is handed to a platform tool when one is installed: notify-send on Linux
+//! and the BSDs, osascript on macOS.
+
+use std::process::{Command, Stdio};
+
+/// Returns whether a tool showed the notification. It never fails, because a missed notification
+/// must not stop the command which sends it.
+pub fn send(summary: &str, body: &str) -> bool {
+    let script = format!(
+        "display notification {} with title {}",
+        apple_script_string(body),
+        apple_script_string(summary)
+    );
+    let tools: [(&str, Vec<&str>); 2] = [
+        ("notify-send", vec!["--app-name=wtt", summary, body]),
+        ("osascript", vec!["-e", &script]),
+    ];
+    tools.iter().any(|(name, args)| {
+        Command::new(name)
+            .args(args)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|x| x.success())
+    })
+}
+
+fn apple_script_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
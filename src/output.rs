@@ -0,0 +1,95 @@
+//! Output formats for the global --format flag.
+//!
+//! Commands which display data compute it first and then render it in the chosen format.
+//! Which formats a command supports is listed in `supported_formats` in main, and the first one
+//! is its default. Asking a command for a format it doesn't support is an error.
+
+use std::{fmt, io, io::Write, sync::Mutex};
+
+use cli_table::{CellStruct, ColorChoice, Style, Table};
+use wtt::{Result, config};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    Table,
+    Json,
+    Csv,
+    Plain,
+    /// The JSON of a custom waybar module, for `wtt status`.
+    Waybar,
+    /// A colored segment for the tmux status line, for `wtt status`.
+    Tmux,
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Format::Table => write!(f, "table"),
+            Format::Json => write!(f, "json"),
+            Format::Csv => write!(f, "csv"),
+            Format::Plain => write!(f, "plain"),
+            Format::Waybar => write!(f, "waybar"),
+            Format::Tmux => write!(f, "tmux"),
+        }
+    }
+}
+
+static FORMAT: Mutex<Format> = Mutex::new(Format::Plain);
+
+/// Choose the format of this run (or of one line of a batch) from the one requested with --format and the ones the command supports.
+pub fn set_format(requested: Option<Format>, supported: &[Format]) -> Result<()> {
+    let format = match requested {
+        None => supported[0],
+        Some(x) if supported.contains(&x) => x,
+        Some(x) => {
+            let supported: Vec<String> = supported.iter().map(|x| x.to_string()).collect();
+            return Err(format!(
+                "This command doesn't support --format {x}. Use one of: {}.",
+                supported.join(", ")
+            )
+            .into());
+        }
+    };
+    *FORMAT.lock().unwrap() = format;
+    Ok(())
+}
+
+pub fn format() -> Format {
+    *FORMAT.lock().unwrap()
+}
+
+pub fn print_json(value: &impl serde::Serialize) -> Result<()> {
+    let json = serde_json::to_string_pretty(value)
+        .map_err(|x| format!("Could not create a JSON string. {x}"))?;
+    writeln!(io::stdout(), "{json}").map_err(|x| format!("Could not write JSON to stdout. {x}"))?;
+    Ok(())
+}
+
+pub fn print_csv(headers: &[&str], rows: &[Vec<String>]) -> Result<()> {
+    let mut writer = csv::Writer::from_writer(io::stdout());
+    writer
+        .write_record(headers)
+        .and_then(|_| rows.iter().try_for_each(|x| writer.write_record(x)))
+        .and_then(|_| writer.flush().map_err(csv::Error::from))
+        .map_err(|x| format!("Could not write CSV to stdout. {x}"))?;
+    Ok(())
+}
+
+/// Print a table with a bold header, styled according to the `display.colors` setting.
+pub fn print_table(headers: &[&str], rows: Vec<Vec<CellStruct>>) -> Result<()> {
+    let color_choice = if config::get().colors.value {
+        ColorChoice::Always
+    } else {
+        ColorChoice::Never
+    };
+    let title: Vec<CellStruct> = headers
+        .iter()
+        .map(|x| cli_table::Cell::cell(*x).bold(true))
+        .collect();
+    let table = rows.table().color_choice(color_choice).title(title);
+    let table = table
+        .display()
+        .map_err(|x| format!("Could not build a table. {x}"))?;
+    writeln!(io::stdout(), "{table}").map_err(|x| format!("Could not write the table. {x}"))?;
+    Ok(())
+}
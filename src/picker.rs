@@ -0,0 +1,169 @@
+//! An interactive picker for the commands which need a session, used with --pick.
+//!
+//! The candidates are narrowed down as you type with a small fuzzy matcher: every typed character
+//! has to appear in the candidate in the same order, and tighter matches rank higher.
+
+use std::{cmp::Reverse, io::IsTerminal};
+
+use chrono::{Local as LocalTZ, TimeZone};
+use ratatui::{
+    TerminalOptions, Viewport,
+    crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Layout},
+    style::Style,
+    text::Line,
+    widgets::{List, ListState, Paragraph},
+};
+use wtt::{Result, store::Session};
+
+use crate::datetime_format;
+
+const HEIGHT: u16 = 12;
+const NOTE_EXCERPT_LENGTH: usize = 40;
+
+struct Candidate {
+    id: String,
+    text: String,
+}
+
+/// Let the user choose one of the sessions. Returns its id.
+pub fn pick_session(sessions: &[&Session]) -> Result<String> {
+    if !std::io::stdin().is_terminal() || !std::io::stdout().is_terminal() {
+        return Err("--pick needs an interactive terminal. Use --id instead.".into());
+    }
+    if sessions.is_empty() {
+        return Err("There are no sessions to pick from.".into());
+    }
+
+    let candidates: Vec<Candidate> = sessions
+        .iter()
+        .map(|x| Candidate {
+            id: x.id.clone(),
+            text: describe(x),
+        })
+        .collect();
+
+    let mut terminal = ratatui::try_init_with_options(TerminalOptions {
+        viewport: Viewport::Inline(HEIGHT),
+    })
+    .map_err(|x| {
+        ratatui::restore();
+        format!("Could not open the picker in this terminal. {x}")
+    })?;
+    let result = run(&mut terminal, &candidates);
+    ratatui::restore();
+    // The inline viewport stays on the screen, so the next output starts below it.
+    println!();
+    result
+}
+
+fn run(terminal: &mut ratatui::DefaultTerminal, candidates: &[Candidate]) -> Result<String> {
+    let mut query = String::new();
+    let mut state = ListState::default().with_selected(Some(0));
+
+    loop {
+        let matches: Vec<&Candidate> = filter(candidates, &query);
+        if state.selected().is_none_or(|x| x >= matches.len()) {
+            state.select(Some(0));
+        }
+
+        terminal.draw(|frame| {
+            let [input_area, list_area] =
+                Layout::vertical([Constraint::Length(1), Constraint::Min(1)]).areas(frame.area());
+            frame.render_widget(
+                Paragraph::new(format!(
+                    "> {query}█  ({}/{})",
+                    matches.len(),
+                    candidates.len()
+                )),
+                input_area,
+            );
+            let items: Vec<Line> = matches
+                .iter()
+                .map(|x| Line::from(x.text.as_str()))
+                .collect();
+            let list = List::new(items).highlight_style(Style::new().reversed());
+            frame.render_stateful_widget(list, list_area, &mut state);
+        })?;
+
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match key.code {
+            KeyCode::Enter => {
+                return match state.selected().and_then(|x| matches.get(x)) {
+                    Some(x) => Ok(x.id.clone()),
+                    None => Err("No session matches the query.".into()),
+                };
+            }
+            KeyCode::Esc => return Err("Nothing was picked.".into()),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                return Err("Nothing was picked.".into());
+            }
+            KeyCode::Down => state.select_next(),
+            KeyCode::Up => state.select_previous(),
+            KeyCode::Backspace => {
+                query.pop();
+            }
+            KeyCode::Char(c) => query.push(c),
+            _ => {}
+        }
+    }
+}
+
+fn describe(session: &Session) -> String {
+    let start = LocalTZ
+        .timestamp_opt(session.start_at, 0)
+        .unwrap()
+        .format(datetime_format());
+    let note: String = session
+        .note
+        .as_deref()
+        .unwrap_or("")
+        .replace('\n', " ")
+        .chars()
+        .take(NOTE_EXCERPT_LENGTH)
+        .collect();
+    format!(
+        "#{:<5} {start}  [{}]  {note}",
+        session.seq,
+        session.labels.join(", ")
+    )
+}
+
+/// The candidates which match the query, the best matches first.
+fn filter<'a>(candidates: &'a [Candidate], query: &str) -> Vec<&'a Candidate> {
+    let mut scored: Vec<(u32, &Candidate)> = candidates
+        .iter()
+        .filter_map(|x| fuzzy_score(&x.text, query).map(|score| (score, x)))
+        .collect();
+    // Stable, so candidates with the same score keep their order.
+    scored.sort_by_key(|x| Reverse(x.0));
+    scored.into_iter().map(|(_, x)| x).collect()
+}
+
+/// None if the query characters don't appear in the text in order (ignoring case).
+/// Otherwise a score which is higher for consecutive matches and matches at word starts.
+fn fuzzy_score(text: &str, query: &str) -> Option<u32> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score: u32 = 0;
+    let mut position: usize = 0;
+    let mut previous: Option<usize> = None;
+
+    for c in query.to_lowercase().chars().filter(|x| !x.is_whitespace()) {
+        let index = position + text[position..].iter().position(|x| *x == c)?;
+        score += 1;
+        if previous.is_some_and(|x| x + 1 == index) {
+            score += 4;
+        }
+        if index == 0 || !text[index - 1].is_alphanumeric() {
+            score += 2;
+        }
+        previous = Some(index);
+        position = index + 1;
+    }
+    Some(score)
+}
@@ -0,0 +1,142 @@
+//! Working in timed blocks with breaks in between.
+//!
+//! Every work block is a normal session, started and ended by the countdown, so the reports
+//! don't need to know about pomodoros. Breaks are not tracked unless asked to, and then they're
+//! sessions labeled "break". Ctrl-C or SIGTERM ends the current session at the moment it came.
+
+use std::{
+    io::Write,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::Duration as StdDuration,
+};
+
+use chrono::{Duration, Local as LocalTZ};
+use signal_hook::consts::{SIGINT, SIGTERM};
+use wtt::{Result, config, store::NoteMode};
+
+use crate::{handle, handle::StoreHandle, notification, prompt};
+
+const BREAK_LABEL: &str = "break";
+
+pub struct Options {
+    pub work: Duration,
+    pub pause: Duration,
+    pub labels: Vec<String>,
+    pub cycles: u32,
+    pub track_breaks: bool,
+}
+
+pub fn run(options: Options) -> Result<()> {
+    if handle::is_dry_run() {
+        return Err(
+            "A pomodoro can't be a dry run, because it saves its sessions as it goes.".into(),
+        );
+    }
+    let stop = Arc::new(AtomicBool::new(false));
+    for signal in [SIGTERM, SIGINT] {
+        signal_hook::flag::register(signal, Arc::clone(&stop))
+            .map_err(|x| format!("Could not listen for the signal {signal}. {x}"))?;
+    }
+
+    for cycle in 1..=options.cycles {
+        let title = format!("Work {cycle}/{}", options.cycles);
+        let (id, start_at) = start(options.labels.clone())?;
+        let end_at = start_at + options.work.num_seconds();
+        let completed = count_down(&title, end_at, &stop);
+        end(&id, completed.then_some(end_at))?;
+        if !completed {
+            println!("\nStopped. The session {id} was ended.");
+            return Ok(());
+        }
+        notification::send(&format!("{title} is done"), "Time for a break.");
+        println!("\n\x07{title} is done.");
+        ask_note(&id)?;
+
+        if cycle == options.cycles {
+            break;
+        }
+        let title = format!("Break {cycle}/{}", options.cycles - 1);
+        let (break_id, start_at) = if options.track_breaks {
+            let (id, start_at) = start(vec![BREAK_LABEL.to_string()])?;
+            (Some(id), start_at)
+        } else {
+            (None, LocalTZ::now().timestamp())
+        };
+        let end_at = start_at + options.pause.num_seconds();
+        let completed = count_down(&title, end_at, &stop);
+        if let Some(id) = break_id.as_deref() {
+            end(id, completed.then_some(end_at))?;
+        }
+        if !completed {
+            println!("\nStopped during the break.");
+            return Ok(());
+        }
+        notification::send(&format!("{title} is over"), "Back to work.");
+        println!("\n\x07{title} is over.");
+    }
+    if options.cycles > 1 {
+        println!("All {} cycles are done.", options.cycles);
+    }
+    Ok(())
+}
+
+/// Start a session and return its id and start.
+fn start(labels: Vec<String>) -> Result<(String, i64)> {
+    let labels = if labels.is_empty() {
+        config::get().default_labels.value.clone()
+    } else {
+        labels
+    };
+    let is_break = labels == [BREAK_LABEL];
+    let kind = (is_break && config::get().kinds.value.iter().any(|x| x == BREAK_LABEL))
+        .then(|| BREAK_LABEL.to_string());
+
+    let mut store = StoreHandle::open()?;
+    let session = store.start_session(labels, None, kind, None)?;
+    let started = (session.id.clone(), session.start_at);
+    store.save_as("pomodoro: start a session")?;
+    Ok(started)
+}
+
+/// End a session at the end of its block, or now when it was stopped early.
+fn end(id: &str, at: Option<i64>) -> Result<()> {
+    let mut store = StoreHandle::open()?;
+    store.end_session(Some(id), None, None, None, at)?;
+    store.save_as("pomodoro: end a session")
+}
+
+/// Let the user describe the block. The session is already ended, so skipping costs nothing.
+fn ask_note(id: &str) -> Result<()> {
+    if !prompt::is_interactive() {
+        return Ok(());
+    }
+    let note = prompt::ask("What did you do? (Enter to skip)", "")?;
+    if note.is_empty() {
+        return Ok(());
+    }
+    let mut store = StoreHandle::open()?;
+    store.update_note(id, note, NoteMode::Replace, false)?;
+    store.save_as("pomodoro: add a note")?;
+    handle::success("Saved the note.");
+    Ok(())
+}
+
+/// Show the time left until the timestamp on one line. Returns false if it was stopped before the end.
+fn count_down(title: &str, end_at: i64, stop: &AtomicBool) -> bool {
+    loop {
+        if stop.load(Ordering::Relaxed) {
+            return false;
+        }
+        let left = end_at - LocalTZ::now().timestamp();
+        if left <= 0 {
+            return true;
+        }
+        print!("\r{title}: {:02}:{:02} left ", left / 60, left % 60);
+        let _ = std::io::stdout().flush();
+        thread::sleep(StdDuration::from_millis(200));
+    }
+}
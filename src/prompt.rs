@@ -0,0 +1,118 @@
+//! Confirmations and questions for the user.
+//!
+//! In a terminal the user has to type "y". Without a terminal (scripts, pipes) nobody can answer,
+//! so the command fails unless it was explicitly allowed with --yes or WTT_ASSUME_YES=1.
+//! Questions with a default answer just take the default then.
+
+use std::io::{BufRead, IsTerminal, Write};
+
+use wtt::Result;
+
+/// Ask the user to confirm a destructive action described by `question`.
+/// Returns false when the user declined.
+pub fn confirm(question: &str, yes: bool) -> Result<bool> {
+    let assume_yes = std::env::var("WTT_ASSUME_YES").is_ok_and(|x| x == "1");
+    confirm_with(
+        question,
+        yes || assume_yes,
+        std::io::stdin().is_terminal(),
+        &mut std::io::stdin().lock(),
+        &mut std::io::stdout(),
+    )
+}
+
+fn confirm_with(
+    question: &str,
+    yes: bool,
+    interactive: bool,
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+) -> Result<bool> {
+    if yes {
+        return Ok(true);
+    }
+    if !interactive {
+        return Err(format!(
+            "{question}\nThis needs a confirmation, but the input is not a terminal. Pass --yes to proceed."
+        )
+        .into());
+    }
+
+    write!(output, "{question} [y/N] ")?;
+    output.flush()?;
+    let mut answer = String::new();
+    input.read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Whether there is a user who can answer questions.
+pub fn is_interactive() -> bool {
+    std::io::stdin().is_terminal()
+}
+
+/// Ask the user for a value. An empty answer (or no terminal to ask in) gives the default.
+/// An empty default is not shown.
+pub fn ask(question: &str, default: &str) -> Result<String> {
+    if !is_interactive() {
+        return Ok(default.to_string());
+    }
+    let mut output = std::io::stdout();
+    if default.is_empty() {
+        write!(output, "{question} ")?;
+    } else {
+        write!(output, "{question} [{default}] ")?;
+    }
+    output.flush()?;
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer)?;
+    let answer = answer.trim();
+    Ok(if answer.is_empty() { default } else { answer }.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn answer(input: &str) -> (Result<bool>, String) {
+        let mut output: Vec<u8> = vec![];
+        let confirmed = confirm_with(
+            "Delete 2 sessions?",
+            false,
+            true,
+            &mut input.as_bytes(),
+            &mut output,
+        );
+        (confirmed, String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn yes_answers_confirm() {
+        for input in ["y\n", "Y\n", "yes\n", " YES \n"] {
+            assert!(answer(input).0.unwrap(), "{input:?}");
+        }
+    }
+
+    #[test]
+    fn anything_else_declines() {
+        for input in ["\n", "n\n", "no\n", "yep\n", ""] {
+            assert!(!answer(input).0.unwrap(), "{input:?}");
+        }
+    }
+
+    #[test]
+    fn the_question_is_shown_with_the_default() {
+        assert_eq!(answer("y\n").1, "Delete 2 sessions? [y/N] ");
+    }
+
+    #[test]
+    fn without_a_terminal_only_yes_proceeds() {
+        let mut output: Vec<u8> = vec![];
+        let error = confirm_with("Delete?", false, false, &mut "y\n".as_bytes(), &mut output)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("--yes"), "{error}");
+        assert!(output.is_empty());
+
+        assert!(confirm_with("Delete?", true, false, &mut "".as_bytes(), &mut output).unwrap());
+    }
+}
@@ -0,0 +1,123 @@
+//! Desktop reminders about a session which has been running for too long.
+//!
+//! It runs in the foreground, meant for a user service or `wtt remind &`. The database file is
+//! read again only when it changes, so waiting costs next to nothing. When the session ends or
+//! a newer one starts, the reminders start over for the newest running session.
+
+use std::{
+    fs,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::{Duration as StdDuration, SystemTime},
+};
+
+use chrono::{Duration, Local as LocalTZ};
+use signal_hook::consts::{SIGINT, SIGTERM};
+use wtt::{
+    Result,
+    store::{Store, get_path_to_store_file},
+};
+
+use crate::{format_duration, notification};
+
+const POLL_INTERVAL: StdDuration = StdDuration::from_secs(1);
+
+/// The running session the reminders are about.
+struct Watched {
+    id: String,
+    labels: Vec<String>,
+    start_at: i64,
+    /// When to remind next, None after the only reminder without --repeat.
+    remind_at: Option<i64>,
+}
+
+pub fn run(after: Duration, repeat: Option<Duration>) -> Result<()> {
+    let stop = Arc::new(AtomicBool::new(false));
+    for signal in [SIGTERM, SIGINT] {
+        signal_hook::flag::register(signal, Arc::clone(&stop))
+            .map_err(|x| format!("Could not listen for the signal {signal}. {x}"))?;
+    }
+    println!(
+        "Reminding about sessions running longer than {}. Stop with Ctrl-C.",
+        format_duration(after.num_seconds().max(0) as u64, false, " ")
+    );
+
+    let mut modified: Option<SystemTime> = None;
+    let mut watched: Option<Watched> = None;
+    let mut loaded = false;
+    while !stop.load(Ordering::Relaxed) {
+        let current_modified = get_modified_time();
+        if !loaded || current_modified != modified {
+            match Store::from_store_file() {
+                Ok(store) => {
+                    watched = watch(&store, watched, after);
+                    modified = current_modified;
+                    loaded = true;
+                }
+                // The file may be in the middle of a save, so try again on the next poll.
+                Err(x) => log::warn!("Could not read the database. {x}"),
+            }
+        }
+
+        let now = LocalTZ::now().timestamp();
+        if let Some(session) = watched.as_mut()
+            && session.remind_at.is_some_and(|x| now >= x)
+        {
+            remind(session, now);
+            session.remind_at = repeat.map(|x| now + x.num_seconds());
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+    println!("Stopped reminding.");
+    Ok(())
+}
+
+/// Keep watching the same session, or start over with the newest running one.
+fn watch(store: &Store, watched: Option<Watched>, after: Duration) -> Option<Watched> {
+    let session = store
+        .sessions
+        .iter()
+        .filter(|x| x.end_at.is_none())
+        .max_by_key(|x| x.start_at)?;
+    match watched {
+        Some(x) if x.id == session.id => Some(Watched {
+            labels: session.labels.clone(),
+            ..x
+        }),
+        _ => {
+            log::debug!("Watching the session {}.", session.id);
+            Some(Watched {
+                id: session.id.clone(),
+                labels: session.labels.clone(),
+                start_at: session.start_at,
+                remind_at: Some(session.start_at + after.num_seconds()),
+            })
+        }
+    }
+}
+
+fn remind(session: &Watched, now: i64) {
+    let elapsed = format_duration((now - session.start_at).max(0) as u64, false, " ");
+    let body = if session.labels.is_empty() {
+        format!("The session has been running for {elapsed}.")
+    } else {
+        format!(
+            "{} has been running for {elapsed}.",
+            session.labels.join(", ")
+        )
+    };
+    if notification::send("Time for a break?", &body) {
+        println!("Reminded: {body}");
+    } else {
+        eprintln!("Could not show a notification, is notify-send installed? {body}");
+    }
+}
+
+fn get_modified_time() -> Option<SystemTime> {
+    fs::metadata(get_path_to_store_file())
+        .and_then(|x| x.modified())
+        .ok()
+}
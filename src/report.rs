@@ -0,0 +1,562 @@
+use std::collections::BTreeMap;
+
+use chrono::{Local as LocalTZ, NaiveTime, TimeZone};
+
+use crate::store::{Filter, Session, Store};
+
+/// How to split the report into subtotals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupBy {
+    None,
+    /// One subtotal per local calendar day the session was started on.
+    Day,
+    /// One subtotal per label. A session with several labels counts towards each of them.
+    Label,
+    /// One subtotal per label. A session with several labels is divided evenly between them,
+    /// so the subtotals add up to the total.
+    LabelSplit,
+    /// One subtotal per kind. Sessions without a kind are grouped under "(none)".
+    Kind,
+}
+
+/// Sessions selected by a filter together with their durations, ready to be rendered.
+/// All durations are in seconds.
+#[derive(serde::Serialize, Debug)]
+pub struct Report {
+    pub rows: Vec<ReportRow>,
+    pub subtotals: Vec<Subtotal>,
+    pub total: u64,
+    /// The length of the union of all rows, so overlapping sessions are counted once.
+    pub wallclock_total: u64,
+}
+
+#[derive(serde::Serialize, Debug)]
+pub struct ReportRow {
+    pub id: String,
+    pub seq: u64,
+    pub start_at: i64,
+    pub end_at: Option<i64>,
+    pub labels: Vec<String>,
+    pub note: Option<String>,
+    /// Running sessions are counted up to now.
+    pub duration: u64,
+    pub rate: Option<f64>,
+    pub kind: Option<String>,
+}
+
+impl ReportRow {
+    /// The row of a running session, counted up to `now`.
+    pub fn running(session: &Session, now: i64) -> Self {
+        Self {
+            id: session.id.clone(),
+            seq: session.seq,
+            start_at: session.start_at,
+            end_at: None,
+            labels: session.labels.clone(),
+            note: session.note.clone(),
+            duration: session
+                .worked_intervals(session.start_at, now)
+                .iter()
+                .map(|(start, end)| end - start)
+                .sum::<i64>()
+                .max(0) as u64,
+            rate: session.rate,
+            kind: session.kind.clone(),
+        }
+    }
+}
+
+#[derive(serde::Serialize, Debug)]
+pub struct Subtotal {
+    /// A label, or a day in the "yyyy-mm-dd" format.
+    pub key: String,
+    pub duration: u64,
+    pub count: u32,
+}
+
+/// Amounts to bill per label. A session with several labels is billed under each of them,
+/// but only once in the total.
+#[derive(serde::Serialize, Debug)]
+pub struct Billing {
+    pub labels: Vec<BillingLine>,
+    pub total_duration: u64,
+    pub total_amount: f64,
+    /// Sessions without their own rate when no default rate was given. They are not billed.
+    pub unrated_count: u32,
+}
+
+#[derive(serde::Serialize, Debug)]
+pub struct BillingLine {
+    pub label: String,
+    pub duration: u64,
+    pub amount: f64,
+}
+
+impl Report {
+    /// Bill every row by its own rate, or by the default rate when the session has none.
+    pub fn billing(&self, default_rate: Option<f64>) -> Billing {
+        let mut labels: BTreeMap<&str, BillingLine> = BTreeMap::new();
+        let mut total_duration: u64 = 0;
+        let mut total_amount: f64 = 0.0;
+        let mut unrated_count: u32 = 0;
+
+        for row in &self.rows {
+            let Some(rate) = row.rate.or(default_rate) else {
+                unrated_count += 1;
+                continue;
+            };
+            let amount = row.duration as f64 / 3600.0 * rate;
+            total_duration += row.duration;
+            total_amount += amount;
+
+            for label in &row.labels {
+                let line = labels.entry(label).or_insert(BillingLine {
+                    label: label.clone(),
+                    duration: 0,
+                    amount: 0.0,
+                });
+                line.duration += row.duration;
+                line.amount += amount;
+            }
+        }
+
+        Billing {
+            labels: labels.into_values().collect(),
+            total_duration,
+            total_amount,
+            unrated_count,
+        }
+    }
+}
+
+impl Store {
+    pub fn report(&self, filter: &Filter, group_by: GroupBy) -> Report {
+        let now = LocalTZ::now().timestamp();
+
+        let mut rows: Vec<ReportRow> = vec![];
+        let mut intervals: Vec<(i64, i64)> = vec![];
+        let mut subtotals: BTreeMap<String, Subtotal> = BTreeMap::new();
+        let mut total: u64 = 0;
+
+        for session in self.get_all_sessions(filter) {
+            let interval = if filter.clamp_to_range {
+                // The "to" bound is the last second of the range, so the range ends one second later.
+                clamp_interval(
+                    session.start_at,
+                    session.end_at.unwrap_or(now),
+                    filter.from,
+                    filter.to.map(|x| x + 1),
+                )
+            } else {
+                (session.start_at, session.end_at.unwrap_or(now))
+            };
+            // A session split at midnight gets a row per day. Only the first one starts
+            // when the session did, and only the last one ends when it did.
+            let pieces = match filter.split_days {
+                true => split_at_midnights(interval.0, interval.1),
+                false => vec![interval],
+            };
+            let last = pieces.len() - 1;
+
+            for (index, (start, end)) in pieces.into_iter().enumerate() {
+                let worked = session.worked_intervals(start, end);
+                let duration = worked
+                    .iter()
+                    .map(|(start, end)| end - start)
+                    .sum::<i64>()
+                    .max(0) as u64;
+                intervals.extend(worked);
+                total += duration;
+
+                let start_at = if index == 0 { session.start_at } else { start };
+                let end_at = if index == last {
+                    session.end_at
+                } else {
+                    Some(end)
+                };
+                let keys: Vec<(String, u64)> = match group_by {
+                    GroupBy::None => vec![],
+                    GroupBy::Day => vec![(
+                        LocalTZ
+                            .timestamp_opt(start_at, 0)
+                            .unwrap()
+                            .format("%Y-%m-%d")
+                            .to_string(),
+                        duration,
+                    )],
+                    GroupBy::Label => session
+                        .labels
+                        .iter()
+                        .map(|x| (x.clone(), duration))
+                        .collect(),
+                    GroupBy::LabelSplit => split_evenly(&session.labels, duration),
+                    GroupBy::Kind => vec![(
+                        session.kind.clone().unwrap_or("(none)".to_string()),
+                        duration,
+                    )],
+                };
+                for (key, duration) in keys {
+                    let subtotal = subtotals.entry(key.clone()).or_insert(Subtotal {
+                        key,
+                        duration: 0,
+                        count: 0,
+                    });
+                    subtotal.duration += duration;
+                    subtotal.count += 1;
+                }
+
+                rows.push(ReportRow {
+                    id: session.id.clone(),
+                    seq: session.seq,
+                    start_at,
+                    end_at,
+                    labels: session.labels.clone(),
+                    note: session.note.clone(),
+                    duration,
+                    rate: session.rate,
+                    kind: session.kind.clone(),
+                });
+            }
+        }
+
+        let wallclock_total: i64 = merge_intervals(intervals)
+            .iter()
+            .map(|(start_at, end_at)| end_at - start_at)
+            .sum();
+
+        log::debug!(
+            "The report has {} rows, {total} seconds in total.",
+            rows.len()
+        );
+        Report {
+            rows,
+            subtotals: subtotals.into_values().collect(),
+            total,
+            wallclock_total: wallclock_total.max(0) as u64,
+        }
+    }
+}
+
+/// Divide a duration between the labels. The seconds which don't divide evenly go to the first
+/// ones, so the shares add up to the duration.
+fn split_evenly(labels: &[String], duration: u64) -> Vec<(String, u64)> {
+    let count = labels.len() as u64;
+    labels
+        .iter()
+        .enumerate()
+        .map(|(index, label)| {
+            let extra = u64::from((index as u64) < duration % count);
+            (label.clone(), duration / count + extra)
+        })
+        .collect()
+}
+
+/// Merge overlapping and adjacent intervals [start, end).
+/// The result is sorted by start and contains no intersecting intervals.
+pub fn merge_intervals(mut intervals: Vec<(i64, i64)>) -> Vec<(i64, i64)> {
+    intervals.sort_unstable();
+    let mut merged: Vec<(i64, i64)> = vec![];
+    for (start, end) in intervals {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+/// Cut the interval [start_at, end_at) at every local midnight inside it.
+pub fn split_at_midnights(start_at: i64, end_at: i64) -> Vec<(i64, i64)> {
+    let mut pieces: Vec<(i64, i64)> = vec![];
+    let mut piece_start = start_at;
+    while let Some(midnight) = next_midnight(piece_start).filter(|x| *x < end_at) {
+        pieces.push((piece_start, midnight));
+        piece_start = midnight;
+    }
+    pieces.push((piece_start, end_at));
+    pieces
+}
+
+/// The start of the local day after the one of the timestamp.
+fn next_midnight(timestamp: i64) -> Option<i64> {
+    let date = LocalTZ.timestamp_opt(timestamp, 0).earliest()?.date_naive();
+    date.succ_opt()?
+        .and_time(NaiveTime::MIN)
+        .and_local_timezone(LocalTZ)
+        .earliest()
+        .map(|x| x.timestamp())
+}
+
+/// Intersect the interval [start_at, end_at) with the range [from, to).
+/// A missing bound leaves the corresponding side of the interval untouched.
+/// Returns an empty interval (start == end) when they don't intersect.
+pub fn clamp_interval(
+    start_at: i64,
+    end_at: i64,
+    from: Option<i64>,
+    to: Option<i64>,
+) -> (i64, i64) {
+    let start_at = from.map_or(start_at, |x| start_at.max(x));
+    let end_at = to.map_or(end_at, |x| end_at.min(x));
+    (start_at, end_at.max(start_at))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::tests::{session, store};
+
+    #[test]
+    fn clamp_interval_inside_the_range_is_unchanged() {
+        assert_eq!(clamp_interval(10, 20, Some(0), Some(30)), (10, 20));
+    }
+
+    #[test]
+    fn clamp_interval_cuts_the_parts_outside() {
+        assert_eq!(clamp_interval(10, 20, Some(15), None), (15, 20));
+        assert_eq!(clamp_interval(10, 20, None, Some(12)), (10, 12));
+        assert_eq!(clamp_interval(0, 100, Some(40), Some(60)), (40, 60));
+    }
+
+    #[test]
+    fn clamp_interval_outside_the_range_is_empty() {
+        let (start, end) = clamp_interval(10, 20, Some(30), Some(40));
+        assert_eq!(start, end);
+        let (start, end) = clamp_interval(10, 20, Some(0), Some(5));
+        assert_eq!(start, end);
+    }
+
+    #[test]
+    fn clamp_to_range_counts_the_part_inside_the_range() {
+        let store = store(vec![
+            session("before", 1000, Some(5000), &[]),
+            session("inside", 6000, Some(7000), &[]),
+            session("after", 9000, Some(12000), &[]),
+            session("outside", 20000, Some(21000), &[]),
+        ]);
+        let filter = Filter {
+            from: Some(4000),
+            // The last second of the range, which ends at 10000.
+            to: Some(9999),
+            clamp_to_range: true,
+            ..Default::default()
+        };
+        let report = store.report(&filter, GroupBy::None);
+        let durations: Vec<(&str, u64)> = report
+            .rows
+            .iter()
+            .map(|x| (x.id.as_str(), x.duration))
+            .collect();
+        assert_eq!(
+            durations,
+            vec![("before", 1000), ("inside", 1000), ("after", 1000)]
+        );
+        assert_eq!(report.total, 3000);
+    }
+
+    #[test]
+    fn without_clamping_the_range_keeps_the_sessions_inside() {
+        let store = store(vec![
+            session("before", 1000, Some(5000), &[]),
+            session("inside", 6000, Some(7000), &[]),
+        ]);
+        let filter = Filter {
+            from: Some(4000),
+            to: Some(9999),
+            ..Default::default()
+        };
+        let report = store.report(&filter, GroupBy::None);
+        assert_eq!(report.rows.len(), 1);
+        assert_eq!(report.total, 1000);
+    }
+
+    #[test]
+    fn short_sessions_add_up_by_the_second() {
+        let store = store(vec![
+            session("a", 0, Some(40), &[]),
+            session("b", 100, Some(140), &[]),
+            session("c", 200, Some(240), &[]),
+        ]);
+        let report = store.report(&Filter::default(), GroupBy::None);
+        assert!(report.rows.iter().all(|x| x.duration == 40));
+        assert_eq!(report.total, 120);
+    }
+
+    #[test]
+    fn merge_nested_intervals() {
+        assert_eq!(
+            merge_intervals(vec![(0, 100), (10, 20), (30, 90)]),
+            vec![(0, 100)]
+        );
+    }
+
+    #[test]
+    fn merge_adjacent_intervals() {
+        assert_eq!(merge_intervals(vec![(10, 20), (0, 10)]), vec![(0, 20)]);
+    }
+
+    #[test]
+    fn disjoint_intervals_stay_apart_and_sorted() {
+        assert_eq!(
+            merge_intervals(vec![(50, 60), (0, 10), (20, 30)]),
+            vec![(0, 10), (20, 30), (50, 60)]
+        );
+        assert!(merge_intervals(vec![]).is_empty());
+    }
+
+    #[test]
+    fn wallclock_total_counts_overlaps_once() {
+        let store = store(vec![
+            session("a", 0, Some(100), &[]),
+            session("b", 50, Some(150), &[]),
+            session("c", 200, Some(300), &[]),
+        ]);
+        let report = store.report(&Filter::default(), GroupBy::None);
+        assert_eq!(report.total, 300);
+        assert_eq!(report.wallclock_total, 250);
+    }
+
+    fn subtotals(report: &Report) -> Vec<(&str, u64, u32)> {
+        report
+            .subtotals
+            .iter()
+            .map(|x| (x.key.as_str(), x.duration, x.count))
+            .collect()
+    }
+
+    fn overlapping_labels() -> Store {
+        store(vec![
+            session("a", 0, Some(3600), &["x", "y"]),
+            session("b", 4000, Some(5800), &["y", "z"]),
+            session("c", 6000, Some(6600), &["x"]),
+            session("d", 7000, Some(7100), &["x", "y", "z"]),
+        ])
+    }
+
+    #[test]
+    fn label_subtotals_count_a_session_for_each_label() {
+        let report = overlapping_labels().report(&Filter::default(), GroupBy::Label);
+        assert_eq!(
+            subtotals(&report),
+            vec![("x", 4300, 3), ("y", 5500, 3), ("z", 1900, 2)]
+        );
+        assert_eq!(report.total, 6100);
+    }
+
+    #[test]
+    fn split_label_subtotals_add_up_to_the_total() {
+        let report = overlapping_labels().report(&Filter::default(), GroupBy::LabelSplit);
+        // d's 100 seconds don't divide by 3, so x gets the extra second.
+        assert_eq!(
+            subtotals(&report),
+            vec![
+                ("x", 1800 + 600 + 34, 3),
+                ("y", 1800 + 900 + 33, 3),
+                ("z", 900 + 33, 2)
+            ]
+        );
+        let sum: u64 = report.subtotals.iter().map(|x| x.duration).sum();
+        assert_eq!(sum, report.total);
+    }
+
+    #[test]
+    fn split_evenly_hands_out_the_remainder_in_order() {
+        let labels = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        let shares: Vec<u64> = split_evenly(&labels, 5).into_iter().map(|x| x.1).collect();
+        assert_eq!(shares, vec![2, 2, 1]);
+        assert!(split_evenly(&[], 5).is_empty());
+    }
+
+    fn local(day: u32, hour: u32, minute: u32) -> i64 {
+        LocalTZ
+            .with_ymd_and_hms(2024, 3, day, hour, minute, 0)
+            .earliest()
+            .unwrap()
+            .timestamp()
+    }
+
+    #[test]
+    fn split_at_midnights_cuts_every_day() {
+        assert_eq!(
+            split_at_midnights(local(11, 22, 0), local(12, 1, 30)),
+            vec![
+                (local(11, 22, 0), local(12, 0, 0)),
+                (local(12, 0, 0), local(12, 1, 30))
+            ]
+        );
+        assert_eq!(
+            split_at_midnights(local(11, 22, 0), local(13, 0, 0)).len(),
+            2
+        );
+        assert_eq!(
+            split_at_midnights(local(11, 9, 0), local(11, 17, 0)),
+            vec![(local(11, 9, 0), local(11, 17, 0))]
+        );
+    }
+
+    #[test]
+    fn day_subtotals_split_a_session_over_midnight() {
+        let store = store(vec![
+            session("late", local(11, 22, 0), Some(local(12, 1, 30)), &[]),
+            session("morning", local(12, 9, 0), Some(local(12, 10, 0)), &[]),
+        ]);
+        let filter = Filter {
+            split_days: true,
+            ..Default::default()
+        };
+        let report = store.report(&filter, GroupBy::Day);
+        assert_eq!(
+            subtotals(&report),
+            vec![("2024-03-11", 7200, 1), ("2024-03-12", 5400 + 3600, 2)]
+        );
+        assert_eq!(report.total, 7200 + 5400 + 3600);
+        let rows: Vec<(i64, Option<i64>, u64)> = report
+            .rows
+            .iter()
+            .map(|x| (x.start_at, x.end_at, x.duration))
+            .collect();
+        assert_eq!(rows[0], (local(11, 22, 0), Some(local(12, 0, 0)), 7200));
+        assert_eq!(rows[1], (local(12, 0, 0), Some(local(12, 1, 30)), 5400));
+
+        // Without splitting, the session counts on the day it started.
+        let report = store.report(&Filter::default(), GroupBy::Day);
+        assert_eq!(
+            subtotals(&report),
+            vec![("2024-03-11", 12600, 1), ("2024-03-12", 3600, 1)]
+        );
+    }
+
+    #[test]
+    fn breaks_stay_on_their_side_of_midnight() {
+        let mut late = session("late", local(11, 23, 0), Some(local(12, 2, 0)), &[]);
+        late.breaks = vec![(local(11, 23, 30), Some(local(12, 0, 30)))];
+        let filter = Filter {
+            split_days: true,
+            ..Default::default()
+        };
+        let report = store(vec![late]).report(&filter, GroupBy::Day);
+        assert_eq!(
+            subtotals(&report),
+            vec![("2024-03-11", 1800, 1), ("2024-03-12", 5400, 1)]
+        );
+    }
+
+    #[test]
+    fn total_above_u32_max_minutes() {
+        // Three sessions of 2^31 minutes, so the minutes of the total don't fit in a u32.
+        let length: i64 = (1 << 31) * 60;
+        let store = store(
+            (0..3)
+                .map(|i| session(&i.to_string(), i * length, Some((i + 1) * length), &[]))
+                .collect(),
+        );
+        let report = store.report(&Filter::default(), GroupBy::Day);
+        assert_eq!(report.total, 3 * (1 << 31) * 60);
+        assert!(report.total / 60 > u64::from(u32::MAX));
+        assert_eq!(report.wallclock_total, report.total);
+        assert_eq!(
+            report.subtotals.iter().map(|x| x.duration).sum::<u64>(),
+            report.total
+        );
+    }
+}
@@ -0,0 +1,277 @@
+//! A JSON HTTP API over the store, for web UIs and scripts.
+//!
+//! Requests are handled one at a time, and every one of them opens the store anew and saves it
+//! like a command does, so the CLI can be used alongside and the hooks, the webhooks and undo
+//! work as usual. With WTT_SERVE_TOKEN set, the requests which change anything must carry it
+//! as a bearer token.
+
+use tiny_http::{Header, Method, Request, Response, Server};
+use wtt::{
+    config,
+    report::GroupBy,
+    store::{Filter, LabelMatch, NoteMode, Session, sorted_labels},
+};
+
+use crate::{handle, handle::StoreHandle, try_get_from_timestamp, try_get_to_timestamp};
+
+/// An error answer: the status code and the message.
+type Failure = (u16, String);
+
+pub fn run(listen: &str) -> wtt::Result<()> {
+    let server = Server::http(listen).map_err(|x| format!("Could not listen on {listen}. {x}"))?;
+    let token = std::env::var("WTT_SERVE_TOKEN")
+        .ok()
+        .filter(|x| !x.is_empty());
+    // The address the server got, which differs from `listen` with port 0.
+    println!("Listening on http://{}", server.server_addr());
+    if token.is_none() {
+        println!(
+            "WTT_SERVE_TOKEN is not set, so anyone who can reach the server can change the sessions."
+        );
+    }
+
+    for mut request in server.incoming_requests() {
+        let (status, body) = match handle_request(&mut request, token.as_deref()) {
+            Ok(x) => (200, x),
+            Err((status, message)) => (status, serde_json::json!({ "error": message })),
+        };
+        log::info!("{} {} -> {status}", request.method(), request.url());
+        let response = Response::from_string(body.to_string())
+            .with_status_code(status)
+            .with_header(Header::from_bytes("Content-Type", "application/json").unwrap());
+        if let Err(x) = request.respond(response) {
+            log::warn!("Could not answer a request. {x}");
+        }
+    }
+    Ok(())
+}
+
+fn handle_request(
+    request: &mut Request,
+    token: Option<&str>,
+) -> Result<serde_json::Value, Failure> {
+    let url = request.url().to_string();
+    let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+    // Decoded, so a session can be given by its number as %2312.
+    let segments: Vec<String> = path
+        .trim_matches('/')
+        .split('/')
+        .map(percent_decode)
+        .collect();
+    let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+    let method = request.method().clone();
+    if !matches!(method, Method::Get) {
+        authorize(request, token)?;
+    }
+
+    match (method, segments.as_slice()) {
+        (Method::Get, ["sessions"]) => list_sessions(query),
+        (Method::Get, ["labels"]) => {
+            let store = handle::load().map_err(internal)?;
+            Ok(serde_json::json!(sorted_labels(store.get_all_labels())))
+        }
+        (Method::Get, ["status"]) => status(),
+        (Method::Post, ["sessions", "start"]) => start(&read_body(request)?),
+        (Method::Post, ["sessions", id, "end"]) => end(id, &read_body(request)?),
+        (Method::Patch, ["sessions", id, "note"]) => update_note(id, &read_body(request)?),
+        _ => Err((404, format!("There is no {} {path}.", request.method()))),
+    }
+}
+
+fn authorize(request: &Request, token: Option<&str>) -> Result<(), Failure> {
+    let Some(token) = token else {
+        return Ok(());
+    };
+    let given = request
+        .headers()
+        .iter()
+        .find(|x| x.field.equiv("Authorization"))
+        .map(|x| x.value.as_str());
+    if given == Some(format!("Bearer {token}").as_str()) {
+        return Ok(());
+    }
+    Err((
+        401,
+        "A bearer token is required to change the sessions.".to_string(),
+    ))
+}
+
+/// The sessions as `session table --format json` prints them. The parameters are the filters
+/// of the table: from, to, labels and exclude_labels (repeated or comma-separated), match_all,
+/// label_prefix, search, running (true for the running sessions, false for the ended ones) and
+/// last (how many of the sessions started last).
+fn list_sessions(query: &str) -> Result<serde_json::Value, Failure> {
+    let mut filter = Filter::default();
+    for (key, value) in parse_query(query) {
+        match key.as_str() {
+            "from" => filter.from = Some(try_get_from_timestamp(&value).map_err(bad_request)?),
+            "to" => filter.to = Some(try_get_to_timestamp(&value).map_err(bad_request)?),
+            "labels" => filter
+                .labels
+                .extend(value.split(',').filter(|x| !x.is_empty()).map(String::from)),
+            "exclude_labels" => filter
+                .exclude_labels
+                .extend(value.split(',').filter(|x| !x.is_empty()).map(String::from)),
+            "label_prefix" => filter.label_prefixes.push(value),
+            "search" => filter.search = Some(value),
+            "match_all" if value == "true" => filter.label_match = LabelMatch::All,
+            "match_all" => filter.label_match = LabelMatch::Any,
+            "running" => filter.running = Some(value == "true"),
+            "last" => match value.parse() {
+                Ok(x) if x > 0 => filter.last = Some(x),
+                _ => return Err(bad_request(format!("'{value}' is not a positive number."))),
+            },
+            "clamp_to_range" => filter.clamp_to_range = value == "true",
+            _ => return Err(bad_request(format!("Unknown parameter '{key}'."))),
+        }
+    }
+    let store = handle::load().map_err(internal)?;
+    Ok(serde_json::json!(store.report(&filter, GroupBy::None)))
+}
+
+/// The running session which was started last, like `wtt status`.
+fn status() -> Result<serde_json::Value, Failure> {
+    let store = handle::load().map_err(internal)?;
+    let report = store.report(&Filter::default(), GroupBy::None);
+    let running: Vec<_> = report.rows.iter().filter(|x| x.end_at.is_none()).collect();
+    Ok(serde_json::json!({
+        "running": running.len(),
+        "session": running.iter().max_by_key(|x| x.start_at),
+    }))
+}
+
+/// Start a session with the labels, kind and rate of the body, all optional.
+fn start(body: &serde_json::Value) -> Result<serde_json::Value, Failure> {
+    let mut labels: Vec<String> = match &body["labels"] {
+        serde_json::Value::Null => vec![],
+        value => serde_json::from_value(value.clone())
+            .map_err(|_| bad_request("labels must be a list of strings."))?,
+    };
+    if labels.is_empty() {
+        labels = config::get().default_labels.value.clone();
+    }
+    let kind = optional_string(body, "kind")?;
+    let rate = match &body["rate"] {
+        serde_json::Value::Null => None,
+        value => Some(
+            value
+                .as_f64()
+                .ok_or_else(|| bad_request("rate must be a number."))?,
+        ),
+    };
+
+    let mut store = StoreHandle::open().map_err(internal)?;
+    let session = store
+        .start_session(labels, rate, kind, None)
+        .map_err(conflict)?
+        .clone();
+    store.save_as("serve: start a session").map_err(conflict)?;
+    Ok(serde_json::json!(session))
+}
+
+/// End a running session, with the note of the body if there is one.
+fn end(id: &str, body: &serde_json::Value) -> Result<serde_json::Value, Failure> {
+    let mut store = StoreHandle::open().map_err(internal)?;
+    let id = store.resolve_id(id).map_err(not_found)?;
+    let note = match optional_string(body, "note")? {
+        Some(x) => Some(x),
+        None if config::get().require_note.value => {
+            return Err(bad_request("A note is required to end a session."));
+        }
+        None => store
+            .get_session_by_id(&id)
+            .map_err(not_found)?
+            .note
+            .clone(),
+    };
+    let session = store
+        .end_session(Some(&id), note, None, None, None)
+        .map_err(conflict)?
+        .clone();
+    store.save_as("serve: end a session").map_err(conflict)?;
+    Ok(serde_json::json!(session))
+}
+
+fn update_note(id: &str, body: &serde_json::Value) -> Result<serde_json::Value, Failure> {
+    let note = optional_string(body, "note")?.ok_or_else(|| bad_request("note is required."))?;
+    let mut store = StoreHandle::open().map_err(internal)?;
+    let id = store.resolve_id(id).map_err(not_found)?;
+    store
+        .update_note(&id, note, NoteMode::Replace, false)
+        .map_err(conflict)?;
+    let session: Session = store.get_session_by_id(&id).map_err(not_found)?.clone();
+    store.save_as("serve: update a note").map_err(conflict)?;
+    Ok(serde_json::json!(session))
+}
+
+fn read_body(request: &mut Request) -> Result<serde_json::Value, Failure> {
+    let mut text = String::new();
+    request
+        .as_reader()
+        .read_to_string(&mut text)
+        .map_err(|x| bad_request(format!("Could not read the body. {x}")))?;
+    if text.trim().is_empty() {
+        return Ok(serde_json::Value::Null);
+    }
+    serde_json::from_str(&text).map_err(|x| bad_request(format!("The body is not JSON. {x}")))
+}
+
+fn optional_string(body: &serde_json::Value, key: &str) -> Result<Option<String>, Failure> {
+    match &body[key] {
+        serde_json::Value::Null => Ok(None),
+        serde_json::Value::String(x) => Ok(Some(x.clone())),
+        _ => Err(bad_request(format!("{key} must be a string."))),
+    }
+}
+
+fn parse_query(query: &str) -> Vec<(String, String)> {
+    query
+        .split('&')
+        .filter(|x| !x.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(key), percent_decode(value))
+        })
+        .collect()
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' => match value
+                .get(i + 1..i + 3)
+                .and_then(|x| u8::from_str_radix(x, 16).ok())
+            {
+                Some(x) => {
+                    decoded.push(x);
+                    i += 2;
+                }
+                None => decoded.push(b'%'),
+            },
+            x => decoded.push(x),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+fn bad_request(message: impl ToString) -> Failure {
+    (400, message.to_string())
+}
+
+fn not_found(message: impl ToString) -> Failure {
+    (404, message.to_string())
+}
+
+/// The store refused the change, e.g. the session is ended already or locked.
+fn conflict(message: impl ToString) -> Failure {
+    (409, message.to_string())
+}
+
+fn internal(message: impl ToString) -> Failure {
+    (500, message.to_string())
+}
@@ -0,0 +1,239 @@
+//! Showing the running session in the Slack status.
+//!
+//! The status found before the first session is stashed in a file next to the database,
+//! together with the id of the session that replaced it. Ending that session puts the stashed
+//! status back, and starting another one (a switch) takes its place while the stash stays.
+//! Slack is reached after the session is saved, so all of its failures are only warnings.
+
+use std::{
+    fs,
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use chrono::{Local as LocalTZ, TimeZone};
+use serde::{Deserialize, Serialize};
+use wtt::{
+    Result, config,
+    report::ReportRow,
+    store::{Session, get_path_to_store_file, write_file_atomically},
+};
+
+use crate::{events::Event, template::Template};
+
+const API: &str = "https://slack.com/api";
+/// How long all of the requests of a command may take together.
+const TIMEOUT: Duration = Duration::from_secs(5);
+/// Slack cuts the status text at this many characters.
+const TEXT_LIMIT: usize = 100;
+/// The fields of the status text on top of those of `--template`: the first label ("work" if
+/// there is none) and when the session is expected to end, after `slack.eta_minutes`.
+const EXTRA_FIELDS: &[&str] = &["label", "eta"];
+
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+static TEMPLATE: Mutex<Option<Template>> = Mutex::new(None);
+
+/// Set the status for the sessions started by this command, even if `slack.enabled` is off.
+/// The template, if given, replaces `slack.template`. It is checked now, so a typo fails the
+/// command instead of being a warning after the session was saved.
+pub fn request(template: Option<String>) -> Result<()> {
+    let template = template.unwrap_or_else(|| config::get().slack_template.value.clone());
+    *TEMPLATE.lock().unwrap() = Some(Template::parse_with(&template, EXTRA_FIELDS)?);
+    REQUESTED.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+#[derive(Serialize, Deserialize)]
+struct Stash {
+    /// The session shown in the status now.
+    session_id: String,
+    status_text: String,
+    status_emoji: String,
+    status_expiration: i64,
+}
+
+/// Update the status for the events: show the last started session, or restore the stashed
+/// status when the session shown in it has ended.
+pub fn update(events: &[(Event, Session)]) {
+    let stash = read_stash();
+    let started = events
+        .iter()
+        .rev()
+        .find(|(event, _)| matches!(event, Event::Start | Event::Resume))
+        .map(|(_, session)| session)
+        .filter(|_| REQUESTED.load(Ordering::Relaxed) || config::get().slack_enabled.value);
+    let ended = stash.as_ref().is_some_and(|stash| {
+        events.iter().any(|(event, session)| {
+            matches!(event, Event::End | Event::Cancel) && session.id == stash.session_id
+        })
+    });
+    if started.is_none() && !ended {
+        return;
+    }
+    let Ok(token) = std::env::var("WTT_SLACK_TOKEN") else {
+        eprintln!("Warning: set WTT_SLACK_TOKEN to update the Slack status.");
+        return;
+    };
+    let slack = Slack::new(token);
+
+    let outcome = match (started, stash) {
+        (Some(session), stash) => slack.show(session, stash),
+        (None, Some(stash)) => slack.restore(stash),
+        (None, None) => Ok(()),
+    };
+    if let Err(x) = outcome {
+        eprintln!("Warning: could not update the Slack status. {x}");
+    }
+}
+
+struct Slack {
+    agent: ureq::Agent,
+    api: String,
+    auth: String,
+}
+
+impl Slack {
+    fn new(token: String) -> Self {
+        Self {
+            agent: ureq::Agent::config_builder()
+                .timeout_global(Some(TIMEOUT))
+                .build()
+                .into(),
+            api: std::env::var("WTT_SLACK_API_URL").unwrap_or_else(|_| API.to_string()),
+            auth: format!("Bearer {token}"),
+        }
+    }
+
+    /// Show the session in the status, stashing the current status unless one is stashed already.
+    fn show(&self, session: &Session, stash: Option<Stash>) -> Result<()> {
+        let mut stash = match stash {
+            Some(x) => x,
+            None => {
+                let profile = self.call_get("users.profile.get")?["profile"].clone();
+                Stash {
+                    session_id: String::new(),
+                    status_text: profile["status_text"].as_str().unwrap_or("").to_string(),
+                    status_emoji: profile["status_emoji"].as_str().unwrap_or("").to_string(),
+                    status_expiration: profile["status_expiration"].as_i64().unwrap_or(0),
+                }
+            }
+        };
+        // Stashed first, so the status can be restored even if the command stops halfway.
+        stash.session_id = session.id.clone();
+        write_stash(&stash)?;
+        self.set(&status_text(session)?, &config::get().slack_emoji.value, 0)?;
+        log::debug!("Set the Slack status for the session {}.", session.id);
+        Ok(())
+    }
+
+    fn restore(&self, stash: Stash) -> Result<()> {
+        self.set(
+            &stash.status_text,
+            &stash.status_emoji,
+            stash.status_expiration,
+        )?;
+        fs::remove_file(get_path_to_stash_file())
+            .map_err(|x| format!("Could not remove the stashed status. {x}"))?;
+        log::debug!("Restored the Slack status.");
+        Ok(())
+    }
+
+    fn set(&self, text: &str, emoji: &str, expiration: i64) -> Result<()> {
+        let body = serde_json::json!({
+            "profile": {
+                "status_text": text,
+                "status_emoji": emoji,
+                "status_expiration": expiration,
+            }
+        });
+        let mut response = self
+            .agent
+            .post(format!("{}/users.profile.set", self.api))
+            .header("Authorization", &self.auth)
+            .header("Content-Type", "application/json; charset=utf-8")
+            .send(body.to_string())?;
+        check(response.body_mut().read_to_string()?)?;
+        Ok(())
+    }
+
+    fn call_get(&self, method: &str) -> Result<serde_json::Value> {
+        let mut response = self
+            .agent
+            .get(format!("{}/{method}", self.api))
+            .header("Authorization", &self.auth)
+            .call()?;
+        check(response.body_mut().read_to_string()?)
+    }
+}
+
+/// Slack answers 200 to failed calls too, with `ok` false and the reason in `error`.
+fn check(text: String) -> Result<serde_json::Value> {
+    let answer: serde_json::Value = serde_json::from_str(&text)?;
+    if answer["ok"].as_bool() != Some(true) {
+        let error = answer["error"].as_str().unwrap_or("unknown error");
+        return Err(format!("Slack answered: {error}.").into());
+    }
+    Ok(answer)
+}
+
+fn status_text(session: &Session) -> Result<String> {
+    let requested = TEMPLATE.lock().unwrap().take();
+    let template = match requested {
+        Some(x) => x,
+        None => Template::parse_with(&config::get().slack_template.value, EXTRA_FIELDS)?,
+    };
+    let now = LocalTZ::now().timestamp();
+    let eta = session.start_at + i64::from(config::get().slack_eta_minutes.value) * 60;
+    let text = template.render_with(
+        &ReportRow::running(session, now),
+        &[
+            (
+                "label",
+                session
+                    .labels
+                    .first()
+                    .cloned()
+                    .unwrap_or("work".to_string()),
+            ),
+            (
+                "eta",
+                LocalTZ
+                    .timestamp_opt(eta, 0)
+                    .unwrap()
+                    .format("%H:%M")
+                    .to_string(),
+            ),
+        ],
+    );
+    // The status is one line.
+    Ok(text
+        .replace(['\n', '\r'], " ")
+        .chars()
+        .take(TEXT_LIMIT)
+        .collect())
+}
+
+fn get_path_to_stash_file() -> String {
+    format!("{}.slack.json", get_path_to_store_file())
+}
+
+fn read_stash() -> Option<Stash> {
+    let content = fs::read_to_string(get_path_to_stash_file()).ok()?;
+    match serde_json::from_str(&content) {
+        Ok(x) => Some(x),
+        Err(x) => {
+            eprintln!("Warning: the stashed Slack status is broken and is ignored. {x}");
+            None
+        }
+    }
+}
+
+fn write_stash(stash: &Stash) -> Result<()> {
+    write_file_atomically(
+        &get_path_to_stash_file(),
+        &serde_json::to_string(stash).unwrap(),
+    )
+}
@@ -0,0 +1,95 @@
+//! A one-line status for status bars (waybar, polybar, i3blocks) and the tmux status line.
+//!
+//! Bars run the command every few seconds, so it only reads the store and renders one session.
+//! It prints nothing to stderr and exits with 0 whether a session is running or not,
+//! because bars treat anything else as a broken module. If the store can't be read quickly
+//! (e.g. it's on a slow network drive), a fallback is printed instead of holding the bar up.
+
+use std::{sync::mpsc, thread, time::Duration};
+
+use chrono::Local as LocalTZ;
+use wtt::{Result, report::ReportRow, store::Store};
+
+use crate::{
+    datetime_format, format_timestamp,
+    output::{self, Format},
+    template::Template,
+};
+
+pub const DEFAULT_TEXT: &str = "{labels} · {duration:short}";
+
+/// How long the store may take to load before the fallback is printed.
+const TIME_BUDGET: Duration = Duration::from_millis(500);
+
+/// Colors of the tmux segment, in tmux's syntax (e.g. green, colour244, #00ff00).
+pub struct TmuxColors {
+    pub running: String,
+    pub idle: String,
+}
+
+pub fn run(text: &Template, idle_text: &str, colors: &TmuxColors) {
+    let (text, tooltip, class) = match load_newest_running() {
+        Ok(Some(row)) => {
+            let mut tooltip = format!(
+                "#{} started at {}",
+                row.seq,
+                format_timestamp(row.start_at, datetime_format())
+            );
+            if let Some(note) = row.note.as_deref() {
+                tooltip.push('\n');
+                tooltip.push_str(note);
+            }
+            (text.render(&row), tooltip, "running")
+        }
+        Ok(None) => (
+            idle_text.to_string(),
+            "No running session.".to_string(),
+            "idle",
+        ),
+        Err(x) => ("wtt: error".to_string(), x.to_string(), "error"),
+    };
+    // A bar shows one line, so a note or a label must not break it.
+    let text = text.replace(['\n', '\r'], " ");
+
+    match output::format() {
+        // Waybar reads one JSON object per line.
+        Format::Waybar => println!(
+            "{}",
+            serde_json::json!({
+                "text": text,
+                "tooltip": tooltip,
+                "class": class,
+                "alt": class,
+            })
+        ),
+        Format::Tmux => {
+            // "#" starts a tmux format, so the text must not contain a bare one.
+            let text = text.replace('#', "##");
+            match class {
+                "running" => print!("#[fg={}]●#[default] {text}", colors.running),
+                _ => print!("#[fg={}]{text}#[default]", colors.idle),
+            }
+        }
+        _ => println!("{text}"),
+    }
+}
+
+/// Load the store in the background and give up after `TIME_BUDGET`.
+fn load_newest_running() -> Result<Option<ReportRow>> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let row = Store::from_store_file()
+            .map(|x| newest_running(&x))
+            .map_err(|x| x.to_string());
+        let _ = sender.send(row);
+    });
+    match receiver.recv_timeout(TIME_BUDGET) {
+        Ok(x) => x.map_err(|x| x.into()),
+        Err(_) => Err("The database took too long to load.".into()),
+    }
+}
+
+fn newest_running(store: &Store) -> Option<ReportRow> {
+    let session = store.newest_running_session()?;
+    Some(ReportRow::running(session, LocalTZ::now().timestamp()))
+}
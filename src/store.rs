@@ -0,0 +1,1644 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs,
+    time::Instant,
+};
+
+use chrono::{Local as LocalTZ, TimeZone};
+use uuid::Uuid;
+
+use crate::{Result, backend, config, msg, suggest, today::Today};
+
+/// The shortest id prefix accepted in place of a full session id.
+pub const MIN_ID_PREFIX_LENGTH: usize = 4;
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Default, Clone)]
+pub struct Store {
+    pub sessions: Vec<Session>,
+    /// The highest sequence number ever given to a session, so numbers are not reused after deletes.
+    #[serde(default)]
+    pub last_seq: u64,
+    /// Fields written by other versions of the app. They are kept as is to survive a round-trip.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Store {
+    pub fn from_store_file() -> Result<Self> {
+        let started = Instant::now();
+        let store = backend::current().load()?;
+        log::debug!(
+            "Loaded {} sessions from {} in {:?}.",
+            store.sessions.len(),
+            get_path_to_store_file(),
+            started.elapsed()
+        );
+        Ok(store)
+    }
+
+    pub(crate) fn from_json_str(content: &str, path: &str) -> Result<Self> {
+        // An empty file is usually left by a crashed save or created by a sync tool.
+        // There is nothing to lose here, so start with an empty store instead of failing.
+        if content.trim().is_empty() {
+            eprintln!("Warning: the database file {path} is empty. Starting with an empty store.");
+            return Ok(Self::default());
+        }
+
+        let mut store: Store = serde_json::from_str(content)
+            .map_err(|x| format!("Could not parse the database file as JSON data. {x}"))?;
+        store.assign_missing_seqs();
+        Ok(store)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let started = Instant::now();
+        backend::current().save(self)?;
+        log::debug!(
+            "Saved {} sessions to {} in {:?}.",
+            self.sessions.len(),
+            get_path_to_store_file(),
+            started.elapsed()
+        );
+        if config::get().today_file.value {
+            Today::update(self)?;
+        }
+        Ok(())
+    }
+
+    /// Give sequence numbers to the sessions created before they existed, in the order of start.
+    pub(crate) fn assign_missing_seqs(&mut self) {
+        let mut indexes: Vec<usize> = (0..self.sessions.len())
+            .filter(|x| self.sessions[*x].seq == 0)
+            .collect();
+        indexes.sort_by_key(|x| self.sessions[*x].start_at);
+        if !indexes.is_empty() {
+            log::debug!(
+                "Numbering {} sessions which have no number yet.",
+                indexes.len()
+            );
+        }
+        for index in indexes {
+            self.sessions[index].seq = self.next_seq();
+        }
+    }
+
+    /// Take the next sequence number for a new session.
+    pub fn next_seq(&mut self) -> u64 {
+        let max_seq = self.sessions.iter().map(|x| x.seq).max().unwrap_or(0);
+        self.last_seq = self.last_seq.max(max_seq) + 1;
+        self.last_seq
+    }
+
+    pub fn get_all_sessions(&self, filter: &Filter) -> Vec<&Session> {
+        let mut sessions: Vec<&Session> = self.sessions.iter().collect();
+        log::debug!("Filtering {} sessions.", sessions.len());
+
+        // Clamped sessions only have to overlap the range, their part outside of it is cut off.
+        if let Some(from) = filter.from
+            && filter.clamp_to_range
+        {
+            sessions.retain(|x| x.end_at.is_none_or(|end_at| end_at > from));
+            log::debug!(
+                "{} sessions ended after {from} or are running.",
+                sessions.len()
+            );
+        } else if let Some(from) = filter.from {
+            sessions.retain(|x| x.start_at >= from);
+            log::debug!("{} sessions started at {from} or later.", sessions.len());
+        }
+
+        if let Some(to) = filter.to
+            && filter.clamp_to_range
+        {
+            sessions.retain(|x| x.start_at <= to);
+            log::debug!("{} sessions started at {to} or earlier.", sessions.len());
+        } else if let Some(to) = filter.to {
+            // A running session has no end yet, so it's kept when it started within the range.
+            sessions.retain(|x| match x.end_at {
+                Some(end_at) => end_at <= to,
+                None => x.start_at <= to,
+            });
+            log::debug!(
+                "{} sessions ended at {to} or earlier, or are running since then.",
+                sessions.len()
+            );
+        }
+
+        if !filter.labels.is_empty() {
+            match filter.label_match {
+                LabelMatch::Any => {
+                    let labelset: HashSet<&str> =
+                        filter.labels.iter().map(|x| x.as_str()).collect();
+                    sessions.retain(|x| x.labels.iter().any(|x| labelset.contains(x.as_str())));
+                    log::debug!(
+                        "{} sessions have one of the labels {:?}.",
+                        sessions.len(),
+                        filter.labels
+                    );
+                }
+                LabelMatch::All => {
+                    sessions.retain(|x| filter.labels.iter().all(|label| x.labels.contains(label)));
+                    log::debug!(
+                        "{} sessions have all of the labels {:?}.",
+                        sessions.len(),
+                        filter.labels
+                    );
+                }
+            }
+        }
+
+        if !filter.label_prefixes.is_empty() {
+            sessions.retain(|x| {
+                x.labels.iter().any(|label| {
+                    filter
+                        .label_prefixes
+                        .iter()
+                        .any(|prefix| label_has_prefix(label, prefix))
+                })
+            });
+            log::debug!(
+                "{} sessions have a label under one of the prefixes {:?}.",
+                sessions.len(),
+                filter.label_prefixes
+            );
+        }
+
+        if !filter.exclude_labels.is_empty() {
+            sessions.retain(|x| !x.labels.iter().any(|x| filter.exclude_labels.contains(x)));
+            log::debug!(
+                "{} sessions have none of the excluded labels {:?}.",
+                sessions.len(),
+                filter.exclude_labels
+            );
+        }
+
+        if let Some(running) = filter.running {
+            sessions.retain(|x| x.end_at.is_none() == running);
+            log::debug!("{} sessions are running: {running}.", sessions.len());
+        }
+
+        if let Some(search) = &filter.search {
+            let search = search.to_lowercase();
+            sessions.retain(|x| {
+                x.note
+                    .as_ref()
+                    .is_some_and(|note| note.to_lowercase().contains(&search))
+            });
+            log::debug!("{} sessions have {search:?} in the note.", sessions.len());
+        }
+
+        sessions.sort_by_key(|x| x.start_at);
+
+        if let Some(last) = filter.last {
+            let skipped = sessions.len().saturating_sub(last as usize);
+            sessions.drain(..skipped);
+            log::debug!("Kept the {} sessions started last.", sessions.len());
+        }
+
+        sessions
+    }
+
+    /// Start a session now, or at another time given by `at`.
+    pub fn start_session(
+        &mut self,
+        labels: Vec<String>,
+        rate: Option<f64>,
+        kind: Option<String>,
+        at: Option<i64>,
+    ) -> Result<&Session> {
+        if let Some(kind) = kind.as_deref() {
+            check_kind(kind)?;
+        }
+        let now = LocalTZ::now().timestamp();
+        if at.is_some_and(|x| x > now) {
+            return Err(msg!("session.starts_in_future").into());
+        }
+        let id = Uuid::new_v4();
+        let session = Session {
+            id: id.to_string(),
+            seq: self.next_seq(),
+            start_at: at.unwrap_or(now),
+            end_at: None,
+            note: None,
+            labels,
+            rate,
+            kind,
+            locked: false,
+            git: None,
+            external_ids: BTreeMap::new(),
+            breaks: vec![],
+            extra: serde_json::Map::new(),
+        };
+        self.sessions.push(session);
+        Ok(self.sessions.last().unwrap())
+    }
+
+    /// Add a session which has already ended, e.g. an imported one.
+    pub fn add_session(
+        &mut self,
+        start_at: i64,
+        end_at: i64,
+        labels: Vec<String>,
+        note: Option<String>,
+        rate: Option<f64>,
+        kind: Option<String>,
+    ) -> Result<&Session> {
+        if end_at < start_at {
+            return Err(msg!("session.would_end_before_start").into());
+        }
+        if end_at > LocalTZ::now().timestamp() {
+            return Err(msg!("session.ends_in_future").into());
+        }
+        if let Some(kind) = kind.as_deref() {
+            check_kind(kind)?;
+        }
+        let session = Session {
+            id: Uuid::new_v4().to_string(),
+            seq: self.next_seq(),
+            start_at,
+            end_at: Some(end_at),
+            note,
+            labels,
+            rate,
+            kind,
+            locked: false,
+            git: None,
+            external_ids: BTreeMap::new(),
+            breaks: vec![],
+            extra: serde_json::Map::new(),
+        };
+        self.sessions.push(session);
+        Ok(self.sessions.last().unwrap())
+    }
+
+    /// End a session now, or at another time given by `at`. The note replaces the one the session
+    /// has, and without a note the session keeps its own.
+    pub fn end_session(
+        &mut self,
+        id: Option<&str>,
+        note: Option<String>,
+        round_start: Option<u32>,
+        round_end: Option<u32>,
+        at: Option<i64>,
+    ) -> Result<&Session> {
+        let session: &mut Session = match id {
+            Some(session_id) => {
+                let session = self.get_session_by_id(session_id)?;
+                if session.end_at.is_some() {
+                    return Err(msg!("session.already_ended", id = session.id).into());
+                }
+                session
+            }
+            None => self.get_newest_running_session()?,
+        };
+
+        let now = at.unwrap_or(LocalTZ::now().timestamp());
+        let start_at = match round_start {
+            Some(minutes) => round_timestamp(session.start_at, minutes, false),
+            None => session.start_at,
+        };
+        let end_at = match round_end {
+            Some(minutes) => round_timestamp(now, minutes, true),
+            None => now,
+        };
+        if end_at < start_at && (round_start.is_some() || round_end.is_some()) {
+            return Err(msg!(
+                "session.would_end_before_start_after_rounding",
+                id = session.id
+            )
+            .into());
+        }
+        if end_at < start_at {
+            return Err(msg!("session.would_end_before_start").into());
+        }
+
+        session.start_at = start_at;
+        session.end_at = Some(end_at);
+        if let Some(note) = note {
+            session.note = Some(note);
+        }
+        if let Some(last) = session.breaks.last_mut()
+            && last.1.is_none()
+        {
+            last.1 = Some(end_at.max(last.0));
+        }
+
+        Ok(session)
+    }
+
+    /// Replace the note of a session, or add the text to its end, see `NoteMode`.
+    pub fn update_note(
+        &mut self,
+        id: &str,
+        note: String,
+        mode: NoteMode,
+        force: bool,
+    ) -> Result<()> {
+        let session = self.get_session_by_id(id)?;
+        session.ensure_unlocked(force)?;
+        session.note = Some(match (mode, session.note.take()) {
+            (NoteMode::Append { separator }, Some(old)) if !old.is_empty() => {
+                format!("{old}{separator}{note}")
+            }
+            _ => note,
+        });
+        Ok(())
+    }
+
+    /// Start a break in a running session, the newest one when no id is given.
+    pub fn pause_session(&mut self, id: Option<&str>) -> Result<&Session> {
+        let now = LocalTZ::now().timestamp();
+        let session = self.get_running_session(id)?;
+        if session.is_paused() {
+            return Err(msg!("session.already_paused", id = session.id).into());
+        }
+        session.breaks.push((now, None));
+        Ok(session)
+    }
+
+    /// End the break of a paused session, the newest running one when no id is given.
+    pub fn unpause_session(&mut self, id: Option<&str>) -> Result<&Session> {
+        let now = LocalTZ::now().timestamp();
+        let session = self.get_running_session(id)?;
+        match session.breaks.last_mut() {
+            Some(last) if last.1.is_none() => last.1 = Some(now.max(last.0)),
+            _ => return Err(msg!("session.not_paused", id = session.id).into()),
+        }
+        Ok(session)
+    }
+
+    /// Record a break which is over in a running session, like a span the user was away.
+    /// It's kept in order before an open break, so a paused session stays paused.
+    pub fn add_break(&mut self, id: &str, start: i64, end: i64) -> Result<&Session> {
+        let now = LocalTZ::now().timestamp();
+        let session = self.get_running_session(Some(id))?;
+        if start < session.start_at || end < start || end > now {
+            return Err(msg!("session.break_outside", id = session.id).into());
+        }
+        let overlaps = session.breaks.iter().any(|(break_start, break_end)| {
+            *break_start < end && break_end.is_none_or(|x| x > start)
+        });
+        if overlaps {
+            return Err(msg!("session.break_overlaps", id = session.id).into());
+        }
+        let index = session
+            .breaks
+            .iter()
+            .position(|(break_start, _)| *break_start > start)
+            .unwrap_or(session.breaks.len());
+        session.breaks.insert(index, (start, Some(end)));
+        Ok(session)
+    }
+
+    fn get_running_session(&mut self, id: Option<&str>) -> Result<&mut Session> {
+        match id {
+            Some(x) => {
+                let session = self.get_session_by_id(x)?;
+                if session.end_at.is_some() {
+                    return Err(msg!("session.already_ended", id = session.id).into());
+                }
+                Ok(session)
+            }
+            None => self.get_newest_running_session(),
+        }
+    }
+
+    /// Split a session in two at the timestamp, which must lie inside it. The first part keeps
+    /// the id, and the second one is a new session with the same labels and note which is
+    /// still running if the session was. Returns the ids of both parts.
+    pub fn split_session(&mut self, id: &str, at: i64, force: bool) -> Result<(String, String)> {
+        let now = LocalTZ::now().timestamp();
+        let seq = self.next_seq();
+        let session = self.get_session_by_id(id)?;
+        session.ensure_unlocked(force)?;
+        if at <= session.start_at || at >= session.end_at.unwrap_or(now) {
+            return Err(msg!("session.split_outside", id = session.id).into());
+        }
+
+        let mut second = Session {
+            id: Uuid::new_v4().to_string(),
+            seq,
+            start_at: at,
+            end_at: session.end_at,
+            note: session.note.clone(),
+            labels: session.labels.clone(),
+            rate: session.rate,
+            kind: session.kind.clone(),
+            locked: false,
+            git: session.git.clone(),
+            external_ids: BTreeMap::new(),
+            breaks: vec![],
+            extra: session.extra.clone(),
+        };
+        // A break across the split point is cut in two as well.
+        for (start, end) in std::mem::take(&mut session.breaks) {
+            if end.is_some_and(|x| x <= at) {
+                session.breaks.push((start, end));
+            } else if start >= at {
+                second.breaks.push((start, end));
+            } else {
+                session.breaks.push((start, Some(at)));
+                second.breaks.push((at, end));
+            }
+        }
+        session.end_at = Some(at);
+
+        let first_id = session.id.clone();
+        let second_id = second.id.clone();
+        self.sessions.push(second);
+        Ok((first_id, second_id))
+    }
+
+    /// Merge ended sessions into the one which started first: it spans from the earliest start
+    /// to the latest end, gets the labels of all of them and their notes one per line, and the
+    /// others are deleted. A gap longer than `max_gap` seconds between them is refused unless
+    /// `force` is given, which also allows merging locked sessions.
+    pub fn merge_sessions(&mut self, ids: &[&str], max_gap: i64, force: bool) -> Result<&Session> {
+        let mut full_ids: Vec<String> = vec![];
+        for id in ids {
+            let full_id = self.resolve_id(id)?;
+            if !full_ids.contains(&full_id) {
+                full_ids.push(full_id);
+            }
+        }
+        if full_ids.len() < 2 {
+            return Err(msg!("session.merge_needs_two").into());
+        }
+        let mut sessions: Vec<&Session> = self
+            .sessions
+            .iter()
+            .filter(|x| full_ids.contains(&x.id))
+            .collect();
+        sessions.sort_by_key(|x| x.start_at);
+        for session in &sessions {
+            if session.end_at.is_none() {
+                return Err(msg!("session.running_cant_merge", id = session.id).into());
+            }
+            session.ensure_unlocked(force)?;
+        }
+        let mut end_at = sessions[0].end_at.unwrap();
+        for session in &sessions[1..] {
+            let gap = session.start_at - end_at;
+            if gap > max_gap && !force {
+                return Err(msg!("session.merge_gap", minutes = gap / 60, id = session.id).into());
+            }
+            end_at = end_at.max(session.end_at.unwrap());
+        }
+
+        let mut labels: Vec<String> = vec![];
+        let mut notes: Vec<String> = vec![];
+        let mut breaks: Vec<(i64, Option<i64>)> = vec![];
+        for session in &sessions {
+            for label in &session.labels {
+                if !labels.contains(label) {
+                    labels.push(label.clone());
+                }
+            }
+            if let Some(note) = session.note.as_deref().filter(|x| !x.trim().is_empty()) {
+                notes.push(note.to_string());
+            }
+            breaks.extend(session.breaks.iter().copied());
+        }
+        let survivor_id = sessions[0].id.clone();
+        self.sessions
+            .retain(|x| x.id == survivor_id || !full_ids.contains(&x.id));
+
+        let survivor = self.get_session_by_id(&survivor_id)?;
+        survivor.end_at = Some(end_at);
+        survivor.labels = labels;
+        survivor.note = (!notes.is_empty()).then(|| notes.join("\n"));
+        survivor.breaks = breaks;
+        Ok(survivor)
+    }
+
+    /// Move the start and the end of a session. Giving the end of a running session ends it.
+    /// The times follow the rules of adding and ending a session, and the breaks must stay
+    /// within them.
+    pub fn edit_session(
+        &mut self,
+        id: &str,
+        start_at: Option<i64>,
+        end_at: Option<i64>,
+        force: bool,
+    ) -> Result<&Session> {
+        let now = LocalTZ::now().timestamp();
+        let session = self.get_session_by_id(id)?;
+        session.ensure_unlocked(force)?;
+        let start_at = start_at.unwrap_or(session.start_at);
+        let end_at = end_at.or(session.end_at);
+        if start_at > now {
+            return Err(msg!("session.starts_in_future").into());
+        }
+        if end_at.is_some_and(|x| x > now) {
+            return Err(msg!("session.ends_in_future").into());
+        }
+        if end_at.is_some_and(|x| x < start_at) {
+            return Err(msg!("session.would_end_before_start").into());
+        }
+        // An open break ends with the session, so only its start has to fit.
+        let breaks_outside = session.breaks.iter().any(|(break_start, break_end)| {
+            *break_start < start_at
+                || end_at.is_some_and(|end| break_end.unwrap_or(*break_start) > end)
+        });
+        if breaks_outside {
+            return Err(msg!("session.breaks_outside", id = session.id).into());
+        }
+
+        session.start_at = start_at;
+        session.end_at = end_at;
+        if let Some(end_at) = end_at
+            && let Some(last) = session.breaks.last_mut()
+            && last.1.is_none()
+        {
+            last.1 = Some(end_at);
+        }
+        Ok(session)
+    }
+
+    /// Lock or unlock the ended sessions which were started before the timestamp.
+    /// Returns how many sessions changed their state.
+    pub fn set_locked_before(&mut self, before: i64, locked: bool) -> u32 {
+        let mut count: u32 = 0;
+        for session in &mut self.sessions {
+            if session.end_at.is_some() && session.start_at < before && session.locked != locked {
+                session.locked = locked;
+                count += 1;
+            }
+        }
+        count
+    }
+
+    /// Lock or unlock one session. Only ended sessions can be locked.
+    pub fn set_locked_by_id(&mut self, id: &str, locked: bool) -> Result<()> {
+        let session = self.get_session_by_id(id)?;
+        if locked && session.end_at.is_none() {
+            return Err(msg!("session.running_cant_lock", id = session.id).into());
+        }
+        session.locked = locked;
+        Ok(())
+    }
+
+    /// Get a session by its id or by a unique prefix of the id.
+    pub fn get_session_by_id(&mut self, id: &str) -> Result<&mut Session> {
+        let full_id = self.resolve_id(id)?;
+        Ok(self.sessions.iter_mut().find(|x| x.id == full_id).unwrap())
+    }
+
+    /// Find a session by its id, its number or a unique prefix of the id, without changing it.
+    pub fn find_session(&self, id: &str) -> Result<&Session> {
+        let full_id = self.resolve_id(id)?;
+        Ok(self.sessions.iter().find(|x| x.id == full_id).unwrap())
+    }
+
+    /// Find the full id of a session by its id, its sequence number ("#12") or by a unique prefix
+    /// of the id, which must be at least `MIN_ID_PREFIX_LENGTH` characters long.
+    pub fn resolve_id(&self, id: &str) -> Result<String> {
+        log::trace!("Resolving the session id {id}.");
+        if let Some(seq) = id.strip_prefix('#') {
+            let seq: u64 = seq
+                .parse()
+                .map_err(|_| msg!("session.invalid_number", id = id))?;
+            return match self.sessions.iter().find(|x| x.seq == seq) {
+                Some(x) => Ok(x.id.clone()),
+                None => Err(msg!("session.not_found", id = id).into()),
+            };
+        }
+        if self.sessions.iter().any(|x| x.id == id) {
+            return Ok(id.to_string());
+        }
+        if id.chars().count() < MIN_ID_PREFIX_LENGTH {
+            return Err(msg!(
+                "session.prefix_too_short",
+                id = id,
+                length = MIN_ID_PREFIX_LENGTH
+            )
+            .into());
+        }
+
+        let candidates: Vec<&str> = self
+            .sessions
+            .iter()
+            .filter(|x| x.id.starts_with(id))
+            .map(|x| x.id.as_str())
+            .collect();
+        match candidates.as_slice() {
+            [] => Err(msg!("session.not_found", id = id).into()),
+            [x] => Ok(x.to_string()),
+            _ => Err(msg!(
+                "session.ambiguous",
+                id = id,
+                candidates = candidates.join("\n")
+            )
+            .into()),
+        }
+    }
+
+    /// The running session which was started last.
+    pub fn newest_running_session(&self) -> Option<&Session> {
+        self.sessions
+            .iter()
+            .filter(|x| x.end_at.is_none())
+            .max_by_key(|x| x.start_at)
+    }
+
+    pub fn get_newest_running_session(&mut self) -> Result<&mut Session> {
+        let id = self
+            .newest_running_session()
+            .ok_or_else(|| msg!("session.none_running"))?
+            .id
+            .clone();
+        Ok(self.sessions.iter_mut().find(|x| x.id == id).unwrap())
+    }
+
+    /// The pairs of sessions which run at the same time, earlier started first. Running sessions
+    /// end at `now`, and sessions which only touch (one ends when the other starts) don't overlap.
+    pub fn overlaps(&self, now: i64) -> Vec<(&Session, &Session)> {
+        let mut sessions: Vec<&Session> = self.sessions.iter().collect();
+        sessions.sort_by_key(|x| x.start_at);
+        let mut pairs: Vec<(&Session, &Session)> = vec![];
+        for (i, first) in sessions.iter().enumerate() {
+            let end_at = first.end_at.unwrap_or(now);
+            for second in sessions[i + 1..].iter().take_while(|x| x.start_at < end_at) {
+                if second.end_at.unwrap_or(now) > second.start_at {
+                    pairs.push((first, second));
+                }
+            }
+        }
+        pairs
+    }
+
+    /// Find the inconsistencies which the commands would trip over.
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems: Vec<String> = vec![];
+        let mut ids: HashSet<&str> = HashSet::new();
+        let mut seqs: HashSet<u64> = HashSet::new();
+        for session in self.sessions.iter() {
+            if !ids.insert(&session.id) {
+                problems.push(msg!("session.duplicate_id", id = session.id));
+            }
+            if !seqs.insert(session.seq) {
+                problems.push(msg!("session.duplicate_number", seq = session.seq));
+            }
+            if session.end_at.is_some_and(|x| x < session.start_at) {
+                problems.push(msg!("session.ends_before_start", id = session.id));
+            }
+        }
+        problems
+    }
+
+    /// Get the running session when there is exactly one, for the commands which don't need an id then.
+    pub fn get_single_running_session(&mut self) -> Result<&mut Session> {
+        let mut running: Vec<&mut Session> = self
+            .sessions
+            .iter_mut()
+            .filter(|x| x.end_at.is_none())
+            .collect();
+        match running.len() {
+            0 => Err(msg!("session.none_running").into()),
+            1 => Ok(running.pop().unwrap()),
+            count => Err(msg!("session.several_running", count = count).into()),
+        }
+    }
+
+    /// Get the ended sessions which were started before the timestamp,
+    /// optionally only those which have the label.
+    pub fn get_old_sessions(&self, before: i64, label: Option<&str>) -> Vec<&Session> {
+        self.sessions
+            .iter()
+            .filter(|x| x.is_older_than(before, label))
+            .collect()
+    }
+
+    /// Delete the sessions returned by `get_old_sessions`. Returns how many were deleted.
+    pub fn delete_old_sessions(
+        &mut self,
+        before: i64,
+        label: Option<&str>,
+        force: bool,
+    ) -> Result<u32> {
+        for session in self.get_old_sessions(before, label) {
+            session.ensure_unlocked(force)?;
+        }
+        let count_before = self.sessions.len();
+        self.sessions.retain(|x| !x.is_older_than(before, label));
+        Ok(u32::try_from(count_before - self.sessions.len()).unwrap())
+    }
+
+    /// Reopen an ended session, the one which ended last when no id is given, so it runs again.
+    /// With `new` a fresh session with its labels, rate and kind is started instead.
+    pub fn resume_session(&mut self, id: Option<&str>, new: bool, force: bool) -> Result<&Session> {
+        let full_id = match id {
+            Some(x) => self.resolve_id(x)?,
+            None => self
+                .sessions
+                .iter()
+                .filter(|x| x.end_at.is_some())
+                .max_by_key(|x| x.end_at)
+                .map(|x| x.id.clone())
+                .ok_or_else(|| msg!("session.none_ended"))?,
+        };
+        let index = self.sessions.iter().position(|x| x.id == full_id).unwrap();
+        let session = &self.sessions[index];
+        if session.end_at.is_none() {
+            return Err(msg!("session.already_running", id = session.id).into());
+        }
+        let labels = sorted_labels(session.labels.iter().map(String::as_str));
+        if let Some(running) = self.sessions.iter().find(|x| {
+            x.end_at.is_none() && sorted_labels(x.labels.iter().map(String::as_str)) == labels
+        }) {
+            return Err(msg!("session.same_labels_running", id = running.id).into());
+        }
+
+        if new {
+            let (labels, rate, kind) = (session.labels.clone(), session.rate, session.kind.clone());
+            return self.start_session(labels, rate, kind, None);
+        }
+        session.ensure_unlocked(force)?;
+        let session = &mut self.sessions[index];
+        session.end_at = None;
+        Ok(session)
+    }
+
+    /// Throw away a running session, the newest one when no id is given, and return it.
+    pub fn cancel_session(&mut self, id: Option<&str>) -> Result<Session> {
+        let full_id = match id {
+            Some(x) => {
+                let session = self.get_session_by_id(x)?;
+                if session.end_at.is_some() {
+                    return Err(msg!("session.already_ended", id = session.id).into());
+                }
+                session.id.clone()
+            }
+            None => self.get_newest_running_session()?.id.clone(),
+        };
+        let index = self.sessions.iter().position(|x| x.id == full_id).unwrap();
+        Ok(self.sessions.remove(index))
+    }
+
+    /// Delete one session and return it. A running or locked session is deleted only with `force`.
+    pub fn delete_session(&mut self, id: &str, force: bool) -> Result<Session> {
+        let full_id = self.resolve_id(id)?;
+        let index = self.sessions.iter().position(|x| x.id == full_id).unwrap();
+        let session = &self.sessions[index];
+        if session.end_at.is_none() && !force {
+            return Err(msg!("session.running_cant_delete", id = session.id).into());
+        }
+        session.ensure_unlocked(force)?;
+        Ok(self.sessions.remove(index))
+    }
+
+    pub fn get_all_labels(&self) -> HashSet<&str> {
+        self.sessions
+            .iter()
+            .flat_map(|x| &x.labels)
+            .map(|x| x.as_str())
+            .collect::<HashSet<&str>>()
+    }
+
+    /// Describe the labels which no session has, with the closest existing labels if there are any.
+    pub fn describe_unknown_labels(&self, labels: &[impl AsRef<str>]) -> Vec<String> {
+        let known = self.get_all_labels();
+        labels
+            .iter()
+            .map(|x| x.as_ref())
+            .filter(|x| !known.contains(x))
+            .map(|label| {
+                let similar = suggest::similar(label, known.iter().copied());
+                if similar.is_empty() {
+                    msg!("label.unknown", label = label)
+                } else {
+                    msg!(
+                        "label.unknown_did_you_mean",
+                        label = label,
+                        similar = similar.join(", ")
+                    )
+                }
+            })
+            .collect()
+    }
+
+    /// Get all labels ordered by the start of the newest session which has them, the newest first.
+    pub fn get_labels_by_recent_use(&self) -> Vec<&str> {
+        let mut last_used: HashMap<&str, i64> = HashMap::new();
+        for session in &self.sessions {
+            for label in &session.labels {
+                let value = last_used.entry(label.as_str()).or_insert(session.start_at);
+                *value = (*value).max(session.start_at);
+            }
+        }
+        let mut labels: Vec<(&str, i64)> = last_used.into_iter().collect();
+        labels.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        labels.into_iter().map(|x| x.0).collect()
+    }
+
+    pub fn count_sessions_with_any_label(&self, labels: &[&str]) -> usize {
+        self.sessions
+            .iter()
+            .filter(|x| {
+                x.labels
+                    .iter()
+                    .any(|label| labels.contains(&label.as_str()))
+            })
+            .count()
+    }
+
+    /// Add a label to one session. Returns false if the session has it already.
+    pub fn add_session_label(&mut self, id: &str, name: &str, force: bool) -> Result<bool> {
+        let session = self.get_session_by_id(id)?;
+        if session.labels.iter().any(|x| x == name) {
+            return Ok(false);
+        }
+        session.ensure_unlocked(force)?;
+        session.labels.push(name.to_string());
+        Ok(true)
+    }
+
+    /// Remove a label from one session, which must have it.
+    pub fn remove_session_label(&mut self, id: &str, name: &str, force: bool) -> Result<()> {
+        let session = self.get_session_by_id(id)?;
+        if !session.labels.iter().any(|x| x == name) {
+            return Err(msg!("session.label_not_on", id = session.id, label = name).into());
+        }
+        session.ensure_unlocked(force)?;
+        session.labels.retain(|x| x != name);
+        Ok(())
+    }
+
+    pub fn remove_label(&mut self, name: &str, force: bool) -> Result<u32> {
+        self.ensure_labels_unlocked(&[name], force)?;
+
+        let mut count: u32 = 0;
+        for session in &mut self.sessions {
+            let count_before = session.labels.len();
+            session.labels.retain(|x| *x != name);
+            count += u32::try_from(count_before - session.labels.len()).unwrap();
+        }
+        Ok(count)
+    }
+
+    pub fn merge_labels(
+        &mut self,
+        source: Vec<String>,
+        target: String,
+        force: bool,
+    ) -> Result<u32> {
+        let mut count: u32 = 0;
+        let sourceset: HashSet<&str> = source.iter().map(|x| x.as_str()).collect();
+        self.ensure_labels_unlocked(&sourceset.iter().copied().collect::<Vec<&str>>(), force)?;
+
+        for session in &mut self.sessions {
+            let count_before = session.labels.len();
+            session.labels.retain(|x| !sourceset.contains(x.as_str()));
+
+            let removed_count = u32::try_from(count_before - session.labels.len()).unwrap();
+            if (removed_count) > 0 {
+                count += removed_count;
+                session.labels.push(target.clone());
+            }
+        }
+
+        Ok(count)
+    }
+
+    /// Fail if any locked session has one of the labels, unless forced.
+    fn ensure_labels_unlocked(&self, labels: &[&str], force: bool) -> Result<()> {
+        for session in &self.sessions {
+            if session.labels.iter().any(|x| labels.contains(&x.as_str())) {
+                session.ensure_unlocked(force)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// How `Store::update_note` treats the note a session has already.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoteMode<'a> {
+    Replace,
+    /// Add the text after the separator. A session without a note simply gets the text.
+    Append {
+        separator: &'a str,
+    },
+}
+
+/// How the labels of a filter are matched.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LabelMatch {
+    /// A session needs at least one of the labels.
+    #[default]
+    Any,
+    /// A session needs every one of the labels.
+    All,
+}
+
+/// Which sessions to take into account.
+#[derive(Debug, Default, Clone)]
+pub struct Filter {
+    /// Keep the sessions which were started at this timestamp or later.
+    pub from: Option<i64>,
+    /// Keep the sessions which were ended at this timestamp or earlier.
+    pub to: Option<i64>,
+    /// Keep the sessions which have these labels, any or all of them by `label_match`.
+    pub labels: Vec<String>,
+    pub label_match: LabelMatch,
+    /// Keep the sessions which have at least one label under one of these prefixes.
+    pub label_prefixes: Vec<String>,
+    /// Drop the sessions which have any of these labels, even when they match the ones above.
+    pub exclude_labels: Vec<String>,
+    /// Keep the sessions whose note contains this text, ignoring the case.
+    pub search: Option<String>,
+    /// Keep only the running sessions (true) or only the ended ones (false).
+    pub running: Option<bool>,
+    /// Keep only this many sessions, the ones started last, after the other filters.
+    pub last: Option<u32>,
+    /// Count only the part of each session that falls inside the from / to range. The sessions
+    /// which only partly fall inside it are kept then.
+    pub clamp_to_range: bool,
+    /// Report a session which crosses midnight as one row per day. The sessions stay as they are.
+    pub split_days: bool,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+pub struct Session {
+    pub id: String,
+    /// A short number to refer to the session by, e.g. "#12". The id stays the identity of the session.
+    #[serde(default)]
+    pub seq: u64,
+    pub start_at: i64,
+    pub end_at: Option<i64>,
+    pub note: Option<String>,
+    pub labels: Vec<String>,
+    /// An hourly rate for billing. Overrides the rate given to the billing command.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate: Option<f64>,
+    /// The type of activity, one of the `behavior.kinds` setting. Unlike labels, there is only one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    /// Locked sessions belong to a finalized period (e.g. an invoiced month) and are not modified without --force.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub locked: bool,
+    /// The repository and branch the session was started in, with `session start --git`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git: Option<GitInfo>,
+    /// The ids of the session in other services, by service, e.g. "toggl", so it's pushed only once.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub external_ids: BTreeMap<String, String>,
+    /// The breaks taken during the session as (start, end). The last one is open while paused.
+    /// They don't count towards the duration.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub breaks: Vec<(i64, Option<i64>)>,
+    /// Fields written by other versions of the app. They are kept as is to survive a round-trip.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+pub struct GitInfo {
+    /// The name of the repository's directory.
+    pub repository: String,
+    /// The top level directory of the repository, to read its log when the session ends.
+    pub path: String,
+    pub branch: String,
+}
+
+impl Session {
+    pub fn is_paused(&self) -> bool {
+        self.breaks.last().is_some_and(|x| x.1.is_none())
+    }
+
+    /// The parts of [start_at, end_at) which are not covered by the breaks. An open break lasts
+    /// until `end_at`.
+    pub fn worked_intervals(&self, start_at: i64, end_at: i64) -> Vec<(i64, i64)> {
+        let mut intervals: Vec<(i64, i64)> = vec![];
+        let mut cursor = start_at;
+        let mut breaks: Vec<(i64, i64)> = self
+            .breaks
+            .iter()
+            .map(|(start, end)| (*start, end.unwrap_or(end_at)))
+            .collect();
+        breaks.sort_unstable();
+        for (break_start, break_end) in breaks {
+            if break_start > cursor {
+                intervals.push((cursor, break_start.min(end_at)));
+            }
+            cursor = cursor.max(break_end);
+            if cursor >= end_at {
+                break;
+            }
+        }
+        if cursor < end_at {
+            intervals.push((cursor, end_at));
+        }
+        intervals.retain(|(start, end)| start < end);
+        intervals
+    }
+
+    fn ensure_unlocked(&self, force: bool) -> Result<()> {
+        if self.locked && !force {
+            return Err(msg!("session.locked", id = self.id).into());
+        }
+        Ok(())
+    }
+
+    fn is_older_than(&self, before: i64, label: Option<&str>) -> bool {
+        if self.end_at.is_none() || self.start_at >= before {
+            return false;
+        }
+        match label {
+            Some(label) => self.labels.iter().any(|x| x == label),
+            None => true,
+        }
+    }
+}
+
+/// Deduplicate labels and sort them alphabetically, so sets of labels
+/// are always rendered in the same order.
+pub fn sorted_labels<'a>(labels: impl IntoIterator<Item = &'a str>) -> Vec<&'a str> {
+    let mut labels: Vec<&str> = labels.into_iter().collect();
+    labels.sort_unstable();
+    labels.dedup();
+    labels
+}
+
+/// Labels may form a hierarchy using "/" as a separator, e.g. "client/acme".
+/// A label is under a prefix if it equals the prefix or continues it with "/".
+pub fn label_has_prefix(label: &str, prefix: &str) -> bool {
+    match label.strip_prefix(prefix) {
+        Some(rest) => rest.is_empty() || rest.starts_with('/'),
+        None => false,
+    }
+}
+
+/// Check that the kind is one of the configured ones.
+pub fn check_kind(kind: &str) -> Result<()> {
+    let kinds = &config::get().kinds.value;
+    if !kinds.iter().any(|x| x == kind) {
+        return Err(msg!(
+            "session.unknown_kind",
+            kind = kind,
+            kinds = kinds.join(", ")
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// The file which holds the sessions. The SQLite database is kept next to the JSON file
+/// (db.json gives db.sqlite3), so switching the backend never overwrites the JSON data.
+pub fn get_path_to_store_file() -> String {
+    let path = &config::get().database_path.value;
+    match config::get().backend.value {
+        config::Backend::Json => path.clone(),
+        config::Backend::Sqlite => {
+            format!("{}.sqlite3", path.strip_suffix(".json").unwrap_or(path))
+        }
+    }
+}
+
+/// Write to a temporary file next to the target first, so readers never see a half-written file.
+pub fn write_file_atomically(path: &str, content: &str) -> Result<()> {
+    let temp_path = format!("{path}.tmp");
+    fs::write(&temp_path, content)
+        .map_err(|x| format!("Could not write the file {}. {}", &temp_path, x))?;
+    fs::rename(&temp_path, path).map_err(|x| format!("Could not replace the file {path}. {x}"))?;
+    Ok(())
+}
+
+/// Snap a timestamp to a grid of the given number of minutes in the local time zone,
+/// so e.g. a 15 minute grid gives :00, :15, :30 and :45 on the wall clock.
+fn round_timestamp(timestamp: i64, minutes: u32, up: bool) -> i64 {
+    let offset = i64::from(
+        LocalTZ
+            .timestamp_opt(timestamp, 0)
+            .unwrap()
+            .offset()
+            .local_minus_utc(),
+    );
+    let step = i64::from(minutes) * 60;
+    let local = timestamp + offset;
+    let mut rounded = local - local.rem_euclid(step);
+    if up && rounded < local {
+        rounded += step;
+    }
+    rounded - offset
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// An ended session, or a running one without `end_at`, for the tests of the other modules too.
+    pub(crate) fn session(
+        id: &str,
+        start_at: i64,
+        end_at: Option<i64>,
+        labels: &[&str],
+    ) -> Session {
+        Session {
+            id: id.to_string(),
+            seq: 0,
+            start_at,
+            end_at,
+            note: None,
+            labels: labels.iter().map(|x| x.to_string()).collect(),
+            rate: None,
+            kind: None,
+            locked: false,
+            git: None,
+            external_ids: BTreeMap::new(),
+            breaks: vec![],
+            extra: serde_json::Map::new(),
+        }
+    }
+
+    pub(crate) fn store(sessions: Vec<Session>) -> Store {
+        let mut store = Store {
+            sessions,
+            ..Default::default()
+        };
+        store.assign_missing_seqs();
+        store
+    }
+
+    #[test]
+    fn empty_file_is_an_empty_store() {
+        let store = Store::from_json_str("", "db.json").unwrap();
+        assert!(store.sessions.is_empty());
+    }
+
+    #[test]
+    fn whitespace_file_is_an_empty_store() {
+        let store = Store::from_json_str(" \n\t\r\n", "db.json").unwrap();
+        assert!(store.sessions.is_empty());
+    }
+
+    #[test]
+    fn malformed_file_is_an_error() {
+        assert!(Store::from_json_str("{\"sessions\": [", "db.json").is_err());
+        assert!(Store::from_json_str("not json", "db.json").is_err());
+    }
+
+    fn ids<'a>(sessions: &[&'a Session]) -> Vec<&'a str> {
+        sessions.iter().map(|x| x.id.as_str()).collect()
+    }
+
+    #[test]
+    fn label_prefix_matches_the_label_and_its_children() {
+        assert!(label_has_prefix("client", "client"));
+        assert!(label_has_prefix("client/acme", "client"));
+        assert!(label_has_prefix("client/acme/web", "client/acme"));
+        assert!(!label_has_prefix("clients", "client"));
+        assert!(!label_has_prefix("cli", "client"));
+        assert!(!label_has_prefix("acme/client", "client"));
+    }
+
+    #[test]
+    fn label_prefix_filter_differs_from_exact_labels() {
+        let store = store(vec![
+            session("a", 1, Some(2), &["client/acme"]),
+            session("b", 3, Some(4), &["client/beta", "meeting"]),
+            session("c", 5, Some(6), &["client"]),
+            session("d", 7, Some(8), &["clients"]),
+        ]);
+        let exact = Filter {
+            labels: vec!["client".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(ids(&store.get_all_sessions(&exact)), vec!["c"]);
+
+        let prefix = Filter {
+            label_prefixes: vec!["client".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(ids(&store.get_all_sessions(&prefix)), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn sorted_labels_are_alphabetical_without_duplicates() {
+        assert_eq!(
+            sorted_labels(["web", "acme", "Zeta", "web", "beta"]),
+            vec!["Zeta", "acme", "beta", "web"]
+        );
+        assert!(sorted_labels([]).is_empty());
+    }
+
+    #[test]
+    fn all_labels_are_sorted_the_same_whatever_the_session_order() {
+        let forward = store(vec![
+            session("a", 1, Some(2), &["web", "acme"]),
+            session("b", 3, Some(4), &["beta"]),
+        ]);
+        let backward = store(vec![
+            session("b", 3, Some(4), &["beta"]),
+            session("a", 1, Some(2), &["acme", "web"]),
+        ]);
+        assert_eq!(
+            sorted_labels(forward.get_all_labels()),
+            vec!["acme", "beta", "web"]
+        );
+        assert_eq!(
+            sorted_labels(forward.get_all_labels()),
+            sorted_labels(backward.get_all_labels())
+        );
+        // A session keeps the order the labels were given in.
+        assert_eq!(forward.sessions[0].labels, vec!["web", "acme"]);
+    }
+
+    #[test]
+    fn labels_used_at_the_same_time_are_ordered_by_name() {
+        let store = store(vec![
+            session("a", 10, Some(20), &["web", "acme"]),
+            session("b", 5, Some(6), &["old"]),
+        ]);
+        assert_eq!(store.get_labels_by_recent_use(), vec!["acme", "web", "old"]);
+    }
+
+    fn prefix_store() -> Store {
+        store(vec![
+            session("abcd1111-0000", 1, Some(2), &[]),
+            session("abcd2222-0000", 3, Some(4), &[]),
+            session("ef01", 5, Some(6), &[]),
+        ])
+    }
+
+    #[test]
+    fn unique_prefix_resolves_to_the_id() {
+        let store = prefix_store();
+        assert_eq!(store.resolve_id("abcd1").unwrap(), "abcd1111-0000");
+        assert_eq!(store.resolve_id("abcd2222-0000").unwrap(), "abcd2222-0000");
+    }
+
+    #[test]
+    fn ambiguous_prefix_lists_the_candidates() {
+        let error = prefix_store().resolve_id("abcd").unwrap_err().to_string();
+        assert!(error.contains("abcd1111-0000"), "{error}");
+        assert!(error.contains("abcd2222-0000"), "{error}");
+    }
+
+    #[test]
+    fn unknown_prefix_is_not_found() {
+        let error = prefix_store().resolve_id("ffff").unwrap_err().to_string();
+        assert_eq!(error, msg!("session.not_found", id = "ffff"));
+    }
+
+    #[test]
+    fn short_prefix_is_rejected() {
+        let store = prefix_store();
+        let error = store.resolve_id("abc").unwrap_err().to_string();
+        assert_eq!(
+            error,
+            msg!(
+                "session.prefix_too_short",
+                id = "abc",
+                length = MIN_ID_PREFIX_LENGTH
+            )
+        );
+        // A full id is fine however short it is.
+        assert_eq!(store.resolve_id("ef01").unwrap(), "ef01");
+    }
+
+    #[test]
+    fn number_resolves_to_the_id() {
+        let store = prefix_store();
+        assert_eq!(store.resolve_id("#2").unwrap(), "abcd2222-0000");
+        assert!(store.resolve_id("#9").is_err());
+        assert!(store.resolve_id("#x").is_err());
+    }
+
+    #[test]
+    fn running_sessions_are_kept_by_to_only_if_they_started_before() {
+        let store = store(vec![
+            session("before", 100, None, &[]),
+            session("at", 500, None, &[]),
+            session("after", 900, None, &[]),
+            session("ended", 100, Some(400), &[]),
+            session("ended_after", 100, Some(600), &[]),
+        ]);
+        let sorted_ids = |filter: &Filter| {
+            let mut ids = ids(&store.get_all_sessions(filter));
+            ids.sort();
+            ids
+        };
+        let filter = Filter {
+            to: Some(500),
+            ..Default::default()
+        };
+        assert_eq!(sorted_ids(&filter), vec!["at", "before", "ended"]);
+        // Clamped to the range, a session only has to start before its end.
+        let clamped = Filter {
+            clamp_to_range: true,
+            ..filter
+        };
+        assert_eq!(
+            sorted_ids(&clamped),
+            vec!["at", "before", "ended", "ended_after"]
+        );
+    }
+
+    fn edit_error(store: &mut Store, start_at: Option<i64>, end_at: Option<i64>) -> String {
+        store
+            .edit_session("a", start_at, end_at, false)
+            .unwrap_err()
+            .to_string()
+    }
+
+    #[test]
+    fn edit_only_the_start_keeps_the_end() {
+        let mut store = store(vec![session("a", 1000, Some(2000), &[])]);
+        let edited = store.edit_session("a", Some(1500), None, false).unwrap();
+        assert_eq!((edited.start_at, edited.end_at), (1500, Some(2000)));
+        // The kept end is checked against the new start.
+        assert_eq!(
+            edit_error(&mut store, Some(2500), None),
+            msg!("session.would_end_before_start")
+        );
+        assert_eq!(store.sessions[0].start_at, 1500);
+    }
+
+    #[test]
+    fn edit_only_the_end_keeps_the_start() {
+        let mut store = store(vec![session("a", 1000, Some(2000), &[])]);
+        let edited = store.edit_session("a", None, Some(3000), false).unwrap();
+        assert_eq!((edited.start_at, edited.end_at), (1000, Some(3000)));
+        assert_eq!(
+            edit_error(&mut store, None, Some(999)),
+            msg!("session.would_end_before_start")
+        );
+        // Ending where it started is allowed.
+        store.edit_session("a", None, Some(1000), false).unwrap();
+    }
+
+    #[test]
+    fn edit_rejects_an_end_before_the_start_and_future_times() {
+        let now = LocalTZ::now().timestamp();
+        let mut store = store(vec![session("a", 1000, Some(2000), &[])]);
+        assert_eq!(
+            edit_error(&mut store, Some(5000), Some(4000)),
+            msg!("session.would_end_before_start")
+        );
+        assert_eq!(
+            edit_error(&mut store, Some(now + 3600), None),
+            msg!("session.starts_in_future")
+        );
+        assert_eq!(
+            edit_error(&mut store, None, Some(now + 3600)),
+            msg!("session.ends_in_future")
+        );
+        assert_eq!(
+            (store.sessions[0].start_at, store.sessions[0].end_at),
+            (1000, Some(2000))
+        );
+    }
+
+    #[test]
+    fn edit_keeps_the_breaks_inside() {
+        let mut running = session("a", 1000, None, &[]);
+        running.breaks = vec![(1200, Some(1300)), (1500, None)];
+        let mut store = store(vec![running]);
+        let outside = msg!("session.breaks_outside", id = "a");
+        assert_eq!(edit_error(&mut store, Some(1250), None), outside);
+        assert_eq!(edit_error(&mut store, None, Some(1250)), outside);
+        // The open break ends with the session.
+        let edited = store.edit_session("a", None, Some(1600), false).unwrap();
+        assert_eq!(edited.breaks, vec![(1200, Some(1300)), (1500, Some(1600))]);
+    }
+
+    #[test]
+    fn edit_of_a_locked_session_needs_force() {
+        let mut locked = session("a", 1000, Some(2000), &[]);
+        locked.locked = true;
+        let mut store = store(vec![locked]);
+        assert_eq!(
+            edit_error(&mut store, Some(1500), None),
+            msg!("session.locked", id = "a")
+        );
+        store.edit_session("a", Some(1500), None, true).unwrap();
+    }
+
+    #[test]
+    fn end_before_the_start_is_an_error() {
+        let now = LocalTZ::now().timestamp();
+        let mut store = store(vec![session("a", now - 60, None, &[])]);
+        let error = store
+            .end_session(None, None, None, None, Some(now - 3600))
+            .unwrap_err();
+        assert_eq!(error.to_string(), msg!("session.would_end_before_start"));
+        let error = store
+            .end_session(Some("a"), None, Some(5), Some(5), Some(now - 3600))
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            msg!("session.would_end_before_start_after_rounding", id = "a")
+        );
+        // Still running, with no negative duration to show.
+        assert_eq!(store.sessions[0].end_at, None);
+        assert_eq!(store.sessions[0].start_at, now - 60);
+
+        let ended = store
+            .end_session(None, None, None, None, Some(now - 60))
+            .unwrap();
+        assert_eq!(ended.end_at, Some(now - 60));
+    }
+
+    fn overlapping_ids(store: &Store, now: i64) -> Vec<(&str, &str)> {
+        store
+            .overlaps(now)
+            .into_iter()
+            .map(|(first, second)| (first.id.as_str(), second.id.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn touching_sessions_dont_overlap() {
+        let store = store(vec![
+            session("b", 2000, Some(3000), &[]),
+            session("a", 1000, Some(2000), &[]),
+            session("c", 3000, None, &[]),
+        ]);
+        assert!(overlapping_ids(&store, 4000).is_empty());
+    }
+
+    #[test]
+    fn nested_sessions_overlap() {
+        let store = store(vec![
+            session("outer", 1000, Some(5000), &[]),
+            session("inner", 2000, Some(3000), &[]),
+            session("later", 4000, Some(6000), &[]),
+        ]);
+        assert_eq!(
+            overlapping_ids(&store, 7000),
+            vec![("outer", "inner"), ("outer", "later")]
+        );
+    }
+
+    #[test]
+    fn running_session_overlaps_until_now() {
+        let store = store(vec![
+            session("running", 1000, None, &[]),
+            session("ended", 2000, Some(3000), &[]),
+        ]);
+        assert_eq!(overlapping_ids(&store, 4000), vec![("running", "ended")]);
+        // Until now, a running session doesn't reach what starts after it.
+        assert!(overlapping_ids(&store, 1500).is_empty());
+    }
+
+    fn temp_file(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("wtt-store-{}-{name}", std::process::id()));
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn atomic_write_replaces_the_file() {
+        let path = temp_file("replace.json");
+        fs::write(&path, "old").unwrap();
+        write_file_atomically(&path, "new").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        assert!(!std::path::Path::new(&format!("{path}.tmp")).exists());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn failed_write_leaves_the_file_intact() {
+        let path = temp_file("intact.json");
+        let temp_path = format!("{path}.tmp");
+        fs::write(&path, "{\"sessions\": []}").unwrap();
+        // The temporary file can't be written when a directory is in its place.
+        fs::create_dir_all(&temp_path).unwrap();
+
+        let error = write_file_atomically(&path, "{\"sessions\"").unwrap_err();
+        assert!(error.to_string().contains(&temp_path), "{error}");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{\"sessions\": []}");
+
+        fs::remove_dir(temp_path).unwrap();
+        fs::remove_file(path).unwrap();
+    }
+
+    const APPEND: NoteMode = NoteMode::Append { separator: "\n" };
+
+    fn note_after(note: Option<&str>, text: &str, mode: NoteMode) -> Option<String> {
+        let mut first = session("a", 1000, Some(2000), &[]);
+        first.note = note.map(String::from);
+        let mut store = store(vec![first]);
+        store
+            .update_note("a", text.to_string(), mode, false)
+            .unwrap();
+        store.sessions[0].note.clone()
+    }
+
+    #[test]
+    fn replace_the_note() {
+        assert_eq!(
+            note_after(Some("old"), "new", NoteMode::Replace).as_deref(),
+            Some("new")
+        );
+        assert_eq!(
+            note_after(None, "new", NoteMode::Replace).as_deref(),
+            Some("new")
+        );
+    }
+
+    #[test]
+    fn append_to_the_note() {
+        assert_eq!(
+            note_after(Some("old"), "new", APPEND).as_deref(),
+            Some("old\nnew")
+        );
+        let mode = NoteMode::Append { separator: " | " };
+        assert_eq!(
+            note_after(Some("old"), "new", mode).as_deref(),
+            Some("old | new")
+        );
+    }
+
+    #[test]
+    fn append_to_a_missing_or_empty_note_creates_it() {
+        assert_eq!(note_after(None, "new", APPEND).as_deref(), Some("new"));
+        assert_eq!(note_after(Some(""), "new", APPEND).as_deref(), Some("new"));
+    }
+
+    #[test]
+    fn note_of_a_locked_session_needs_force() {
+        let mut locked = session("a", 1000, Some(2000), &[]);
+        locked.locked = true;
+        let mut store = store(vec![locked]);
+        let error = store
+            .update_note("a", "new".to_string(), APPEND, false)
+            .unwrap_err();
+        assert_eq!(error.to_string(), msg!("session.locked", id = "a"));
+        assert_eq!(store.sessions[0].note, None);
+        store
+            .update_note("a", "new".to_string(), APPEND, true)
+            .unwrap();
+        assert_eq!(store.sessions[0].note.as_deref(), Some("new"));
+    }
+
+    #[test]
+    fn ending_without_a_note_keeps_the_appended_one() {
+        let now = LocalTZ::now().timestamp();
+        let mut store = store(vec![session("a", now - 60, None, &[])]);
+        store
+            .update_note("a", "first thought".to_string(), APPEND, false)
+            .unwrap();
+        store
+            .update_note("a", "second".to_string(), APPEND, false)
+            .unwrap();
+        let ended = store.end_session(None, None, None, None, None).unwrap();
+        assert_eq!(ended.note.as_deref(), Some("first thought\nsecond"));
+    }
+
+    #[test]
+    fn ending_with_a_note_replaces_it() {
+        let now = LocalTZ::now().timestamp();
+        let mut running = session("a", now - 60, None, &[]);
+        running.note = Some("draft".to_string());
+        let mut store = store(vec![running]);
+        let ended = store
+            .end_session(None, Some("done".to_string()), None, None, None)
+            .unwrap();
+        assert_eq!(ended.note.as_deref(), Some("done"));
+    }
+
+    #[test]
+    fn break_is_added_in_order() {
+        let now = LocalTZ::now().timestamp();
+        let mut running = session("a", now - 1000, None, &[]);
+        running.breaks = vec![(now - 900, Some(now - 800)), (now - 100, None)];
+        let mut store = store(vec![running]);
+        let session = store.add_break("a", now - 600, now - 300).unwrap();
+        assert_eq!(
+            session.breaks,
+            vec![
+                (now - 900, Some(now - 800)),
+                (now - 600, Some(now - 300)),
+                (now - 100, None)
+            ]
+        );
+        assert!(session.is_paused());
+        assert_eq!(store.sessions.len(), 1);
+    }
+
+    #[test]
+    fn break_must_fit_the_session_and_the_other_breaks() {
+        let now = LocalTZ::now().timestamp();
+        let mut running = session("a", now - 1000, None, &[]);
+        running.breaks = vec![(now - 900, Some(now - 800)), (now - 100, None)];
+        let mut store = store(vec![running, session("b", 0, Some(100), &[])]);
+        let outside = msg!("session.break_outside", id = "a");
+        let overlaps = msg!("session.break_overlaps", id = "a");
+        let error = |store: &mut Store, start: i64, end: i64| {
+            store.add_break("a", start, end).unwrap_err().to_string()
+        };
+        assert_eq!(error(&mut store, now - 1100, now - 950), outside);
+        assert_eq!(error(&mut store, now - 500, now + 60), outside);
+        assert_eq!(error(&mut store, now - 500, now - 600), outside);
+        assert_eq!(error(&mut store, now - 850, now - 700), overlaps);
+        // The open break lasts until now.
+        assert_eq!(error(&mut store, now - 200, now - 50), overlaps);
+        // Touching is fine.
+        store.add_break("a", now - 800, now - 700).unwrap();
+        assert_eq!(
+            store.add_break("b", 10, 20).unwrap_err().to_string(),
+            msg!("session.already_ended", id = "b")
+        );
+    }
+}
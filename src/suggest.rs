@@ -0,0 +1,90 @@
+//! "Did you mean" suggestions for values which match nothing, e.g. a mistyped label.
+
+/// How many suggestions are shown at most.
+const MAX_SUGGESTIONS: usize = 3;
+
+/// The candidates close to the value by edit distance, the closest first.
+/// A candidate is close when at most a third of its characters (but at least one) differ.
+pub fn similar<'a>(value: &str, candidates: impl IntoIterator<Item = &'a str>) -> Vec<&'a str> {
+    let value = value.to_lowercase();
+    let mut scored: Vec<(usize, &str)> = candidates
+        .into_iter()
+        .filter_map(|candidate| {
+            let distance = edit_distance(&value, &candidate.to_lowercase());
+            let threshold = (value.chars().count().max(candidate.chars().count()) / 3).max(1);
+            (distance <= threshold).then_some((distance, candidate))
+        })
+        .collect();
+    scored.sort_unstable();
+    scored.dedup();
+    scored
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, x)| x)
+        .collect()
+}
+
+/// The Levenshtein distance: how many characters have to be inserted, removed or replaced.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current: Vec<usize> = vec![0; b.len() + 1];
+    for (i, a_char) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, b_char) in b.iter().enumerate() {
+            let replace = previous[j] + usize::from(a_char != *b_char);
+            current[j + 1] = replace.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edit_distances() {
+        assert_eq!(edit_distance("acme", "acme"), 0);
+        assert_eq!(edit_distance("acme", "acne"), 1);
+        assert_eq!(edit_distance("acme", "acm"), 1);
+        assert_eq!(edit_distance("acme", "xacme"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("café", "cafe"), 1);
+    }
+
+    #[test]
+    fn short_values_allow_one_typo() {
+        assert_eq!(similar("acne", ["acme"]), vec!["acme"]);
+        assert_eq!(similar("web", ["wb", "we", "wbe"]), vec!["wb", "we"]);
+        assert!(similar("acme", ["beta"]).is_empty());
+        assert!(similar("ab", ["xy"]).is_empty());
+    }
+
+    #[test]
+    fn long_values_allow_a_third_of_typos() {
+        // 11 characters allow 3 edits, not 4.
+        assert_eq!(similar("development", ["devlpmnt"]), vec!["devlpmnt"]);
+        assert!(similar("development", ["dvlpmnt"]).is_empty());
+    }
+
+    #[test]
+    fn several_matches_closest_first_and_at_most_three() {
+        let candidates = ["clients", "client", "cliente", "clint", "clientes", "other"];
+        assert_eq!(
+            similar("client", candidates),
+            vec!["client", "cliente", "clients"]
+        );
+        assert_eq!(
+            similar("clent", ["client", "clint"]),
+            vec!["client", "clint"]
+        );
+    }
+
+    #[test]
+    fn case_is_ignored_but_kept_in_the_suggestion() {
+        assert_eq!(similar("ACME", ["Acme", "beta"]), vec!["Acme"]);
+    }
+}
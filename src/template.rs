@@ -0,0 +1,225 @@
+//! Templates for printing every session on its own line, e.g. "{start:%H:%M} {duration:hm} {note}".
+//!
+//! A placeholder is a field name with an optional format spec after a colon.
+//! Write `{{` and `}}` for literal braces. The template is checked before anything is printed,
+//! so a typo in a placeholder is an error instead of a blank column.
+
+use chrono::{
+    Local as LocalTZ, TimeZone,
+    format::{Item, StrftimeItems},
+};
+use wtt::{Result, report::ReportRow};
+
+use crate::{datetime_format, format_duration};
+
+pub const FIELDS: &[&str] = &[
+    "id", "seq", "start", "end", "duration", "labels", "note", "rate", "kind",
+];
+
+enum Segment {
+    Text(String),
+    Field { name: String, spec: Option<String> },
+}
+
+pub struct Template {
+    segments: Vec<Segment>,
+}
+
+impl Template {
+    pub fn parse(template: &str) -> Result<Self> {
+        Self::parse_with(template, &[])
+    }
+
+    /// Parse a template which may also use the `extra` fields, e.g. the Slack status's {eta}.
+    /// Their values are given to `render_with`, and they take no format spec.
+    pub fn parse_with(template: &str, extra: &[&str]) -> Result<Self> {
+        let mut segments: Vec<Segment> = vec![];
+        let mut text = String::new();
+        let mut chars = template.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let mut placeholder = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(x) => placeholder.push(x),
+                            None => {
+                                return Err(format!(
+                                    "The placeholder {{{placeholder} in the template is not closed."
+                                )
+                                .into());
+                            }
+                        }
+                    }
+                    if !text.is_empty() {
+                        segments.push(Segment::Text(std::mem::take(&mut text)));
+                    }
+                    segments.push(parse_field(&placeholder, extra)?);
+                }
+                '}' => {
+                    return Err("Unmatched } in the template. Write }} for a literal brace.".into());
+                }
+                x => text.push(x),
+            }
+        }
+        if !text.is_empty() {
+            segments.push(Segment::Text(text));
+        }
+
+        Ok(Self { segments })
+    }
+
+    pub fn render(&self, row: &ReportRow) -> String {
+        self.render_with(row, &[])
+    }
+
+    /// Render with the values of the extra fields the template was parsed with.
+    pub fn render_with(&self, row: &ReportRow, extra: &[(&str, String)]) -> String {
+        let mut line = String::new();
+        for segment in self.segments.iter() {
+            match segment {
+                Segment::Text(x) => line.push_str(x),
+                Segment::Field { name, spec } => {
+                    match extra.iter().find(|(field, _)| field == name) {
+                        Some((_, value)) => line.push_str(value),
+                        None => line.push_str(&render_field(row, name, spec.as_deref())),
+                    }
+                }
+            }
+        }
+        line
+    }
+}
+
+fn parse_field(placeholder: &str, extra: &[&str]) -> Result<Segment> {
+    let (name, spec) = match placeholder.split_once(':') {
+        Some((name, spec)) => (name.trim(), Some(spec.to_string())),
+        None => (placeholder.trim(), None),
+    };
+    if !FIELDS.contains(&name) && !extra.contains(&name) {
+        return Err(format!(
+            "Unknown placeholder {{{name}}} in the template. Available: {}.",
+            FIELDS
+                .iter()
+                .chain(extra)
+                .copied()
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+        .into());
+    }
+
+    if let Some(spec) = spec.as_deref() {
+        let valid = match name {
+            "start" | "end" => !StrftimeItems::new(spec).any(|x| matches!(x, Item::Error)),
+            "duration" => ["hm", "short", "m", "s", "text"].contains(&spec),
+            "id" => spec.parse::<usize>().is_ok(),
+            "labels" => true,
+            _ => false,
+        };
+        if !valid {
+            return Err(
+                format!("Invalid format spec '{spec}' for {{{name}}} in the template.").into(),
+            );
+        }
+    }
+
+    Ok(Segment::Field {
+        name: name.to_string(),
+        spec,
+    })
+}
+
+/// Fields:
+/// - id, or its first N characters with {id:N}, and seq, the session number
+/// - start and end, with an optional chrono format, e.g. {start:%H:%M}
+/// - duration as text (default), hm ("1:05"), short ("1h 5m"), m (minutes) or s (seconds)
+/// - labels, joined with ", " or with the separator given as the spec
+/// - note, rate and kind
+fn render_field(row: &ReportRow, name: &str, spec: Option<&str>) -> String {
+    match name {
+        "id" => match spec {
+            Some(x) => row.id.chars().take(x.parse().unwrap()).collect(),
+            None => row.id.clone(),
+        },
+        "seq" => row.seq.to_string(),
+        "start" => format_timestamp(row.start_at, spec),
+        "end" => row
+            .end_at
+            .map(|x| format_timestamp(x, spec))
+            .unwrap_or_default(),
+        "duration" => match spec {
+            Some("hm") => format!("{}:{:02}", row.duration / 3600, row.duration / 60 % 60),
+            Some("short") if row.duration >= 3600 => {
+                format!("{}h {}m", row.duration / 3600, row.duration / 60 % 60)
+            }
+            Some("short") => format!("{}m", row.duration / 60),
+            Some("m") => (row.duration / 60).to_string(),
+            Some("s") => row.duration.to_string(),
+            _ => format_duration(row.duration, row.end_at.is_none(), " "),
+        },
+        "labels" => row.labels.join(spec.unwrap_or(", ")),
+        "note" => row.note.clone().unwrap_or_default(),
+        "rate" => row.rate.map(|x| x.to_string()).unwrap_or_default(),
+        "kind" => row.kind.clone().unwrap_or_default(),
+        _ => unreachable!("The field {name} was checked while parsing."),
+    }
+}
+
+fn format_timestamp(timestamp: i64, spec: Option<&str>) -> String {
+    LocalTZ
+        .timestamp_opt(timestamp, 0)
+        .unwrap()
+        .format(spec.unwrap_or(datetime_format()))
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row() -> ReportRow {
+        ReportRow {
+            id: "abcdef".to_string(),
+            seq: 3,
+            start_at: 0,
+            end_at: None,
+            labels: vec!["acme".to_string(), "web".to_string()],
+            note: None,
+            duration: 3900,
+            rate: None,
+            kind: None,
+        }
+    }
+
+    #[test]
+    fn extra_fields_are_filled_in_with_the_row() {
+        let template = Template::parse_with(
+            "{label}: {labels} {duration:hm} until {eta}",
+            &["label", "eta"],
+        )
+        .unwrap();
+        let line = template.render_with(
+            &row(),
+            &[("label", "acme".to_string()), ("eta", "12:30".to_string())],
+        );
+        assert_eq!(line, "acme: acme, web 1:05 until 12:30");
+    }
+
+    #[test]
+    fn extra_fields_are_unknown_without_parse_with() {
+        let error = Template::parse("{eta}").err().unwrap().to_string();
+        assert!(error.contains("Unknown placeholder {eta}"), "{error}");
+        assert!(Template::parse_with("{eta:%H}", &["eta"]).is_err());
+    }
+}
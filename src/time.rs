@@ -0,0 +1,298 @@
+//! Parsing the points in time given to flags like --at, and lengths of time.
+//!
+//! Besides a full date with time, people think in relative terms: "10m ago", "in 5 minutes",
+//! "14:30" for today, or just "now". Anything else, like a bare number, is an error with examples,
+//! because guessing whether "30" means minutes ago or half past is worse than asking.
+
+use chrono::{
+    DateTime, Datelike, Duration, Local as LocalTZ, NaiveDate, NaiveDateTime, NaiveTime, Weekday,
+};
+
+use crate::{Result, config};
+
+const EXAMPLES: &str = "Use e.g. now, 10m ago, 2h ago, in 5 minutes, 14:30 or a date with time";
+const DURATION_EXAMPLES: &str = "Use e.g. 50m, 10 minutes or 2h";
+/// The days `parse_relative_day` understands.
+pub const RELATIVE_DAYS: &str = "today, yesterday, week, month, -Nd or -Nw";
+/// The value name of the flags which take a day, like --from and --to.
+pub const DAY_VALUE_NAME: &str = "dd.mm.yyyy, today, yesterday, week, month, -Nd or -Nw";
+
+/// Parse a point in time relative to `now` into a timestamp.
+pub fn parse_time(value: &str, now: DateTime<LocalTZ>) -> Result<i64> {
+    let value = value.trim().to_lowercase();
+    if value == "now" {
+        return Ok(now.timestamp());
+    }
+    if let Some(amount) = value.strip_suffix(" ago") {
+        return now
+            .checked_sub_signed(parse_amount(amount, &value, EXAMPLES)?)
+            .map(|x| x.timestamp())
+            .ok_or_else(|| format!("'{value}' is too far in the past.").into());
+    }
+    if let Some(amount) = value.strip_prefix("in ") {
+        return now
+            .checked_add_signed(parse_amount(amount, &value, EXAMPLES)?)
+            .map(|x| x.timestamp())
+            .ok_or_else(|| format!("'{value}' is too far in the future.").into());
+    }
+    if value.contains(':')
+        && !value.contains(' ')
+        && let Ok(time) = NaiveTime::parse_from_str(&value, "%H:%M")
+    {
+        return local_timestamp(now.date_naive().and_time(time), &value);
+    }
+    let format = &config::get().datetime_format.value;
+    if let Ok(datetime) = NaiveDateTime::parse_from_str(&value, format) {
+        return local_timestamp(datetime, &value);
+    }
+    if value.parse::<f64>().is_ok() {
+        return Err(format!("'{value}' is ambiguous without a unit. {EXAMPLES}.").into());
+    }
+    Err(format!("'{value}' is not a point in time. {EXAMPLES} ({format}).").into())
+}
+
+/// The first day of the week `today` is in, for weeks starting on `first`.
+pub fn week_start(today: NaiveDate, first: Weekday) -> NaiveDate {
+    today - Duration::days(days_into_week(today, first))
+}
+
+fn days_into_week(today: NaiveDate, first: Weekday) -> i64 {
+    i64::from(today.weekday().days_since(first))
+}
+
+/// Parse a day relative to `today`: "today", "yesterday", "week" (the first day of this week,
+/// `behavior.week_start`),
+/// "month" (the first of this month), "-7d" (7 days ago) or "-2w" (2 weeks ago).
+/// Returns None for anything else, which is then likely a date.
+pub fn parse_relative_day(value: &str, today: NaiveDate) -> Result<Option<NaiveDate>> {
+    let value = value.trim().to_lowercase();
+    let days = match value.as_str() {
+        "today" => 0,
+        "yesterday" => 1,
+        "week" => days_into_week(today, config::get().week_start.value),
+        "month" => i64::from(today.day0()),
+        _ => {
+            let Some(amount) = value.strip_prefix('-') else {
+                return Ok(None);
+            };
+            let unit_length = amount.chars().last().map_or(0, char::len_utf8);
+            let (number, unit) = amount.split_at(amount.len() - unit_length);
+            let number: u32 = number
+                .parse()
+                .map_err(|_| format!("'{value}' is not a relative day. Use {RELATIVE_DAYS}."))?;
+            match unit {
+                "d" => i64::from(number),
+                "w" => i64::from(number) * 7,
+                _ => return Err(format!("Unknown unit in '{value}'. Use d or w.").into()),
+            }
+        }
+    };
+    Duration::try_days(days)
+        .and_then(|x| today.checked_sub_signed(x))
+        .map(Some)
+        .ok_or_else(|| format!("'{value}' is too far in the past.").into())
+}
+
+/// Parse a length of time like "50m" or "2 hours".
+pub fn parse_duration(value: &str) -> Result<Duration> {
+    let value = value.trim().to_lowercase();
+    parse_amount(&value, &value, DURATION_EXAMPLES)
+}
+
+/// Parse "10m", "10 min", "2h" or "2 hours".
+fn parse_amount(amount: &str, value: &str, examples: &str) -> Result<Duration> {
+    let amount = amount.trim();
+    let split = amount
+        .find(|x: char| !x.is_ascii_digit())
+        .unwrap_or(amount.len());
+    let (number, unit) = amount.split_at(split);
+    let number: i64 = number
+        .parse()
+        .map_err(|_| format!("'{value}' has no number. {examples}."))?;
+    let duration = match unit.trim() {
+        "m" | "min" | "mins" | "minute" | "minutes" => Duration::try_minutes(number),
+        "h" | "hr" | "hrs" | "hour" | "hours" => Duration::try_hours(number),
+        "" => return Err(format!("'{value}' is ambiguous without a unit. {examples}.").into()),
+        unit => return Err(format!("Unknown unit '{unit}' in '{value}'. Use m or h.").into()),
+    };
+    duration.ok_or_else(|| format!("'{value}' is too long.").into())
+}
+
+fn local_timestamp(datetime: NaiveDateTime, value: &str) -> Result<i64> {
+    datetime
+        .and_local_timezone(LocalTZ)
+        .earliest()
+        .map(|x| x.timestamp())
+        .ok_or_else(|| format!("'{value}' doesn't exist in the local timezone.").into())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn now() -> DateTime<LocalTZ> {
+        LocalTZ
+            .with_ymd_and_hms(2024, 3, 12, 15, 0, 0)
+            .single()
+            .unwrap()
+    }
+
+    fn parse(value: &str) -> Result<i64> {
+        parse_time(value, now())
+    }
+
+    #[test]
+    fn now_and_relative_times() {
+        let now = now().timestamp();
+        assert_eq!(parse("now").unwrap(), now);
+        assert_eq!(parse(" NOW ").unwrap(), now);
+        assert_eq!(parse("10m ago").unwrap(), now - 600);
+        assert_eq!(parse("10 minutes ago").unwrap(), now - 600);
+        assert_eq!(parse("2h ago").unwrap(), now - 7200);
+        assert_eq!(parse("1 hour ago").unwrap(), now - 3600);
+        assert_eq!(parse("in 5 minutes").unwrap(), now + 300);
+    }
+
+    #[test]
+    fn time_of_today() {
+        let expected = now().date_naive().and_hms_opt(14, 30, 0).unwrap();
+        assert_eq!(
+            parse("14:30").unwrap(),
+            expected.and_local_timezone(LocalTZ).unwrap().timestamp()
+        );
+        assert!(parse("25:00").is_err());
+    }
+
+    #[test]
+    fn date_with_time_in_the_configured_format() {
+        let at = now() - Duration::days(3);
+        let value = at.format(&config::get().datetime_format.value).to_string();
+        assert_eq!(parse(&value).unwrap(), at.timestamp() - at.timestamp() % 60);
+    }
+
+    #[test]
+    fn bare_number_is_ambiguous() {
+        for value in ["30", "1.5", "30 ago", "in 5"] {
+            let error = parse(value).unwrap_err().to_string();
+            assert!(error.contains("ambiguous"), "{value}: {error}");
+            assert!(error.contains("10m ago"), "{value}: {error}");
+        }
+    }
+
+    #[test]
+    fn unknown_values_are_errors_with_examples() {
+        for value in ["soon", "10 parsecs ago", "ago", ""] {
+            let error = parse(value).unwrap_err().to_string();
+            assert!(error.contains("Use"), "{value}: {error}");
+        }
+    }
+
+    #[test]
+    fn huge_amounts_are_errors_not_panics() {
+        assert!(parse("99999999999999999999m ago").is_err());
+        assert!(parse("9999999999999h ago").is_err());
+        assert!(parse("in 9223372036854775807 minutes").is_err());
+        assert!(parse_duration("9999999999999999h").is_err());
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("50m").unwrap(), Duration::minutes(50));
+        assert_eq!(parse_duration("2 hours").unwrap(), Duration::hours(2));
+        assert!(parse_duration("50").is_err());
+    }
+
+    fn relative_day(value: &str) -> Option<NaiveDate> {
+        parse_relative_day(value, NaiveDate::from_ymd_opt(2024, 3, 12).unwrap()).unwrap()
+    }
+
+    fn date(year: i32, month: u32, day: u32) -> Option<NaiveDate> {
+        NaiveDate::from_ymd_opt(year, month, day)
+    }
+
+    #[test]
+    fn relative_days_back_from_today() {
+        assert_eq!(relative_day("today"), date(2024, 3, 12));
+        assert_eq!(relative_day("yesterday"), date(2024, 3, 11));
+        assert_eq!(relative_day("-0d"), date(2024, 3, 12));
+        assert_eq!(relative_day("-7d"), date(2024, 3, 5));
+        // Back over the end of February of a leap year.
+        assert_eq!(relative_day("-12d"), date(2024, 2, 29));
+        assert_eq!(relative_day("-2w"), date(2024, 2, 27));
+        assert_eq!(relative_day("-53w"), date(2023, 3, 7));
+        assert_eq!(relative_day("YESTERDAY"), date(2024, 3, 11));
+    }
+
+    #[test]
+    fn dates_are_not_relative_days() {
+        assert_eq!(relative_day("12.03.2024"), None);
+        assert_eq!(relative_day("2024-03-12"), None);
+    }
+
+    fn relative_day_on(value: &str, today: Option<NaiveDate>) -> Option<NaiveDate> {
+        parse_relative_day(value, today.unwrap()).unwrap()
+    }
+
+    #[test]
+    fn week_starts_on_the_configured_day() {
+        // A Tuesday, a Sunday and a Saturday.
+        let on = |year, month, day, first| week_start(date(year, month, day).unwrap(), first);
+        assert_eq!(on(2024, 3, 12, Weekday::Sun), date(2024, 3, 10).unwrap());
+        assert_eq!(on(2024, 3, 10, Weekday::Sun), date(2024, 3, 10).unwrap());
+        assert_eq!(on(2024, 3, 16, Weekday::Sun), date(2024, 3, 10).unwrap());
+        assert_eq!(on(2024, 3, 12, Weekday::Sat), date(2024, 3, 9).unwrap());
+        assert_eq!(on(2024, 3, 1, Weekday::Mon), date(2024, 2, 26).unwrap());
+    }
+
+    #[test]
+    fn week_starts_on_monday_even_in_the_previous_month_or_year() {
+        // A Tuesday, a Monday and a Sunday of the same week.
+        assert_eq!(relative_day("week"), date(2024, 3, 11));
+        assert_eq!(
+            relative_day_on("week", date(2024, 3, 11)),
+            date(2024, 3, 11)
+        );
+        assert_eq!(
+            relative_day_on("week", date(2024, 3, 17)),
+            date(2024, 3, 11)
+        );
+        assert_eq!(relative_day_on("week", date(2024, 3, 1)), date(2024, 2, 26));
+        assert_eq!(
+            relative_day_on("week", date(2025, 1, 1)),
+            date(2024, 12, 30)
+        );
+    }
+
+    #[test]
+    fn month_starts_on_the_first() {
+        assert_eq!(relative_day("month"), date(2024, 3, 1));
+        assert_eq!(
+            relative_day_on("month", date(2024, 3, 31)),
+            date(2024, 3, 1)
+        );
+        assert_eq!(
+            relative_day_on("month", date(2024, 2, 29)),
+            date(2024, 2, 1)
+        );
+        assert_eq!(relative_day_on("month", date(2025, 1, 1)), date(2025, 1, 1));
+    }
+
+    #[test]
+    fn invalid_relative_days_are_errors() {
+        let today = NaiveDate::from_ymd_opt(2024, 3, 12).unwrap();
+        for value in ["-", "-d", "-xd", "-1.5w"] {
+            let error = parse_relative_day(value, today).unwrap_err().to_string();
+            assert!(error.contains(RELATIVE_DAYS), "{value}: {error}");
+        }
+        let error = parse_relative_day("-7y", today).unwrap_err().to_string();
+        assert!(error.contains("Use d or w."), "{error}");
+        let error = parse_relative_day("-4000000000w", today);
+        assert!(error.is_err());
+        let error = parse_relative_day("-400000000w", today)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("too far in the past"), "{error}");
+    }
+}
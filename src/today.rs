@@ -0,0 +1,254 @@
+//! A small sidecar file with today's totals, for tools which ask for them often.
+//!
+//! When `storage.today_file` is enabled, every save of the store also writes "<db>.today.json".
+//! Reading it doesn't require parsing the whole store. The sidecar remembers the size and
+//! the modification time of the database file it was computed from, so if the database was
+//! changed by something else (or the day is over), it is recomputed instead of trusted.
+
+use std::{fs, time::UNIX_EPOCH};
+
+use chrono::Local as LocalTZ;
+
+use crate::{
+    Result,
+    store::{Store, get_path_to_store_file, write_file_atomically},
+};
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, PartialEq)]
+pub struct Today {
+    /// The day of the totals, "%Y-%m-%d".
+    pub date: String,
+    /// Seconds worked today in the sessions which have ended.
+    pub ended_total: u64,
+    /// The sessions which are still running, as far as they are in today.
+    pub running: Vec<Running>,
+    store_stamp: Option<StoreStamp>,
+}
+
+/// A running session, for counting its time up to any moment of the day.
+#[derive(serde::Deserialize, serde::Serialize, Debug, PartialEq, Clone, Copy)]
+pub struct Running {
+    /// The start, or midnight if the session started before today.
+    pub start_at: i64,
+    /// Seconds of the ended breaks since `start_at`.
+    pub break_seconds: u64,
+    /// The start of the open break, if the session is paused.
+    pub paused_at: Option<i64>,
+}
+
+impl Running {
+    fn worked_until(&self, at: i64) -> u64 {
+        let until = self.paused_at.map_or(at, |x| x.min(at));
+        ((until - self.start_at).max(0) as u64).saturating_sub(self.break_seconds)
+    }
+}
+
+/// Identifies a version of the database file without reading it.
+#[derive(serde::Deserialize, serde::Serialize, Debug, PartialEq, Clone, Copy)]
+struct StoreStamp {
+    len: u64,
+    modified_nanos: u64,
+}
+
+pub fn get_path_to_today_file() -> String {
+    format!("{}.today.json", get_path_to_store_file())
+}
+
+impl Today {
+    pub fn compute(store: &Store) -> Self {
+        let now = LocalTZ::now();
+        let midnight = now
+            .date_naive()
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+            .and_local_timezone(LocalTZ)
+            .unwrap()
+            .timestamp();
+        let mut today = Self {
+            date: now.format("%Y-%m-%d").to_string(),
+            ended_total: 0,
+            running: vec![],
+            store_stamp: get_store_stamp(),
+        };
+        today.add_sessions(store, midnight, now.timestamp());
+        today
+    }
+
+    fn add_sessions(&mut self, store: &Store, midnight: i64, now: i64) {
+        // Only the part of a session after midnight counts, so the sessions which started
+        // yesterday are clamped instead of skipped.
+        for session in &store.sessions {
+            let start_at = session.start_at.max(midnight);
+            let worked = |end_at: i64| -> u64 {
+                session
+                    .worked_intervals(start_at, end_at)
+                    .iter()
+                    .map(|(start, end)| (end - start) as u64)
+                    .sum()
+            };
+            match session.end_at {
+                Some(end_at) if end_at > midnight => self.ended_total += worked(end_at),
+                Some(_) => {}
+                None => {
+                    let paused_at = session
+                        .breaks
+                        .iter()
+                        .find(|(_, end)| end.is_none())
+                        .map(|(start, _)| (*start).max(start_at));
+                    // The time up to now minus the time worked is the time in the breaks.
+                    let until = paused_at.unwrap_or(now).max(start_at);
+                    self.running.push(Running {
+                        start_at,
+                        break_seconds: (until - start_at) as u64 - worked(until),
+                        paused_at,
+                    });
+                }
+            }
+        }
+    }
+
+    /// Seconds tracked today up to now.
+    pub fn total(&self) -> u64 {
+        self.total_at(LocalTZ::now().timestamp())
+    }
+
+    fn total_at(&self, at: i64) -> u64 {
+        self.ended_total + self.running.iter().map(|x| x.worked_until(at)).sum::<u64>()
+    }
+
+    pub fn is_running(&self) -> bool {
+        !self.running.is_empty()
+    }
+
+    /// Recompute the sidecar from the store and write it.
+    pub fn update(store: &Store) -> Result<Self> {
+        let today = Self::compute(store);
+        today.save()?;
+        Ok(today)
+    }
+
+    /// Read the sidecar without touching the store, unless the sidecar is missing or stale.
+    pub fn load_fast() -> Result<Self> {
+        let path = get_path_to_today_file();
+        let cached: Option<Self> = fs::read_to_string(&path)
+            .ok()
+            .and_then(|x| serde_json::from_str(&x).ok());
+        if let Some(today) = cached
+            && today.date == LocalTZ::now().format("%Y-%m-%d").to_string()
+            && today.store_stamp == get_store_stamp()
+        {
+            return Ok(today);
+        }
+        Self::update(&Store::from_store_file()?)
+    }
+
+    fn save(&self) -> Result<()> {
+        let json = serde_json::to_string(self)
+            .map_err(|x| format!("Could not create a JSON string from today's totals. {x}"))?;
+        write_file_atomically(&get_path_to_today_file(), &json)
+    }
+}
+
+fn get_store_stamp() -> Option<StoreStamp> {
+    let metadata = fs::metadata(get_path_to_store_file()).ok()?;
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some(StoreStamp {
+        len: metadata.len(),
+        modified_nanos: modified.as_nanos() as u64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIDNIGHT: i64 = 1_000_000;
+
+    fn store(sessions: serde_json::Value) -> Store {
+        serde_json::from_value(serde_json::json!({ "sessions": sessions })).unwrap()
+    }
+
+    fn session(start_at: i64, end_at: Option<i64>, breaks: serde_json::Value) -> serde_json::Value {
+        serde_json::json!({
+            "id": start_at.to_string(),
+            "start_at": start_at,
+            "end_at": end_at,
+            "note": null,
+            "labels": [],
+            "breaks": breaks,
+        })
+    }
+
+    fn today(store: &Store, now: i64) -> Today {
+        let mut today = Today {
+            date: "2024-01-01".to_string(),
+            ended_total: 0,
+            running: vec![],
+            store_stamp: None,
+        };
+        today.add_sessions(store, MIDNIGHT, now);
+        today
+    }
+
+    #[test]
+    fn ended_sessions_are_clamped_to_midnight() {
+        let store = store(serde_json::json!([
+            session(MIDNIGHT - 600, Some(MIDNIGHT - 300), serde_json::json!([])),
+            session(MIDNIGHT - 600, Some(MIDNIGHT + 900), serde_json::json!([])),
+            session(
+                MIDNIGHT + 1000,
+                Some(MIDNIGHT + 2000),
+                serde_json::json!([[MIDNIGHT + 1100, MIDNIGHT + 1200]])
+            ),
+        ]));
+        let today = today(&store, MIDNIGHT + 3000);
+        assert_eq!(today.ended_total, 900 + 900);
+        assert!(!today.is_running());
+    }
+
+    #[test]
+    fn running_session_since_yesterday_counts_from_midnight_without_breaks() {
+        let store = store(serde_json::json!([session(
+            MIDNIGHT - 3600,
+            None,
+            serde_json::json!([
+                [MIDNIGHT - 1800, MIDNIGHT - 900],
+                [MIDNIGHT + 100, MIDNIGHT + 400]
+            ])
+        )]));
+        let today = today(&store, MIDNIGHT + 1000);
+        assert!(today.is_running());
+        assert_eq!(today.total_at(MIDNIGHT + 1000), 700);
+        // The sidecar keeps counting after it was computed.
+        assert_eq!(today.total_at(MIDNIGHT + 2000), 1700);
+    }
+
+    #[test]
+    fn paused_session_stops_counting() {
+        let store = store(serde_json::json!([session(
+            MIDNIGHT + 100,
+            None,
+            serde_json::json!([[MIDNIGHT + 200, MIDNIGHT + 300], [MIDNIGHT + 500, null]])
+        )]));
+        let today = today(&store, MIDNIGHT + 1000);
+        assert_eq!(today.total_at(MIDNIGHT + 1000), 300);
+        assert_eq!(today.total_at(MIDNIGHT + 5000), 300);
+    }
+
+    #[test]
+    fn fast_path_reads_back_the_same_total() {
+        let store = store(serde_json::json!([
+            session(
+                MIDNIGHT - 3600,
+                None,
+                serde_json::json!([[MIDNIGHT + 100, MIDNIGHT + 400]])
+            ),
+            session(MIDNIGHT + 10, Some(MIDNIGHT + 50), serde_json::json!([])),
+        ]));
+        let today = today(&store, MIDNIGHT + 1000);
+        let json = serde_json::to_string(&today).unwrap();
+        let read: Today = serde_json::from_str(&json).unwrap();
+        assert_eq!(read, today);
+        assert_eq!(read.total_at(MIDNIGHT + 2000), 40 + 1700);
+    }
+}
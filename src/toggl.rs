@@ -0,0 +1,192 @@
+//! Pushing sessions to Toggl Track as time entries.
+//!
+//! A pushed session remembers the id of its time entry, so pushing it again updates the entry
+//! instead of creating another one. Labels mapped to a project by `toggl.projects` set
+//! the project, the rest become tags, and the note becomes the description. Running sessions
+//! are not pushed until they end.
+
+use std::{thread, time::Duration};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use chrono::{DateTime, SecondsFormat};
+use wtt::{
+    Result, config,
+    report::GroupBy,
+    store::{Filter, Session},
+};
+
+use crate::{handle, handle::StoreHandle};
+
+const API: &str = "https://api.track.toggl.com/api/v9";
+/// The key of the time entry id in the session's external ids.
+const SERVICE: &str = "toggl";
+/// Toggl asks for about one request per second.
+const REQUEST_INTERVAL: Duration = Duration::from_secs(1);
+/// How many times a rate-limited request is tried.
+const ATTEMPTS: u64 = 3;
+
+/// Push the ended sessions selected by the filter. Returns the exit code.
+pub fn push(filter: &Filter) -> Result<i32> {
+    let workspace_id: u64 = config::get().toggl_workspace_id.value.parse().map_err(
+        |_| "Set toggl.workspace_id (or WTT_TOGGL_WORKSPACE_ID) to the id of the Toggl workspace.",
+    )?;
+    let token = match std::env::var("WTT_TOGGL_TOKEN") {
+        Ok(x) if !x.is_empty() => x,
+        _ if handle::is_dry_run() => String::new(),
+        _ => return Err("Set WTT_TOGGL_TOKEN to your Toggl API token.".into()),
+    };
+    let auth = format!("Basic {}", STANDARD.encode(format!("{token}:api_token")));
+    let api = std::env::var("WTT_TOGGL_API_URL").unwrap_or_else(|_| API.to_string());
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .http_status_as_error(false)
+        .timeout_global(Some(Duration::from_secs(30)))
+        .build()
+        .into();
+
+    let mut store = StoreHandle::open()?;
+    let ids: Vec<String> = store
+        .report(filter, GroupBy::None)
+        .rows
+        .into_iter()
+        .filter(|x| x.end_at.is_some())
+        .map(|x| x.id)
+        .collect();
+
+    let (mut created, mut updated) = (0, 0);
+    let mut failures: Vec<(u64, String)> = vec![];
+    for (index, id) in ids.iter().enumerate() {
+        let session = store.get_session_by_id(id)?;
+        let entry_id = session.external_ids.get(SERVICE).cloned();
+        let entry = match time_entry(session, workspace_id) {
+            Ok(x) => x,
+            Err(x) => {
+                failures.push((session.seq, x));
+                continue;
+            }
+        };
+        let action = if entry_id.is_some() {
+            "update"
+        } else {
+            "create"
+        };
+        if handle::is_dry_run() {
+            println!("(dry-run) Would {action} #{}: {entry}", session.seq);
+            continue;
+        }
+
+        if index > 0 {
+            thread::sleep(REQUEST_INTERVAL);
+        }
+        let url = match entry_id.as_deref() {
+            Some(x) => format!("{api}/workspaces/{workspace_id}/time_entries/{x}"),
+            None => format!("{api}/workspaces/{workspace_id}/time_entries"),
+        };
+        log::debug!("Pushing #{} to Toggl ({action}).", session.seq);
+        match send(&agent, &url, entry_id.is_some(), &auth, &entry.to_string()) {
+            Ok(entry_id) => {
+                session
+                    .external_ids
+                    .insert(SERVICE.to_string(), entry_id.to_string());
+                match action {
+                    "create" => created += 1,
+                    _ => updated += 1,
+                }
+            }
+            Err(x) => failures.push((session.seq, x)),
+        }
+    }
+
+    if !handle::is_dry_run() {
+        // Saved even when some sessions failed, so the created entries are not created again.
+        store.save_as("push toggl")?;
+        println!("Pushed to Toggl: {created} created, {updated} updated.");
+    }
+    if failures.is_empty() {
+        return Ok(0);
+    }
+    eprintln!("{} sessions were not pushed:", failures.len());
+    for (seq, reason) in failures {
+        eprintln!("  #{seq}: {reason}");
+    }
+    Ok(1)
+}
+
+fn time_entry(
+    session: &Session,
+    workspace_id: u64,
+) -> std::result::Result<serde_json::Value, String> {
+    let projects = &config::get().toggl_projects.value;
+    let mut project_id: Option<u64> = None;
+    let mut tags: Vec<&str> = vec![];
+    for label in &session.labels {
+        match projects.get(label) {
+            Some(project) if project_id.is_none() => {
+                project_id = Some(project.parse().map_err(|_| {
+                    format!("The Toggl project of the label {label}, '{project}', is not a number.")
+                })?);
+            }
+            _ => tags.push(label),
+        }
+    }
+    let end_at = session.end_at.unwrap();
+    Ok(serde_json::json!({
+        "created_with": "wtt",
+        "workspace_id": workspace_id,
+        "description": session.note.as_deref().unwrap_or(""),
+        "start": format_utc(session.start_at),
+        "stop": format_utc(end_at),
+        "duration": end_at - session.start_at,
+        "project_id": project_id,
+        "tags": tags,
+    }))
+}
+
+/// Send a time entry and return its id. Rate-limited requests are tried again after a pause.
+fn send(
+    agent: &ureq::Agent,
+    url: &str,
+    update: bool,
+    auth: &str,
+    body: &str,
+) -> std::result::Result<u64, String> {
+    for attempt in 1..=ATTEMPTS {
+        let request = if update {
+            agent.put(url)
+        } else {
+            agent.post(url)
+        };
+        let mut response = request
+            .header("Authorization", auth)
+            .header("Content-Type", "application/json")
+            .send(body)
+            .map_err(|x| x.to_string())?;
+        let status = response.status().as_u16();
+        if status == 429 && attempt < ATTEMPTS {
+            let wait = response
+                .headers()
+                .get("Retry-After")
+                .and_then(|x| x.to_str().ok())
+                .and_then(|x| x.parse().ok())
+                .unwrap_or(attempt * 2);
+            log::debug!("Toggl asked to slow down, waiting for {wait} seconds.");
+            thread::sleep(Duration::from_secs(wait));
+            continue;
+        }
+        let text = response.body_mut().read_to_string().unwrap_or_default();
+        if !(200..300).contains(&status) {
+            return Err(format!("Toggl answered {status}. {}", text.trim()));
+        }
+        let entry: serde_json::Value = serde_json::from_str(&text)
+            .map_err(|x| format!("Could not parse the answer of Toggl. {x}"))?;
+        return entry["id"]
+            .as_u64()
+            .ok_or_else(|| "The answer of Toggl has no id.".to_string());
+    }
+    Err("Toggl kept asking to slow down.".to_string())
+}
+
+fn format_utc(timestamp: i64) -> String {
+    DateTime::from_timestamp(timestamp, 0)
+        .unwrap()
+        .to_rfc3339_opts(SecondsFormat::Secs, true)
+}
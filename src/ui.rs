@@ -0,0 +1,308 @@
+//! An interactive terminal UI for the daily loop: today's sessions with live durations,
+//! starting, ending and switching sessions, editing notes and filtering by labels.
+//!
+//! Changes go through `StoreHandle` like the other commands, so they can be undone and
+//! respect --dry-run. The store is reloaded whenever the database file changes on disk.
+
+use std::{fs, time::SystemTime};
+
+use ratatui::{
+    DefaultTerminal, Frame,
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Style, Stylize},
+    widgets::{Block, Paragraph, Row, Table, TableState},
+};
+use wtt::{
+    Result, config,
+    report::{GroupBy, ReportRow},
+    store::{Filter, NoteMode, Store, get_path_to_store_file},
+};
+
+use crate::{format_duration, handle::StoreHandle, try_get_from_timestamp};
+
+const HELP: &str = "s start  e end  w switch  n note  / filter  ↑↓ select  q quit";
+
+enum PromptKind {
+    Start,
+    Switch,
+    Note { id: String },
+    Filter,
+}
+
+struct Prompt {
+    kind: PromptKind,
+    input: String,
+}
+
+struct App {
+    store: Store,
+    /// When the database file was modified as of the last load.
+    modified: Option<SystemTime>,
+    rows: Vec<ReportRow>,
+    total: u64,
+    table_state: TableState,
+    prompt: Option<Prompt>,
+    /// Label prefixes separated with spaces or commas.
+    filter: String,
+    message: Option<String>,
+}
+
+pub fn run() -> Result<()> {
+    let mut app = App::new()?;
+    let mut terminal = ratatui::init();
+    let result = app.run(&mut terminal);
+    ratatui::restore();
+    result
+}
+
+impl App {
+    fn new() -> Result<Self> {
+        Ok(Self {
+            store: Store::from_store_file()?,
+            modified: get_modified_time(),
+            rows: vec![],
+            total: 0,
+            table_state: TableState::default().with_selected(Some(0)),
+            prompt: None,
+            filter: String::new(),
+            message: None,
+        })
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        loop {
+            self.refresh();
+            terminal.draw(|frame| self.draw(frame))?;
+
+            // Redraw every second, so the durations of running sessions stay current.
+            if !event::poll(std::time::Duration::from_secs(1))? {
+                continue;
+            }
+            if let Event::Key(key) = event::read()?
+                && key.kind == KeyEventKind::Press
+                && !self.handle_key(key.code)
+            {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Reload the store if the file was changed elsewhere, and recompute today's rows.
+    fn refresh(&mut self) {
+        let modified = get_modified_time();
+        if modified != self.modified {
+            match Store::from_store_file() {
+                Ok(store) => {
+                    self.store = store;
+                    self.modified = modified;
+                }
+                Err(x) => self.message = Some(x.to_string()),
+            }
+        }
+
+        let filter = Filter {
+            from: try_get_from_timestamp("today").ok(),
+            label_prefixes: split_labels(&self.filter),
+            ..Default::default()
+        };
+        let report = self.store.report(&filter, GroupBy::None);
+        self.rows = report.rows;
+        self.total = report.total;
+
+        let last = self.rows.len().saturating_sub(1);
+        if self.table_state.selected().is_none_or(|x| x > last) {
+            self.table_state.select(Some(last));
+        }
+    }
+
+    /// Returns false when the UI should be closed.
+    fn handle_key(&mut self, code: KeyCode) -> bool {
+        if let Some(prompt) = self.prompt.as_mut() {
+            match code {
+                KeyCode::Enter => {
+                    let prompt = self.prompt.take().unwrap();
+                    self.submit(prompt);
+                }
+                KeyCode::Esc => self.prompt = None,
+                KeyCode::Backspace => {
+                    prompt.input.pop();
+                }
+                KeyCode::Char(c) => prompt.input.push(c),
+                _ => {}
+            }
+            return true;
+        }
+
+        self.message = None;
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Down | KeyCode::Char('j') => self.table_state.select_next(),
+            KeyCode::Up | KeyCode::Char('k') => self.table_state.select_previous(),
+            KeyCode::Char('s') => self.open_prompt(PromptKind::Start, String::new()),
+            KeyCode::Char('w') => self.open_prompt(PromptKind::Switch, String::new()),
+            KeyCode::Char('/') => self.open_prompt(PromptKind::Filter, self.filter.clone()),
+            KeyCode::Char('n') => {
+                if let Some(row) = self.selected() {
+                    let id = row.id.clone();
+                    let note = row.note.clone().unwrap_or_default();
+                    self.open_prompt(PromptKind::Note { id }, note);
+                }
+            }
+            KeyCode::Char('e') => {
+                if let Some(row) = self.selected() {
+                    let id = row.id.clone();
+                    self.modify("ui: end a session", |store| {
+                        store.end_session(Some(&id), None, None, None, None)?;
+                        Ok(format!("The session {id} was successfully ended."))
+                    });
+                }
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn open_prompt(&mut self, kind: PromptKind, input: String) {
+        self.prompt = Some(Prompt { kind, input });
+    }
+
+    fn submit(&mut self, prompt: Prompt) {
+        match prompt.kind {
+            PromptKind::Filter => self.filter = prompt.input,
+            PromptKind::Start => {
+                let labels = labels_or_default(&prompt.input);
+                self.modify("ui: start a session", |store| {
+                    let session = store.start_session(labels, None, None, None)?;
+                    Ok(format!(
+                        "New session was successfully started: {}",
+                        session.id
+                    ))
+                });
+            }
+            PromptKind::Switch => {
+                let labels = labels_or_default(&prompt.input);
+                self.modify("ui: switch sessions", |store| {
+                    store.end_session(None, None, None, None, None)?;
+                    let session = store.start_session(labels, None, None, None)?;
+                    Ok(format!("Switched to the session {}", session.id))
+                });
+            }
+            PromptKind::Note { id } => {
+                self.modify("ui: edit a note", |store| {
+                    store.update_note(&id, prompt.input, NoteMode::Replace, false)?;
+                    Ok("Updated.".to_string())
+                });
+            }
+        }
+    }
+
+    /// Change the store through the same path as the commands and show the outcome.
+    fn modify(&mut self, description: &str, change: impl FnOnce(&mut Store) -> Result<String>) {
+        let result = StoreHandle::open().and_then(|mut handle| {
+            let message = change(&mut handle)?;
+            handle.save_as(description)?;
+            Ok(message)
+        });
+        self.message = Some(match result {
+            Ok(x) => x,
+            Err(x) => x.to_string(),
+        });
+        // Force a reload, the modification time may not have changed within the same second.
+        self.modified = None;
+    }
+
+    fn selected(&self) -> Option<&ReportRow> {
+        self.table_state.selected().and_then(|x| self.rows.get(x))
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [table_area, status_area] =
+            Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+
+        let rows: Vec<Row> = self
+            .rows
+            .iter()
+            .map(|row| {
+                let end = row.end_at.map(format_time).unwrap_or_default();
+                let note = row
+                    .note
+                    .as_deref()
+                    .unwrap_or("")
+                    .lines()
+                    .next()
+                    .unwrap_or("");
+                Row::new(vec![
+                    format!("#{}", row.seq),
+                    format_time(row.start_at),
+                    end,
+                    format_duration(row.duration, row.end_at.is_none(), " "),
+                    row.labels.join(", "),
+                    note.to_string(),
+                ])
+            })
+            .collect();
+        let widths = [
+            Constraint::Length(8),
+            Constraint::Length(5),
+            Constraint::Length(5),
+            Constraint::Length(26),
+            Constraint::Percentage(25),
+            Constraint::Fill(1),
+        ];
+        let mut title = format!(" Today: {} ", format_duration(self.total, false, " "));
+        if !self.filter.is_empty() {
+            title.push_str(&format!("| filter: {} ", self.filter));
+        }
+        let table = Table::new(rows, widths)
+            .header(Row::new(["#", "Start", "End", "Duration", "Labels", "Note"]).bold())
+            .block(Block::bordered().title(title))
+            .row_highlight_style(Style::new().reversed());
+        frame.render_stateful_widget(table, table_area, &mut self.table_state);
+
+        let status = match (&self.prompt, &self.message) {
+            (Some(prompt), _) => {
+                let label = match prompt.kind {
+                    PromptKind::Start => "Labels of the new session",
+                    PromptKind::Switch => "Labels of the next session",
+                    PromptKind::Note { .. } => "Note",
+                    PromptKind::Filter => "Label prefixes",
+                };
+                format!("{label}: {}█", prompt.input)
+            }
+            (None, Some(message)) => message.clone(),
+            (None, None) => HELP.to_string(),
+        };
+        frame.render_widget(Paragraph::new(status), status_area);
+    }
+}
+
+fn get_modified_time() -> Option<SystemTime> {
+    fs::metadata(get_path_to_store_file())
+        .and_then(|x| x.modified())
+        .ok()
+}
+
+fn format_time(timestamp: i64) -> String {
+    chrono::DateTime::from_timestamp(timestamp, 0)
+        .unwrap()
+        .with_timezone(&chrono::Local)
+        .format("%H:%M")
+        .to_string()
+}
+
+fn split_labels(text: &str) -> Vec<String> {
+    text.split([',', ' '])
+        .filter(|x| !x.is_empty())
+        .map(|x| x.to_string())
+        .collect()
+}
+
+fn labels_or_default(text: &str) -> Vec<String> {
+    let labels = split_labels(text);
+    if labels.is_empty() {
+        config::get().default_labels.value.clone()
+    } else {
+        labels
+    }
+}
@@ -0,0 +1,94 @@
+//! One level of undo for the commands which modify the store.
+//!
+//! Before a command saves the store, the content of the database file it started from is kept
+//! next to the database in a file with the ".undo" suffix, together with a hash of what was saved.
+//! Undo puts the old content back, but only if the database hasn't been changed since.
+
+use std::fs;
+
+use crate::{Result, backend, store::get_path_to_store_file};
+
+#[derive(serde::Deserialize, serde::Serialize, Debug)]
+pub struct UndoRecord {
+    /// The command line of the command, for telling the user what will be undone.
+    pub command: String,
+    /// The database content before the command. None if there was no database file.
+    pub before: Option<String>,
+    /// A hash of the database content right after the command.
+    after_hash: u64,
+}
+
+pub fn get_path_to_undo_file() -> String {
+    format!("{}.undo", get_path_to_store_file())
+}
+
+/// Read the database as it is on disk, if it exists. SQLite gives the store as JSON.
+pub fn read_store_file() -> Result<Option<String>> {
+    backend::current().snapshot()
+}
+
+impl UndoRecord {
+    /// Remember how to revert the command that has just saved the store.
+    pub fn save(command: String, before: Option<String>) -> Result<()> {
+        let after = read_store_file()?.unwrap_or_default();
+        let record = UndoRecord {
+            command,
+            before,
+            after_hash: hash(&after),
+        };
+
+        let path = get_path_to_undo_file();
+        let record_json = serde_json::to_string(&record)
+            .map_err(|x| format!("Could not create a JSON string from the undo record. {x}"))?;
+        fs::write(&path, record_json)
+            .map_err(|x| format!("Could not write the undo file {}. {}", &path, x))?;
+        Ok(())
+    }
+
+    pub fn load() -> Result<Self> {
+        let path = get_path_to_undo_file();
+        let file_exists = fs::exists(&path)
+            .map_err(|x| format!("Could not check the undo file {}. {}", &path, x))?;
+        if !file_exists {
+            return Err("There is nothing to undo.".into());
+        }
+        let content = fs::read_to_string(&path)
+            .map_err(|x| format!("Could not open the undo file {}. {}", &path, x))?;
+        let record: UndoRecord = serde_json::from_str(&content)
+            .map_err(|x| format!("Could not parse the undo file as JSON data. {x}"))?;
+        Ok(record)
+    }
+
+    /// Fail if the database was changed after the command, since restoring would lose those changes.
+    pub fn check_applicable(&self) -> Result<()> {
+        let current = read_store_file()?.unwrap_or_default();
+        if hash(&current) != self.after_hash {
+            return Err(format!(
+                "The database was modified after `{}`, so it can't be undone.",
+                self.command
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Restore the database as it was before the command. Only one level of undo is kept,
+    /// so the record is removed afterwards.
+    pub fn apply(self) -> Result<()> {
+        self.check_applicable()?;
+
+        backend::current().restore(self.before.as_deref())?;
+
+        let undo_path = get_path_to_undo_file();
+        fs::remove_file(&undo_path)
+            .map_err(|x| format!("Could not remove the undo file {}. {}", &undo_path, x))?;
+        Ok(())
+    }
+}
+
+/// FNV-1a. It only has to tell whether the file changed, and it is stable across builds.
+fn hash(content: &str) -> u64 {
+    content.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    })
+}
@@ -0,0 +1,86 @@
+//! Notifying other services when sessions start and end.
+//!
+//! The session is already saved when the requests go out, so a failed delivery is only a warning.
+
+use std::{thread, time::Duration};
+
+use wtt::{config, store::Session};
+
+use crate::events::Event;
+
+/// How long all of the requests of a command may take together.
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Send the events to the configured URLs.
+pub fn notify(events: &[(Event, Session)]) {
+    let start_url = &config::get().webhook_start_url.value;
+    let end_url = &config::get().webhook_end_url.value;
+    let requests: Vec<(&str, String)> = events
+        .iter()
+        .filter_map(|(event, session)| {
+            let url = match event {
+                Event::Start | Event::Resume => start_url,
+                Event::End | Event::Cancel => end_url,
+            };
+            (!url.is_empty()).then(|| (url.as_str(), payload(event.name(), session)))
+        })
+        .collect();
+    if requests.is_empty() {
+        return;
+    }
+
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(TIMEOUT))
+        .build()
+        .into();
+    let secret = std::env::var("WTT_WEBHOOK_SECRET").ok();
+    thread::scope(|scope| {
+        for (url, body) in requests {
+            let agent = agent.clone();
+            let secret = secret.as_deref();
+            scope.spawn(move || {
+                let mut request = agent.post(url).header("Content-Type", "application/json");
+                if let Some(secret) = secret {
+                    request = request.header("X-Wtt-Signature", sign(&body, secret));
+                }
+                match request.send(&body) {
+                    Ok(_) => log::debug!("Sent the webhook to {url}."),
+                    Err(x) => eprintln!("Warning: the webhook to {url} failed. {x}"),
+                }
+            });
+        }
+    });
+}
+
+fn payload(event: &str, session: &Session) -> String {
+    serde_json::json!({
+        "event": event,
+        "id": session.id,
+        "start_at": session.start_at,
+        "end_at": session.end_at,
+        "labels": session.labels,
+        "note": session.note,
+    })
+    .to_string()
+}
+
+/// "sha256=" and the hex HMAC-SHA256 of the body, like GitHub's webhooks.
+fn sign(body: &str, secret: &str) -> String {
+    let mac = hmac_sha256::HMAC::mac(body, secret);
+    let hex: String = mac.iter().map(|x| format!("{x:02x}")).collect();
+    format!("sha256={hex}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signature_is_the_hmac_sha256_of_the_body() {
+        // RFC 4231, test case 2.
+        assert_eq!(
+            sign("what do ya want for nothing?", "Jefe"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+}
@@ -0,0 +1,78 @@
+mod common;
+
+use common::Wtt;
+
+fn batch(wtt: &Wtt, commands: &str, keep_going: bool) -> std::process::Output {
+    let path = wtt.path("commands.txt");
+    std::fs::write(&path, commands).unwrap();
+    let path = path.to_str().unwrap();
+    let mut args = vec!["batch", path];
+    if keep_going {
+        args.push("--keep-going");
+    }
+    wtt.run(&args)
+}
+
+fn labels(wtt: &Wtt) -> Vec<String> {
+    let rows = wtt.json(&["session", "table"])["rows"].clone();
+    rows.as_array()
+        .unwrap()
+        .iter()
+        .map(|x| x["labels"][0].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[test]
+fn current_without_a_running_session_exits_with_1() {
+    let wtt = Wtt::new();
+    let output = wtt.run(&["session", "current"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        "No running session."
+    );
+    assert!(output.stderr.is_empty());
+
+    wtt.ok(&["start", "-l", "a"]);
+    wtt.ok(&["session", "current"]);
+}
+
+#[test]
+fn exit_status_in_a_batch_is_a_failed_line() {
+    let wtt = Wtt::new();
+    let commands = "start -l a\nstop\nsession current\nstart -l b\n";
+
+    let output = batch(&wtt, commands, false);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Line 3 failed: session current"),
+        "{stderr}"
+    );
+    assert!(stderr.contains("Nothing was saved."), "{stderr}");
+    assert!(labels(&wtt).is_empty());
+
+    let output = batch(&wtt, commands, true);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("1 lines failed and were skipped: 3."),
+        "{stderr}"
+    );
+    assert_eq!(labels(&wtt), vec!["a", "b"]);
+}
+
+#[test]
+fn session_status_in_a_batch_is_a_failed_line() {
+    let wtt = Wtt::new();
+    let output = wtt.run(&["session", "status", "--quiet"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty() && output.stderr.is_empty());
+
+    let output = batch(&wtt, "start -l a\nstop\nsession status\nstart -l b\n", true);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Line 3 failed: session status"), "{stderr}");
+    assert_eq!(labels(&wtt), vec!["a", "b"]);
+    wtt.ok(&["session", "status", "--quiet"]);
+}
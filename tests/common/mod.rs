@@ -0,0 +1,128 @@
+//! Runs the binary against a database in a directory of its own, so the tests neither touch
+//! the user's data nor see each other's sessions.
+
+#![allow(dead_code)]
+
+use std::{
+    fs,
+    path::PathBuf,
+    process::{Command, Output},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+pub struct Wtt {
+    pub dir: PathBuf,
+}
+
+impl Wtt {
+    pub fn new() -> Self {
+        let dir = std::env::temp_dir().join(format!(
+            "wtt-test-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        Self { dir }
+    }
+
+    pub fn db(&self) -> PathBuf {
+        self.dir.join("db.json")
+    }
+
+    pub fn config(&self) -> PathBuf {
+        self.dir.join("config.toml")
+    }
+
+    /// The command with a clean environment: English messages, no config file but the test's
+    /// own, and no nagging about long sessions.
+    pub fn command(&self, args: &[&str]) -> Command {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_wtt"));
+        command
+            .args(args)
+            .env_clear()
+            .env("PATH", std::env::var("PATH").unwrap_or_default())
+            .env("HOME", &self.dir)
+            .env("XDG_CONFIG_HOME", self.dir.join("config"))
+            .env("XDG_DATA_HOME", self.dir.join("data"))
+            .env("WTT_PATH_DATABASE", self.db())
+            .env("WTT_PATH_CONFIG", self.config())
+            .env("WTT_LANG", "en")
+            .env("WTT_NO_NAG", "1")
+            .current_dir(&self.dir);
+        command
+    }
+
+    pub fn run(&self, args: &[&str]) -> Output {
+        self.command(args).output().unwrap()
+    }
+
+    /// Run a command which must succeed and return its stdout.
+    pub fn ok(&self, args: &[&str]) -> String {
+        let output = self.run(args);
+        assert!(
+            output.status.success(),
+            "wtt {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    /// Run a command which must fail and return its stderr.
+    pub fn fails(&self, args: &[&str]) -> String {
+        let output = self.run(args);
+        assert!(
+            !output.status.success(),
+            "wtt {} succeeded: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stdout)
+        );
+        String::from_utf8(output.stderr).unwrap()
+    }
+
+    /// The output of a command run with `--format json`.
+    pub fn json(&self, args: &[&str]) -> serde_json::Value {
+        let args: Vec<&str> = ["--format", "json"].iter().chain(args).copied().collect();
+        serde_json::from_str(&self.ok(&args)).unwrap()
+    }
+
+    pub fn write_db(&self, content: &str) {
+        fs::write(self.db(), content).unwrap();
+    }
+
+    pub fn read_db(&self) -> String {
+        fs::read_to_string(self.db()).unwrap()
+    }
+
+    pub fn write_config(&self, content: &str) {
+        fs::write(self.config(), content).unwrap();
+    }
+
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(name)
+    }
+}
+
+impl Drop for Wtt {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// A session as the database stores it.
+pub fn session(id: &str, start_at: i64, end_at: Option<i64>, labels: &[&str]) -> serde_json::Value {
+    serde_json::json!({
+        "id": id,
+        "start_at": start_at,
+        "end_at": end_at,
+        "note": null,
+        "labels": labels,
+    })
+}
+
+pub fn store(sessions: &[serde_json::Value]) -> String {
+    serde_json::json!({ "sessions": sessions }).to_string()
+}
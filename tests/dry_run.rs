@@ -0,0 +1,99 @@
+mod common;
+
+use common::{Wtt, session, store};
+
+const FIRST: &str = "aaaa0000-0000-4000-8000-000000000001";
+const SECOND: &str = "bbbb0000-0000-4000-8000-000000000002";
+const RUNNING: &str = "cccc0000-0000-4000-8000-000000000003";
+
+fn now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64
+}
+
+/// Two back-to-back ended sessions and a running one.
+fn fixture(wtt: &Wtt) -> String {
+    let now = now();
+    let db = store(&[
+        session(FIRST, now - 7200, Some(now - 5400), &["acme"]),
+        session(SECOND, now - 5400, Some(now - 3600), &["acme"]),
+        session(RUNNING, now - 1800, None, &["web"]),
+    ]);
+    wtt.write_db(&db);
+    db
+}
+
+#[test]
+fn dry_run_leaves_the_database_as_is() {
+    let wtt = Wtt::new();
+    std::fs::write(
+        wtt.path("import.csv"),
+        "From,To\n01.01.2024 10:00,01.01.2024 11:00\n",
+    )
+    .unwrap();
+    let commands: &[&[&str]] = &[
+        &["session", "start", "-l", "new"],
+        &["start", "-l", "new"],
+        &["session", "end"],
+        &["stop"],
+        &["session", "note", "hello"],
+        &["session", "note", "--id", "aaaa", "more", "--append"],
+        &["session", "add", "--start", "3h ago", "--end", "2h ago"],
+        &["session", "switch", "-l", "other"],
+        &["session", "pause"],
+        &["session", "split", "--id", "aaaa", "--at", "110m ago"],
+        &["session", "merge", "--id", "aaaa", "--id", "bbbb"],
+        &["session", "resume", "--id", "bbbb"],
+        &["session", "restart", "--id", "bbbb"],
+        &["session", "cancel"],
+        &[
+            "session", "edit", "--id", "aaaa", "--start", "3h ago", "--yes",
+        ],
+        &["session", "delete", "--id", "aaaa", "--yes"],
+        &["session", "lock", "--id", "aaaa"],
+        &["session", "label", "add", "--id", "aaaa", "urgent"],
+        &["session", "label", "remove", "--id", "aaaa", "acme"],
+        &["label", "remove", "acme", "--yes"],
+        &["label", "merge", "client", "-s", "acme", "--yes"],
+        &[
+            "session",
+            "import",
+            "--csv",
+            "import.csv",
+            "--map",
+            "start=From,end=To",
+        ],
+    ];
+    for command in commands {
+        let db = fixture(&wtt);
+        let args: Vec<&str> = ["--dry-run"]
+            .iter()
+            .chain(command.iter())
+            .copied()
+            .collect();
+        let output = wtt.ok(&args);
+        assert!(
+            output.contains("(dry-run)"),
+            "wtt {} doesn't say it's a dry run: {output}",
+            args.join(" ")
+        );
+        assert_eq!(
+            wtt.read_db(),
+            db,
+            "wtt {} changed the database",
+            args.join(" ")
+        );
+    }
+}
+
+#[test]
+fn dry_run_of_a_paused_session() {
+    let wtt = Wtt::new();
+    fixture(&wtt);
+    wtt.ok(&["session", "pause"]);
+    let db = wtt.read_db();
+    wtt.ok(&["--dry-run", "session", "unpause"]);
+    assert_eq!(wtt.read_db(), db);
+}
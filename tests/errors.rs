@@ -0,0 +1,68 @@
+mod common;
+
+use common::Wtt;
+
+/// Run a command which must fail cleanly: exit code 1 and a message instead of a panic.
+fn fails_cleanly(wtt: &Wtt, args: &[&str]) -> String {
+    let output = wtt.run(args);
+    assert_eq!(output.status.code(), Some(1), "wtt {}", args.join(" "));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(!stderr.contains("panicked"), "{stderr}");
+    assert!(!stderr.contains("RUST_BACKTRACE"), "{stderr}");
+    stderr
+}
+
+#[test]
+fn malformed_database_is_a_message() {
+    let wtt = Wtt::new();
+    wtt.write_db("{\"sessions\": [");
+    for args in [
+        &["session", "table"][..],
+        &["session", "start"],
+        &["label", "list"],
+    ] {
+        let stderr = fails_cleanly(&wtt, args);
+        assert!(
+            stderr.starts_with("Could not parse the database file as JSON data."),
+            "{stderr}"
+        );
+    }
+    // Nothing was overwritten.
+    assert_eq!(wtt.read_db(), "{\"sessions\": [");
+
+    // Status bars get a fallback and exit code 0, which they don't treat as a broken module.
+    let output = wtt.run(&["status"]);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "wtt: error");
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn unreadable_database_is_a_message() {
+    let wtt = Wtt::new();
+    std::fs::create_dir(wtt.db()).unwrap();
+    let stderr = fails_cleanly(&wtt, &["session", "table"]);
+    assert!(
+        stderr.contains("Could not open the database file"),
+        "{stderr}"
+    );
+    assert!(stderr.contains(&wtt.db().display().to_string()), "{stderr}");
+}
+
+#[test]
+fn invalid_dates_are_messages() {
+    let wtt = Wtt::new();
+    let stderr = fails_cleanly(&wtt, &["session", "table", "--from", "nope"]);
+    assert!(stderr.contains("'nope' is not a day."), "{stderr}");
+    let stderr = fails_cleanly(&wtt, &["session", "start", "--at", "30"]);
+    assert!(stderr.contains("ambiguous"), "{stderr}");
+}
+
+#[test]
+fn invalid_week_start_is_a_message() {
+    let wtt = Wtt::new();
+    wtt.write_config("[behavior]\nweek_start = \"someday\"\n");
+    let stderr = fails_cleanly(&wtt, &["session", "table", "--from", "week"]);
+    assert!(stderr.contains("behavior.week_start"), "{stderr}");
+    assert!(stderr.contains("'someday'"), "{stderr}");
+}
@@ -0,0 +1,126 @@
+mod common;
+
+use std::{path::Path, process::Command};
+
+use common::Wtt;
+
+fn git(repo: &Path, args: &[&str], date: Option<&str>) {
+    let mut command = Command::new("git");
+    command
+        .args(args)
+        .current_dir(repo)
+        .env("HOME", repo)
+        .env("GIT_CONFIG_NOSYSTEM", "1");
+    if let Some(date) = date {
+        command
+            .env("GIT_AUTHOR_DATE", date)
+            .env("GIT_COMMITTER_DATE", date);
+    }
+    let output = command.output().unwrap();
+    assert!(
+        output.status.success(),
+        "git {}: {}",
+        args.join(" "),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+/// A repository on the branch "feature" with one commit made long ago.
+fn repository(wtt: &Wtt) -> std::path::PathBuf {
+    let repo = wtt.path("project");
+    std::fs::create_dir(&repo).unwrap();
+    git(
+        &repo,
+        &["init", "--quiet", "--initial-branch", "feature"],
+        None,
+    );
+    git(&repo, &["config", "user.name", "Test"], None);
+    git(&repo, &["config", "user.email", "test@example.com"], None);
+    git(
+        &repo,
+        &["commit", "--quiet", "--allow-empty", "-m", "Old work"],
+        Some("2020-01-01T10:00:00Z"),
+    );
+    repo
+}
+
+fn run_in(wtt: &Wtt, directory: &Path, args: &[&str]) -> std::process::Output {
+    wtt.command(args).current_dir(directory).output().unwrap()
+}
+
+#[test]
+fn branch_is_recorded_and_commits_go_to_the_note() {
+    let wtt = Wtt::new();
+    let repo = repository(&wtt);
+
+    let output = run_in(
+        &wtt,
+        &repo,
+        &["session", "start", "--git", "--at", "1m ago"],
+    );
+    assert!(output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stdout).contains("Recorded the branch feature of project.")
+    );
+    let db: serde_json::Value = serde_json::from_str(&wtt.read_db()).unwrap();
+    assert_eq!(db["sessions"][0]["git"]["branch"], "feature");
+    assert_eq!(db["sessions"][0]["git"]["repository"], "project");
+
+    git(
+        &repo,
+        &[
+            "commit",
+            "--quiet",
+            "--allow-empty",
+            "-m",
+            "Fix the login form",
+        ],
+        None,
+    );
+    // Ended elsewhere, so the recorded repository is used.
+    let output = run_in(&wtt, &wtt.dir, &["session", "end", "--git"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Added 1 commits to the note."));
+
+    let db: serde_json::Value = serde_json::from_str(&wtt.read_db()).unwrap();
+    let note = db["sessions"][0]["note"].as_str().unwrap();
+    assert!(note.starts_with("Commits on feature:\n"), "{note}");
+    assert!(note.contains("Fix the login form"), "{note}");
+    assert!(!note.contains("Old work"), "{note}");
+}
+
+#[test]
+fn outside_a_repository_is_only_a_warning() {
+    let wtt = Wtt::new();
+    let output = run_in(&wtt, &wtt.dir, &["session", "start", "--git"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Warning: git rev-parse failed."));
+    let db: serde_json::Value = serde_json::from_str(&wtt.read_db()).unwrap();
+    assert!(db["sessions"][0].get("git").is_none());
+
+    let output = run_in(&wtt, &wtt.dir, &["session", "end", "--git"]);
+    assert!(output.status.success());
+}
+
+#[test]
+fn detached_head_is_only_a_warning() {
+    let wtt = Wtt::new();
+    let repo = repository(&wtt);
+    git(&repo, &["checkout", "--quiet", "--detach"], None);
+
+    let output = run_in(&wtt, &repo, &["session", "start", "--git"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("HEAD is detached"));
+}
+
+#[test]
+fn missing_git_is_only_a_warning() {
+    let wtt = Wtt::new();
+    let output = wtt
+        .command(&["session", "start", "--git"])
+        .env("PATH", wtt.path("no-such-dir"))
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Warning: could not run git."));
+}
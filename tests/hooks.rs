@@ -0,0 +1,105 @@
+#![cfg(unix)]
+
+mod common;
+
+use std::{fs, os::unix::fs::PermissionsExt};
+
+use common::Wtt;
+
+/// Put a shell script into the hooks directory next to the test's config file.
+fn hook(wtt: &Wtt, name: &str, body: &str, mode: u32) {
+    let dir = wtt.path("hooks");
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    fs::write(&path, format!("#!/bin/sh\n{body}\n")).unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(mode)).unwrap();
+}
+
+/// A hook which saves the session's variables to "<dir>/<name>.env".
+const DUMP: &str = r#"for x in WTT_SESSION_ID WTT_LABELS WTT_START WTT_END WTT_NOTE; do
+    eval "echo $x=\$$x"
+done > "$(dirname "$0")/../$(basename "$0").env""#;
+
+fn dumped(wtt: &Wtt, name: &str) -> Vec<String> {
+    fs::read_to_string(wtt.path(&format!("{name}.env")))
+        .unwrap()
+        .lines()
+        .map(str::to_string)
+        .collect()
+}
+
+#[test]
+fn hooks_get_the_session_in_the_environment() {
+    let wtt = Wtt::new();
+    hook(&wtt, "pre-start", DUMP, 0o755);
+    hook(&wtt, "post-end", DUMP, 0o755);
+
+    wtt.ok(&["session", "start", "-l", "work", "-l", "docs"]);
+    let db: serde_json::Value = serde_json::from_str(&wtt.read_db()).unwrap();
+    let id = db["sessions"][0]["id"].as_str().unwrap().to_string();
+    let start = dumped(&wtt, "pre-start");
+    assert_eq!(start[0], format!("WTT_SESSION_ID={id}"));
+    assert_eq!(start[1], "WTT_LABELS=work,docs");
+    assert!(start[2].starts_with("WTT_START=20"), "{start:?}");
+    assert_eq!(start[3], "WTT_END=");
+    assert_eq!(start[4], "WTT_NOTE=");
+
+    wtt.ok(&["session", "end", "--note", "Wrote the guide"]);
+    let end = dumped(&wtt, "post-end");
+    assert_eq!(end[0], format!("WTT_SESSION_ID={id}"));
+    assert_eq!(end[2], start[2]);
+    assert!(end[3].starts_with("WTT_END=20"), "{end:?}");
+    assert_eq!(end[4], "WTT_NOTE=Wrote the guide");
+}
+
+#[test]
+fn failing_pre_hook_aborts_the_command() {
+    let wtt = Wtt::new();
+    hook(
+        &wtt,
+        "pre-start",
+        "echo 'No untracked starts.' >&2; exit 3",
+        0o755,
+    );
+    hook(
+        &wtt,
+        "post-start",
+        "touch \"$(dirname \"$0\")/../post-ran\"",
+        0o755,
+    );
+
+    let stderr = wtt.fails(&["session", "start"]);
+    assert!(
+        stderr.contains("The pre-start hook stopped the command."),
+        "{stderr}"
+    );
+    assert!(stderr.contains("No untracked starts."), "{stderr}");
+    assert!(!wtt.db().exists() || !wtt.read_db().contains("start_at"));
+    assert!(!wtt.path("post-ran").exists());
+}
+
+#[test]
+fn failing_post_hook_only_warns() {
+    let wtt = Wtt::new();
+    hook(&wtt, "post-start", "exit 1", 0o755);
+
+    let output = wtt.run(&["session", "start"]);
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Warning: the post-start hook failed."),
+        "{stderr}"
+    );
+    assert!(wtt.read_db().contains("start_at"));
+}
+
+#[test]
+fn hooks_of_other_events_and_plain_files_are_not_run() {
+    let wtt = Wtt::new();
+    hook(&wtt, "pre-end", "exit 1", 0o755);
+    hook(&wtt, "pre-start", "exit 1", 0o644);
+
+    wtt.ok(&["session", "start"]);
+    assert!(wtt.read_db().contains("start_at"));
+    wtt.fails(&["session", "end"]);
+}
@@ -0,0 +1,34 @@
+mod common;
+
+use common::Wtt;
+
+#[test]
+fn import_reads_the_configured_date_format() {
+    let wtt = Wtt::new();
+    std::fs::write(
+        wtt.path("export.csv"),
+        "From,To,Project\n12.03.2024 10:00,12.03.2024 11:00,acme\nbad,12.03.2024 11:00,\n",
+    )
+    .unwrap();
+
+    let output = wtt.run(&[
+        "session",
+        "import",
+        "--csv",
+        "export.csv",
+        "--map",
+        "start=From,end=To,labels=Project",
+    ]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.contains("Imported 1 sessions, skipped 1."),
+        "{stdout}"
+    );
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("line 3"), "{stderr}");
+
+    let table = wtt.json(&["session", "table"]);
+    assert_eq!(table["rows"][0]["labels"], serde_json::json!(["acme"]));
+    assert_eq!(table["total"], 3600);
+}
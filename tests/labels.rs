@@ -0,0 +1,113 @@
+mod common;
+
+use common::{Wtt, session, store};
+
+#[test]
+fn label_list_of_an_empty_store() {
+    let wtt = Wtt::new();
+    assert_eq!(wtt.ok(&["label", "list"]), "No labels.\n");
+    assert_eq!(wtt.json(&["label", "list"]), serde_json::json!([]));
+}
+
+#[test]
+fn label_list_of_stored_labels() {
+    let wtt = Wtt::new();
+    wtt.write_db(&store(&[
+        session(
+            "1b3f0f6e-0000-4000-8000-000000000001",
+            100,
+            Some(200),
+            &["web", "acme"],
+        ),
+        session(
+            "1b3f0f6e-0000-4000-8000-000000000002",
+            300,
+            Some(400),
+            &["acme"],
+        ),
+    ]));
+    assert_eq!(wtt.ok(&["label", "list"]), "acme\nweb\n");
+}
+
+#[test]
+fn label_remove_needs_a_confirmation_without_a_terminal() {
+    let wtt = Wtt::new();
+    let db = store(&[session(
+        "1b3f0f6e-0000-4000-8000-000000000001",
+        100,
+        Some(200),
+        &["acme"],
+    )]);
+    wtt.write_db(&db);
+
+    let error = wtt.fails(&["label", "remove", "acme"]);
+    assert!(error.contains("--yes"), "{error}");
+    assert_eq!(wtt.read_db(), db);
+
+    wtt.ok(&["label", "remove", "acme", "--yes"]);
+    assert_eq!(wtt.ok(&["label", "list"]), "No labels.\n");
+}
+
+#[test]
+fn assume_yes_skips_the_confirmation() {
+    let wtt = Wtt::new();
+    wtt.write_db(&store(&[session(
+        "1b3f0f6e-0000-4000-8000-000000000001",
+        100,
+        Some(200),
+        &["acme"],
+    )]));
+    let output = wtt
+        .command(&["label", "remove", "acme"])
+        .env("WTT_ASSUME_YES", "1")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(wtt.ok(&["label", "list"]), "No labels.\n");
+}
+
+#[test]
+fn messages_follow_the_language() {
+    let wtt = Wtt::new();
+    let output = wtt
+        .command(&["label", "list"])
+        .env("WTT_LANG", "uk")
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "Немає міток.\n");
+
+    // A language without a catalog gets English.
+    let output = wtt
+        .command(&["label", "list"])
+        .env("WTT_LANG", "fr")
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "No labels.\n");
+}
+
+#[test]
+fn unknown_labels_get_suggestions() {
+    let wtt = Wtt::new();
+    wtt.write_db(&store(&[session(
+        "1b3f0f6e-0000-4000-8000-000000000001",
+        100,
+        Some(200),
+        &["acme", "website"],
+    )]));
+
+    let output = wtt.run(&["session", "table", "-l", "acmee"]);
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(
+        stderr,
+        "No session has the label acmee. Did you mean: acme?\n"
+    );
+
+    let output = wtt.run(&["label", "remove", "websiet"]);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Did you mean: website?"), "{stderr}");
+
+    let output = wtt.run(&["session", "table", "-l", "zzz"]);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(stderr, "No session has the label zzz.\n");
+}
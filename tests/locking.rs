@@ -0,0 +1,63 @@
+mod common;
+
+use std::{fs::File, thread};
+
+use common::Wtt;
+
+const SESSIONS_PER_WRITER: usize = 10;
+
+#[test]
+fn concurrent_writers_lose_nothing() {
+    let wtt = Wtt::new();
+    thread::scope(|scope| {
+        for writer in ["a", "b"] {
+            let wtt = &wtt;
+            scope.spawn(move || {
+                for i in 0..SESSIONS_PER_WRITER {
+                    let start = format!("{}m ago", 10 * (i + 1));
+                    let end = format!("{}m ago", 10 * (i + 1) - 5);
+                    wtt.ok(&[
+                        "session", "add", "--start", &start, "--end", &end, "-l", writer,
+                    ]);
+                }
+            });
+        }
+    });
+
+    let rows = wtt.json(&["session", "table"])["rows"].clone();
+    let rows = rows.as_array().unwrap();
+    assert_eq!(rows.len(), 2 * SESSIONS_PER_WRITER);
+    for writer in ["a", "b"] {
+        let count = rows.iter().filter(|x| x["labels"][0] == writer).count();
+        assert_eq!(count, SESSIONS_PER_WRITER, "{writer}");
+    }
+    let db: serde_json::Value = serde_json::from_str(&wtt.read_db()).unwrap();
+    let mut seqs: Vec<u64> = db["sessions"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|x| x["seq"].as_u64().unwrap())
+        .collect();
+    seqs.sort();
+    seqs.dedup();
+    assert_eq!(seqs.len(), 2 * SESSIONS_PER_WRITER);
+}
+
+#[test]
+fn held_lock_is_a_clear_error() {
+    let wtt = Wtt::new();
+    let lock_path = format!("{}.lock", wtt.db().display());
+    let lock = File::create(&lock_path).unwrap();
+    lock.lock().unwrap();
+
+    let stderr = wtt.fails(&["session", "start"]);
+    assert!(
+        stderr.contains("Another wtt command is changing the database"),
+        "{stderr}"
+    );
+    assert!(stderr.contains(&lock_path), "{stderr}");
+    assert!(!wtt.db().exists());
+
+    lock.unlock().unwrap();
+    wtt.ok(&["session", "start"]);
+}
@@ -0,0 +1,43 @@
+mod common;
+
+use common::Wtt;
+
+fn last_note(wtt: &Wtt) -> serde_json::Value {
+    wtt.json(&["session", "table"])["rows"][0]["note"].clone()
+}
+
+#[test]
+fn start_note_stop_keeps_the_note() {
+    let wtt = Wtt::new();
+    wtt.ok(&["start", "-l", "dev"]);
+    wtt.ok(&["note", "first thought"]);
+    wtt.ok(&["session", "note", "--append", "second"]);
+    wtt.ok(&["stop"]);
+    assert_eq!(last_note(&wtt), "first thought\nsecond");
+    assert_eq!(
+        wtt.json(&["session", "table", "--running"])["rows"],
+        serde_json::json!([])
+    );
+}
+
+#[test]
+fn stop_with_a_note_replaces_it() {
+    let wtt = Wtt::new();
+    wtt.ok(&["start", "-l", "dev"]);
+    wtt.ok(&["note", "draft"]);
+    wtt.ok(&["stop", "--note", "done"]);
+    assert_eq!(last_note(&wtt), "done");
+}
+
+#[test]
+fn required_note_can_be_written_while_running() {
+    let wtt = Wtt::new();
+    wtt.write_config("[behavior]\nrequire_note = true\n");
+    wtt.ok(&["start", "-l", "dev"]);
+    let stderr = wtt.fails(&["stop"]);
+    assert!(stderr.contains("note"), "{stderr}");
+
+    wtt.ok(&["note", "wrote it earlier"]);
+    wtt.ok(&["stop"]);
+    assert_eq!(last_note(&wtt), "wrote it earlier");
+}
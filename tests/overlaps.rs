@@ -0,0 +1,57 @@
+mod common;
+
+use common::Wtt;
+
+#[test]
+fn starting_next_to_a_running_session_warns() {
+    let wtt = Wtt::new();
+    wtt.ok(&["session", "start", "-l", "first"]);
+    let output = wtt.run(&["session", "start", "-l", "second", "--at", "1m ago"]);
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Warning: the session #1 [first] is still running"),
+        "{stderr}"
+    );
+    assert_eq!(
+        wtt.json(&["session", "table"])["rows"]
+            .as_array()
+            .unwrap()
+            .len(),
+        2
+    );
+}
+
+#[test]
+fn overlaps_are_listed() {
+    let wtt = Wtt::new();
+    assert_eq!(
+        wtt.ok(&["session", "overlaps"]).trim(),
+        "No sessions overlap."
+    );
+
+    wtt.ok(&[
+        "session", "add", "--start", "3h ago", "--end", "1h ago", "-l", "a",
+    ]);
+    wtt.ok(&[
+        "session", "add", "--start", "1h ago", "--end", "30m ago", "-l", "b",
+    ]);
+    assert_eq!(
+        wtt.ok(&["session", "overlaps"]).trim(),
+        "No sessions overlap."
+    );
+
+    let output = wtt.run(&[
+        "session", "add", "--start", "2h ago", "--end", "90m ago", "-l", "c",
+    ]);
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("overlaps with #1"), "{stderr}");
+
+    let stdout = wtt.ok(&["session", "overlaps"]);
+    assert!(stdout.contains("overlap by 30 minutes."), "{stdout}");
+    assert!(
+        stdout.contains("#1 [a]") && stdout.contains("#3 [c]"),
+        "{stdout}"
+    );
+}
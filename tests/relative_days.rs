@@ -0,0 +1,44 @@
+mod common;
+
+use common::{Wtt, session, store};
+
+const OLD: &str = "aaaa0000-0000-4000-8000-000000000001";
+const RECENT: &str = "bbbb0000-0000-4000-8000-000000000002";
+
+fn now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64
+}
+
+#[test]
+fn lock_and_delete_take_relative_days() {
+    let wtt = Wtt::new();
+    let now = now();
+    wtt.write_db(&store(&[
+        session(
+            OLD,
+            now - 10 * 86400,
+            Some(now - 10 * 86400 + 3600),
+            &["acme"],
+        ),
+        session(RECENT, now - 60, Some(now - 30), &["acme"]),
+    ]));
+
+    let stdout = wtt.ok(&["session", "lock", "--before", "-1d"]);
+    assert!(stdout.contains("Locked 1 sessions."), "{stdout}");
+    let stdout = wtt.ok(&["session", "unlock", "--before", "-1w"]);
+    assert!(stdout.contains("Unlocked 1 sessions."), "{stdout}");
+
+    let stdout = wtt.ok(&["session", "delete", "--older-than", "-1d", "--yes"]);
+    assert!(stdout.contains("Deleted 1 sessions."), "{stdout}");
+    let rows = wtt.json(&["session", "table"])["rows"].clone();
+    let ids: Vec<&str> = rows
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|x| x["id"].as_str().unwrap())
+        .collect();
+    assert_eq!(ids, vec![RECENT]);
+}
@@ -0,0 +1,167 @@
+mod common;
+
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpStream,
+    process::{Child, ChildStdout, Stdio},
+};
+
+use common::Wtt;
+
+/// A running `wtt serve` on a free port, killed when dropped.
+struct Server {
+    child: Child,
+    address: String,
+    /// Kept open, so the server doesn't fail on printing.
+    _stdout: BufReader<ChildStdout>,
+}
+
+impl Server {
+    fn start(wtt: &Wtt, token: Option<&str>) -> Self {
+        let mut command = wtt.command(&["serve", "--listen", "127.0.0.1:0"]);
+        if let Some(token) = token {
+            command.env("WTT_SERVE_TOKEN", token);
+        }
+        let mut child = command.stdout(Stdio::piped()).spawn().unwrap();
+        let mut stdout = BufReader::new(child.stdout.take().unwrap());
+        let mut line = String::new();
+        stdout.read_line(&mut line).unwrap();
+        let address = line
+            .trim()
+            .strip_prefix("Listening on http://")
+            .unwrap_or_else(|| panic!("unexpected output: {line}"))
+            .to_string();
+        Self {
+            child,
+            address,
+            _stdout: stdout,
+        }
+    }
+
+    /// Send a request and return the status code and the JSON body.
+    fn request(
+        &self,
+        method: &str,
+        path: &str,
+        body: Option<serde_json::Value>,
+        token: Option<&str>,
+    ) -> (u16, serde_json::Value) {
+        let body = body.map(|x| x.to_string()).unwrap_or_default();
+        let mut request = format!(
+            "{method} {path} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\nContent-Length: {}\r\n",
+            self.address,
+            body.len()
+        );
+        if let Some(token) = token {
+            request.push_str(&format!("Authorization: Bearer {token}\r\n"));
+        }
+        request.push_str("\r\n");
+        request.push_str(&body);
+
+        let mut stream = TcpStream::connect(&self.address).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status = head.split(' ').nth(1).unwrap().parse().unwrap();
+        (status, serde_json::from_str(body).unwrap())
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[test]
+fn every_route() {
+    let wtt = Wtt::new();
+    let server = Server::start(&wtt, None);
+
+    let (status, body) = server.request("GET", "/status", None, None);
+    assert_eq!(status, 200);
+    assert_eq!(body["running"], 0);
+
+    let (status, session) = server.request(
+        "POST",
+        "/sessions/start",
+        Some(serde_json::json!({ "labels": ["acme", "api"] })),
+        None,
+    );
+    assert_eq!(status, 200, "{session}");
+    let id = session["id"].as_str().unwrap();
+    assert_eq!(session["labels"], serde_json::json!(["acme", "api"]));
+
+    let (status, body) = server.request("GET", "/status", None, None);
+    assert_eq!(status, 200);
+    assert_eq!(body["running"], 1);
+    assert_eq!(body["session"]["id"], id);
+
+    let (status, body) = server.request(
+        "PATCH",
+        &format!("/sessions/{id}/note"),
+        Some(serde_json::json!({ "note": "Draft" })),
+        None,
+    );
+    assert_eq!(status, 200, "{body}");
+    assert_eq!(body["note"], "Draft");
+
+    let (status, body) = server.request("POST", &format!("/sessions/{id}/end"), None, None);
+    assert_eq!(status, 200, "{body}");
+    assert!(body["end_at"].is_number());
+    assert_eq!(body["note"], "Draft");
+
+    let (status, body) = server.request("POST", &format!("/sessions/{id}/end"), None, None);
+    assert_eq!(status, 409, "{body}");
+
+    let (status, body) = server.request("GET", "/sessions?labels=acme", None, None);
+    assert_eq!(status, 200);
+    assert_eq!(body["rows"].as_array().unwrap().len(), 1);
+    assert_eq!(body["rows"][0]["id"], id);
+    let (_, body) = server.request("GET", "/sessions?labels=other", None, None);
+    assert!(body["rows"].as_array().unwrap().is_empty());
+
+    let (status, body) = server.request("GET", "/labels", None, None);
+    assert_eq!(status, 200);
+    assert_eq!(body, serde_json::json!(["acme", "api"]));
+
+    // The CLI sees what the server saved.
+    assert_eq!(wtt.json(&["session", "table"])["rows"][0]["note"], "Draft");
+}
+
+#[test]
+fn errors_are_json() {
+    let wtt = Wtt::new();
+    let server = Server::start(&wtt, None);
+
+    let (status, body) = server.request("GET", "/nothing", None, None);
+    assert_eq!(status, 404);
+    assert!(body["error"].is_string());
+    let (status, _) = server.request("GET", "/sessions?last=0", None, None);
+    assert_eq!(status, 400);
+    let (status, _) = server.request("POST", "/sessions/unknown/end", None, None);
+    assert_eq!(status, 404);
+}
+
+#[test]
+fn token_gates_the_changes() {
+    let wtt = Wtt::new();
+    let server = Server::start(&wtt, Some("secret"));
+
+    let (status, body) = server.request("POST", "/sessions/start", None, None);
+    assert_eq!(status, 401);
+    assert!(body["error"].as_str().unwrap().contains("bearer token"));
+    let (status, _) = server.request("POST", "/sessions/start", None, Some("wrong"));
+    assert_eq!(status, 401);
+    assert!(!wtt.db().exists() || !wtt.read_db().contains("start_at"));
+
+    // Reading needs no token.
+    let (status, _) = server.request("GET", "/status", None, None);
+    assert_eq!(status, 200);
+
+    let (status, body) = server.request("POST", "/sessions/start", None, Some("secret"));
+    assert_eq!(status, 200, "{body}");
+    assert!(wtt.read_db().contains("start_at"));
+}
@@ -0,0 +1,34 @@
+mod common;
+
+use common::Wtt;
+
+#[test]
+fn unknown_fields_survive_a_save() {
+    let wtt = Wtt::new();
+    wtt.write_db(
+        &serde_json::json!({
+            "sessions": [{
+                "id": "1b3f0f6e-0000-4000-8000-000000000001",
+                "start_at": 1_700_000_000,
+                "end_at": 1_700_003_600,
+                "note": null,
+                "labels": ["acme"],
+                "mood": "focused",
+                "meta": { "device": "laptop" },
+            }],
+            "version": 7,
+            "devices": ["laptop", "desktop"],
+        })
+        .to_string(),
+    );
+
+    wtt.ok(&["session", "note", "--id", "1b3f", "reviewed"]);
+
+    let saved: serde_json::Value = serde_json::from_str(&wtt.read_db()).unwrap();
+    assert_eq!(saved["version"], 7);
+    assert_eq!(saved["devices"], serde_json::json!(["laptop", "desktop"]));
+    let session = &saved["sessions"][0];
+    assert_eq!(session["note"], "reviewed");
+    assert_eq!(session["mood"], "focused");
+    assert_eq!(session["meta"], serde_json::json!({ "device": "laptop" }));
+}
@@ -0,0 +1,35 @@
+mod common;
+
+use common::{Wtt, session, store};
+
+#[test]
+fn label_summary_is_sorted_by_duration() {
+    let wtt = Wtt::new();
+    wtt.write_db(&store(&[
+        session("a", 0, Some(3600), &["x", "y"]),
+        session("b", 4000, Some(5800), &["y", "z"]),
+        session("c", 6000, Some(6600), &["x"]),
+    ]));
+
+    let summary = wtt.json(&["session", "summary", "--by", "label"]);
+    let keys: Vec<&str> = summary["subtotals"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|x| x["key"].as_str().unwrap())
+        .collect();
+    assert_eq!(keys, vec!["y", "x", "z"]);
+    assert_eq!(summary["subtotals"][0]["duration"], 5400);
+    assert_eq!(summary["subtotals"][0]["count"], 2);
+    assert_eq!(summary["total"], 6000);
+
+    let split = wtt.json(&["session", "summary", "--by", "label", "--split"]);
+    let durations: Vec<u64> = split["subtotals"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|x| x["duration"].as_u64().unwrap())
+        .collect();
+    assert_eq!(durations, vec![2700, 2400, 900]);
+    assert_eq!(durations.iter().sum::<u64>(), 6000);
+}
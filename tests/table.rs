@@ -0,0 +1,93 @@
+mod common;
+
+use common::{Wtt, session, store};
+
+/// The table without the terminal colors.
+fn plain(text: &str) -> String {
+    let mut result = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            chars.by_ref().find(|x| *x == 'm');
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+fn header(wtt: &Wtt, args: &[&str], columns_env: Option<&str>) -> Vec<String> {
+    let mut command = wtt.command(args);
+    if let Some(columns) = columns_env {
+        command.env("WTT_TABLE_COLUMNS", columns);
+    }
+    let output = command.output().unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let table = plain(&String::from_utf8(output.stdout).unwrap());
+    table
+        .lines()
+        .nth(1)
+        .unwrap()
+        .split('|')
+        .map(|x| x.trim().to_string())
+        .filter(|x| !x.is_empty())
+        .collect()
+}
+
+fn one_session(wtt: &Wtt) {
+    wtt.write_db(&store(&[session(
+        "1b3f0f6e-0000-4000-8000-000000000001",
+        1_700_000_000,
+        Some(1_700_003_600),
+        &["acme"],
+    )]));
+}
+
+#[test]
+fn table_columns_from_the_env_and_the_flag() {
+    let wtt = Wtt::new();
+    one_session(&wtt);
+
+    assert_eq!(
+        header(&wtt, &["session", "table"], Some("labels,duration")),
+        vec!["Labels", "Duration"]
+    );
+    // The flag wins over the env.
+    assert_eq!(
+        header(
+            &wtt,
+            &["session", "table", "--columns", "number,note"],
+            Some("labels,duration")
+        ),
+        vec!["#", "Note"]
+    );
+}
+
+#[test]
+fn table_columns_from_the_config_file() {
+    let wtt = Wtt::new();
+    one_session(&wtt);
+    wtt.write_config("[display]\ntable_columns = [\"id\", \"labels\"]\n");
+    assert_eq!(
+        header(&wtt, &["session", "table"], None),
+        vec!["Id", "Labels"]
+    );
+}
+
+#[test]
+fn unknown_table_column_fails_at_startup() {
+    let wtt = Wtt::new();
+    let output = wtt
+        .command(&["label", "list"])
+        .env("WTT_TABLE_COLUMNS", "labels,bogus")
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("WTT_TABLE_COLUMNS"), "{stderr}");
+    assert!(stderr.contains("one of number, id,"), "{stderr}");
+}
@@ -0,0 +1,146 @@
+mod common;
+
+use std::{
+    net::TcpListener,
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use common::Wtt;
+
+struct Received {
+    url: String,
+    signature: Option<String>,
+    body: String,
+}
+
+/// A server which answers every request with 200 and hands it over to the test.
+fn mock_server() -> (String, mpsc::Receiver<Received>) {
+    let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+    let address = format!("http://{}", server.server_addr().to_ip().unwrap());
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for mut request in server.incoming_requests() {
+            let mut body = String::new();
+            request.as_reader().read_to_string(&mut body).unwrap();
+            let signature = request
+                .headers()
+                .iter()
+                .find(|x| x.field.equiv("X-Wtt-Signature"))
+                .map(|x| x.value.to_string());
+            let url = request.url().to_string();
+            let _ = request.respond(tiny_http::Response::empty(200));
+            if sender
+                .send(Received {
+                    url,
+                    signature,
+                    body,
+                })
+                .is_err()
+            {
+                break;
+            }
+        }
+    });
+    (address, receiver)
+}
+
+fn receive(receiver: &mpsc::Receiver<Received>) -> Received {
+    receiver.recv_timeout(Duration::from_secs(5)).unwrap()
+}
+
+#[test]
+fn start_and_end_are_posted() {
+    let wtt = Wtt::new();
+    let (address, receiver) = mock_server();
+    let run = |args: &[&str]| {
+        let output = wtt
+            .command(args)
+            .env("WTT_WEBHOOK_START_URL", format!("{address}/start"))
+            .env("WTT_WEBHOOK_END_URL", format!("{address}/end"))
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+    };
+
+    run(&["session", "start", "-l", "acme"]);
+    let start = receive(&receiver);
+    assert_eq!(start.url, "/start");
+    assert_eq!(start.signature, None);
+    let payload: serde_json::Value = serde_json::from_str(&start.body).unwrap();
+    assert_eq!(payload["event"], "start");
+    assert_eq!(payload["labels"], serde_json::json!(["acme"]));
+    assert!(payload["end_at"].is_null());
+
+    run(&["session", "end", "--note", "done"]);
+    let end = receive(&receiver);
+    assert_eq!(end.url, "/end");
+    let end_payload: serde_json::Value = serde_json::from_str(&end.body).unwrap();
+    assert_eq!(end_payload["event"], "end");
+    assert_eq!(end_payload["id"], payload["id"]);
+    assert_eq!(end_payload["note"], "done");
+    assert!(end_payload["end_at"].is_i64());
+}
+
+#[test]
+fn payload_is_signed_with_the_secret() {
+    let wtt = Wtt::new();
+    let (address, receiver) = mock_server();
+    let output = wtt
+        .command(&["session", "start"])
+        .env("WTT_WEBHOOK_START_URL", &address)
+        .env("WTT_WEBHOOK_SECRET", "s3cret")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let request = receive(&receiver);
+    let mac = hmac_sha256::HMAC::mac(request.body.as_bytes(), b"s3cret");
+    let hex: String = mac.iter().map(|x| format!("{x:02x}")).collect();
+    assert_eq!(request.signature, Some(format!("sha256={hex}")));
+}
+
+#[test]
+fn unreachable_webhook_is_only_a_warning() {
+    let wtt = Wtt::new();
+    // Nothing listens on the port once the listener is dropped.
+    let port = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let output = wtt
+        .command(&["session", "start"])
+        .env("WTT_WEBHOOK_START_URL", format!("http://127.0.0.1:{port}"))
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Warning: the webhook"), "{stderr}");
+    assert!(wtt.read_db().contains("\"end_at\":null"));
+}
+
+#[test]
+fn silent_webhook_doesnt_hold_the_command_up() {
+    let wtt = Wtt::new();
+    // Accepts the connection but never answers.
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let _accepting = thread::spawn(move || {
+        let _connections: Vec<_> = listener.incoming().collect();
+    });
+
+    let started = Instant::now();
+    let output = wtt
+        .command(&["session", "start"])
+        .env("WTT_WEBHOOK_START_URL", format!("http://{address}"))
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(
+        started.elapsed() < Duration::from_secs(5),
+        "{:?}",
+        started.elapsed()
+    );
+}